@@ -1,2 +1,9 @@
 pub mod add;
+pub mod calendar;
 pub mod edit;
+pub mod ids;
+pub mod notify;
+pub mod output;
+pub mod profile;
+pub mod reminder;
+pub mod table;
@@ -1,22 +1,26 @@
 use chrono::NaiveDate;
+use chrono_tz::Tz;
 use edit;
 
+use prm::db::loader::LoadContext;
 use prm::db_interface::DbOperations;
 use prm::entities::activity::Activity;
+use prm::entities::datetime::Moment;
 use prm::entities::note::{Note, NOTE_TEMPLATE};
 use prm::entities::person::{ContactInfo, Person, PERSON_TEMPLATE};
-use prm::entities::reminder::{
-    ParseReminderFromEditorData, RecurringType, Reminder, REMINDER_TEMPLATE,
-};
+use prm::entities::recurrence;
+use prm::entities::reminder::{ParseReminderFromEditorData, Reminder, REMINDER_TEMPLATE};
 use prm::{
-    AddSnafu, BirthdayParseSnafu, CliError, DateParseSnafu, EditorParseSnafu, EntitySnafu,
-    MissingFieldSnafu, NotFoundSnafu, RecurringTypeParseSnafu, TemplateSnafu,
+    AddSnafu, BirthdayParseSnafu, CliError, DatabaseSnafu, DateTimeParseSnafu, EditorParseSnafu,
+    EditorRunSnafu, EntitySnafu, MissingFieldSnafu, NotFoundSnafu, RecurringTypeParseSnafu,
+    TemplateSnafu,
 };
 use rusqlite::Connection;
+use snafu::ResultExt;
 
 extern crate strfmt;
 use prm::helpers::{
-    get_activity_type, get_contact_info, parse_from_str_md, parse_from_str_ymd,
+    get_activity_type, get_contact_info, parse_dates, parse_from_str_md, parse_from_str_ymd,
     unwrap_arg_or_empty_string, ActivityVars,
 };
 use std::collections::HashMap;
@@ -61,13 +65,7 @@ pub fn person(
         };
         let edited = match edit::edit(person_str) {
             Ok(edited) => edited,
-            Err(e) => {
-                return EditorParseSnafu {
-                    entity: "Person",
-                    message: format!("Error editing person: {:#?}", e),
-                }
-                .fail()
-            }
+            Err(e) => return Err(e).context(EditorRunSnafu { entity: "Person" }),
         };
         let (n, b, c, a, r) = match Person::parse_from_editor(edited.as_str()) {
             Ok(d) => (
@@ -77,13 +75,7 @@ pub fn person(
                 d.activities,
                 d.reminders,
             ),
-            Err(e) => {
-                return EditorParseSnafu {
-                    entity: "Person",
-                    message: format!("Error editing person: {:#?}", e),
-                }
-                .fail()
-            }
+            Err(e) => return Err(Box::new(e)).context(EditorParseSnafu { entity: "Person" }),
         };
         name_str = n;
         birthday_str = b;
@@ -145,7 +137,7 @@ pub fn person(
 
     let mut activities: Vec<Activity> = vec![];
     for id in activity_ids {
-        match Activity::get_by_id(conn, id) {
+        match Activity::get_by_id(conn, &mut LoadContext::new(true), id) {
             Ok(entity) => match entity {
                 Some(prm::entities::Entities::Activity(activity)) => activities.push(activity),
                 _ => {
@@ -168,7 +160,7 @@ pub fn person(
 
     let mut reminders: Vec<Reminder> = vec![];
     for i in reminder_ids {
-        match Reminder::get_by_id(conn, i) {
+        match Reminder::get_by_id(conn, &mut LoadContext::new(true), i) {
             Ok(entity) => match entity {
                 Some(prm::entities::Entities::Reminder(reminder)) => reminders.push(reminder),
                 _ => {
@@ -200,7 +192,7 @@ pub fn person(
     );
     match person.add(conn) {
         Ok(_) => println!("{} added successfully", person),
-        Err(_) => return AddSnafu { entity: "Person" }.fail(),
+        Err(e) => return Err(e).context(AddSnafu { entity: "Person" }),
     };
     Ok(person)
 }
@@ -212,6 +204,7 @@ pub fn activity(
     date: Option<String>,
     content: Option<String>,
     people: Vec<String>,
+    timezone: Tz,
 ) -> Result<Activity, CliError> {
     let mut vars = HashMap::new();
     vars.insert("name".to_string(), unwrap_arg_or_empty_string(name.clone()));
@@ -237,16 +230,7 @@ pub fn activity(
             .iter()
             .any(Option::is_none)
     {
-        match prm::editor::populate_activity_vars(vars) {
-            Ok(activity_vars) => activity_vars,
-            Err(err) => {
-                return EditorParseSnafu {
-                    entity: err.to_string(),
-                    message: format!("Error editing activity: {:#?}", err),
-                }
-                .fail()
-            }
-        }
+        prm::editor::populate_activity_vars(vars)?
     } else {
         let entity = "Activity";
         let name = match name {
@@ -300,49 +284,50 @@ pub fn activity(
 
     let activity_type = get_activity_type(activity_vars.activity_type.clone())?;
 
-    let date_obj = match parse_from_str_ymd(activity_vars.date.as_str()) {
-        Ok(date) => date,
-        Err(_) => {
-            return DateParseSnafu {
-                date: activity_vars.date.clone(),
-            }
-            .fail()
-        }
-    };
+    let moment = Moment::parse(&activity_vars.date, timezone).context(DateTimeParseSnafu {
+        date: &activity_vars.date,
+    })?;
 
-    let people = match prm::db::db_helpers::people::get_by_names(conn, activity_vars.people) {
+    let people = match prm::db::db_helpers::people::get_by_names(
+        conn,
+        &mut LoadContext::new(true),
+        activity_vars.people,
+    ) {
         Ok(people) => people,
         Err(e) => {
-            return EntitySnafu {
+            return Err(e).context(DatabaseSnafu {
                 entity: String::from("Person"),
-                message: format!("Error fetching person: {:#?}", e),
-            }
-            .fail()
+                action: "fetching",
+            })
         }
     };
 
-    let activity = Activity::new(
+    let mut activity = Activity::new(
         0,
         activity_vars.name,
         activity_type,
-        date_obj,
+        moment.date,
         activity_vars.content,
         people,
     );
+    activity.set_moment(moment);
     match activity.add(conn) {
         Ok(_) => println!("{:#?} added successfully", activity),
-        Err(_) => return AddSnafu { entity: "Activity" }.fail(),
+        Err(e) => return Err(e).context(AddSnafu { entity: "Activity" }),
     };
     Ok(activity)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn reminder(
     conn: &Connection,
     name: Option<String>,
     date: Option<String>,
     recurring: Option<String>,
+    exdates: Vec<String>,
     description: Option<String>,
     mut people: Vec<String>,
+    timezone: Tz,
 ) -> Result<Reminder, CliError> {
     let mut name_string: String = String::new();
     let mut date_string: String = String::new();
@@ -386,13 +371,7 @@ pub fn reminder(
         };
         let edited = match edit::edit(reminder_str) {
             Ok(edited) => edited,
-            Err(e) => {
-                return EditorParseSnafu {
-                    entity: "Reminder",
-                    message: format!("Error editing reminder: {:#?}", e),
-                }
-                .fail()
-            }
+            Err(e) => return Err(e).context(EditorRunSnafu { entity: "Reminder" }),
         };
         let (n, da, r, de, p) = match Reminder::parse_from_editor(edited.as_str()) {
             Ok(ParseReminderFromEditorData {
@@ -402,13 +381,7 @@ pub fn reminder(
                 description,
                 people,
             }) => (name, date, recurring_type, description, people),
-            Err(e) => {
-                return EditorParseSnafu {
-                    entity: "Reminder",
-                    message: format!("Error editing reminder: {:#?}", e),
-                }
-                .fail()
-            }
+            Err(e) => return Err(Box::new(e)).context(EditorParseSnafu { entity: "Reminder" }),
         };
         name_string = n;
         date_string = match da {
@@ -478,57 +451,47 @@ pub fn reminder(
         description_string = description.unwrap_or("".to_string());
     }
 
-    let recurring_type_str = recurring_type_string;
-    let recurring_type = match recurring_type_str.as_str() {
-        "daily" => RecurringType::Daily,
-        "weekly" => RecurringType::Weekly,
-        "fortnightly" => RecurringType::Fortnightly,
-        "monthly" => RecurringType::Monthly,
-        "quarterly" => RecurringType::Quarterly,
-        "biannual" => RecurringType::Biannual,
-        "yearly" => RecurringType::Yearly,
-        "onetime" => RecurringType::OneTime,
-        _ => {
-            return RecurringTypeParseSnafu {
-                recurring_type: recurring_type_str.clone(),
-            }
-            .fail()
-        }
-    };
+    let moment =
+        Moment::parse(&date_string, timezone).context(DateTimeParseSnafu { date: &date_string })?;
 
-    let date_obj = match parse_from_str_ymd(date_string.as_str()) {
-        Ok(date) => date,
-        Err(_) => {
-            return DateParseSnafu {
-                date: date_string.clone(),
-            }
-            .fail()
-        }
-    };
+    let recurring = recurrence::parse(&recurring_type_string, moment.date).context(
+        RecurringTypeParseSnafu {
+            recurring_type: recurring_type_string.clone(),
+        },
+    )?;
+    let exdates = parse_dates(exdates)?;
 
-    let people = match prm::db::db_helpers::people::get_by_names(conn, people) {
+    let people = match prm::db::db_helpers::people::get_by_names(
+        conn,
+        &mut LoadContext::new(true),
+        people,
+    ) {
         Ok(people) => people,
         Err(e) => {
-            return EntitySnafu {
+            return Err(e).context(DatabaseSnafu {
                 entity: String::from("people"),
-                message: format!("Error fetching people: {:#?}", e),
-            }
-            .fail()
+                action: "fetching",
+            })
         }
     };
 
-    let reminder = Reminder::new(
-        0,
-        name_string,
-        date_obj,
-        Some(description_string),
-        recurring_type,
-        people,
-    );
+    let reminder = Reminder {
+        time: moment.time,
+        timezone: moment.timezone,
+        exdates,
+        ..Reminder::new(
+            0,
+            name_string,
+            moment.date,
+            Some(description_string),
+            recurring,
+            people,
+        )
+    };
     println!("Reminder: {:#?}", reminder);
     match reminder.add(conn) {
         Ok(_) => println!("{:#?} added successfully", reminder),
-        Err(_) => return AddSnafu { entity: "Reminder" }.fail(),
+        Err(e) => return Err(e).context(AddSnafu { entity: "Reminder" }),
     };
     Ok(reminder)
 }
@@ -537,6 +500,7 @@ pub fn note(
     conn: &Connection,
     content: Option<String>,
     people: Vec<String>,
+    timezone: Tz,
 ) -> Result<Note, CliError> {
     let mut date_string: String = String::new();
     let mut content_string: String = String::new();
@@ -563,53 +527,35 @@ pub fn note(
         };
         let edited = match edit::edit(note_str) {
             Ok(edited) => edited,
-            Err(e) => {
-                return EditorParseSnafu {
-                    entity,
-                    message: format!("Error editing entity: {:#?}", e),
-                }
-                .fail()
-            }
+            Err(e) => return Err(e).context(EditorRunSnafu { entity }),
         };
         let (d, c, p) = match Note::parse_from_editor(edited.as_str()) {
             Ok((date, content, people)) => (date, content, people),
-            Err(e) => {
-                return EditorParseSnafu {
-                    entity,
-                    message: format!("Error editing entity: {:#?}", e),
-                }
-                .fail()
-            }
+            Err(e) => return Err(Box::new(e)).context(EditorParseSnafu { entity }),
         };
         date_string = d;
         content_string = c;
-        people_vec = match prm::db::db_helpers::people::get_by_names(conn, p) {
-            Ok(people) => people,
-            Err(e) => {
-                return EntitySnafu {
-                    entity: String::from("people"),
-                    message: format!("Error fetching people: {:#?}", e),
+        people_vec =
+            match prm::db::db_helpers::people::get_by_names(conn, &mut LoadContext::new(true), p) {
+                Ok(people) => people,
+                Err(e) => {
+                    return Err(e).context(DatabaseSnafu {
+                        entity: String::from("people"),
+                        action: "fetching",
+                    })
                 }
-                .fail()
-            }
-        };
+            };
     }
 
-    let date = match parse_from_str_ymd(date_string.as_str()) {
-        Ok(date) => date,
-        Err(_) => {
-            return DateParseSnafu {
-                date: date_string.clone(),
-            }
-            .fail()
-        }
-    };
+    let moment =
+        Moment::parse(&date_string, timezone).context(DateTimeParseSnafu { date: &date_string })?;
 
-    let note = Note::new(0, date, content_string, people_vec);
+    let mut note = Note::new(0, moment.date, content_string, people_vec);
+    note.set_moment(moment);
     println!("Note: {:#?}", note);
     match note.add(conn) {
         Ok(_) => println!("{:#?} added successfully", note),
-        Err(_) => return AddSnafu { entity }.fail(),
+        Err(e) => return Err(e).context(AddSnafu { entity }),
     };
     Ok(note)
 }
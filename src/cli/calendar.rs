@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone as _, Utc,
+};
+use chrono_tz::{OffsetComponents, Tz};
+use ics::components::Parameter;
+use ics::parameters::{TzIDParam, Value};
+use ics::properties::{DtStart, Due, ExDate, RRule as IcsRRule};
+use ics::{Daylight, Standard, TimeZone};
+use prm::entities::datetime::{localize, DateTimeError, Moment, OutOfRangeSnafu};
+use prm::entities::recurrence::RRule;
+use snafu::OptionExt;
+
+// How a moment's time is written: as a local time in its timezone, which
+// needs a TZID, or as a whole day
+fn value(moment: &Moment, date: NaiveDate) -> String {
+    match moment.time {
+        Some(time) => date.and_time(time).format("%Y%m%dT%H%M%S").to_string(),
+        None => date.format("%Y%m%d").to_string(),
+    }
+}
+
+fn parameter(moment: &Moment) -> Parameter<'static> {
+    match moment.time {
+        Some(_) => TzIDParam::new(moment.timezone().name()).into(),
+        None => Value::DATE.into(),
+    }
+}
+
+// DTSTART on `date` at the time of `moment`
+pub fn dtstart(moment: &Moment, date: NaiveDate) -> DtStart<'static> {
+    let mut property = DtStart::new(value(moment, date));
+    property.add(parameter(moment));
+    property
+}
+
+pub fn due(moment: &Moment, date: NaiveDate) -> Due<'static> {
+    let mut property = Due::new(value(moment, date));
+    property.add(parameter(moment));
+    property
+}
+
+pub fn exdate(moment: &Moment, date: NaiveDate) -> ExDate<'static> {
+    let mut property = ExDate::new(value(moment, date));
+    property.add(parameter(moment));
+    property
+}
+
+// The rule of a reminder starting at `moment`. With a local start time UNTIL
+// has to be given in UTC, at the time of the last occurrence.
+pub fn rrule(rule: &RRule, moment: &Moment) -> IcsRRule<'static> {
+    match (rule.until, moment.time) {
+        (Some(until), Some(time)) => {
+            let until_utc = localize(moment.timezone(), until.and_time(time)).with_timezone(&Utc);
+            let rule = RRule {
+                until: None,
+                ..rule.clone()
+            };
+            IcsRRule::new(format!(
+                "{};UNTIL={}",
+                rule,
+                until_utc.format("%Y%m%dT%H%M%SZ")
+            ))
+        }
+        _ => IcsRRule::new(rule.to_string()),
+    }
+}
+
+// The timezones used by timed moments, with the first and last year they
+// are used in, to be described by VTIMEZONE components
+#[derive(Debug, Default)]
+pub struct Timezones(BTreeMap<&'static str, (Tz, i32, i32)>);
+
+impl Timezones {
+    // Records that `moment`'s timezone is used on `date`
+    pub fn add(&mut self, moment: &Moment, date: NaiveDate) {
+        if moment.time.is_none() {
+            return;
+        }
+        let timezone = moment.timezone();
+        let year = date.year();
+        let entry = self
+            .0
+            .entry(timezone.name())
+            .or_insert((timezone, year, year));
+        entry.1 = entry.1.min(year);
+        entry.2 = entry.2.max(year);
+    }
+
+    pub fn components(&self) -> Result<Vec<TimeZone<'static>>, DateTimeError> {
+        self.0
+            .values()
+            .map(|(timezone, first, last)| vtimezone(*timezone, *first, *last))
+            .collect()
+    }
+}
+
+fn offset(seconds: i64) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if seconds == 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+}
+
+fn utc(at: i64, year: i32) -> Result<NaiveDateTime, DateTimeError> {
+    DateTime::from_timestamp(at, 0)
+        .map(|at| at.naive_utc())
+        .context(OutOfRangeSnafu { year })
+}
+
+fn year_start(year: i32) -> Result<i64, DateTimeError> {
+    Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0)
+        .single()
+        .map(|start| start.timestamp())
+        .context(OutOfRangeSnafu { year })
+}
+
+// The offset of `timezone` at `at` and whether it is daylight saving time
+fn utc_offset(timezone: Tz, at: i64, year: i32) -> Result<(i64, bool), DateTimeError> {
+    let offset = timezone.offset_from_utc_datetime(&utc(at, year)?);
+    Ok((
+        (offset.base_utc_offset() + offset.dst_offset()).num_seconds(),
+        !offset.dst_offset().is_zero(),
+    ))
+}
+
+// The weekday of `date` as the nth, or the last, of its month
+fn byday(date: NaiveDate) -> String {
+    let weekday = &date.weekday().to_string()[..2];
+    let last = (date + Duration::days(7)).month() != date.month();
+    if last {
+        format!("-1{}", weekday.to_uppercase())
+    } else {
+        format!("{}{}", (date.day() - 1) / 7 + 1, weekday.to_uppercase())
+    }
+}
+
+// A change of offset, at a local time before the change
+#[derive(Debug, Clone)]
+struct Transition {
+    local: NaiveDateTime,
+    from: i64,
+    to: i64,
+    daylight: bool,
+}
+
+impl Transition {
+    fn utc(&self) -> NaiveDateTime {
+        self.local - Duration::seconds(self.from)
+    }
+
+    // The yearly rule the transition follows
+    fn rule(&self) -> String {
+        format!(
+            "FREQ=YEARLY;BYMONTH={};BYDAY={}",
+            self.local.month(),
+            byday(self.local.date())
+        )
+    }
+
+    // Transitions in consecutive years with the same key are one rule
+    fn key(&self) -> (String, NaiveTime, i64, i64, bool) {
+        (
+            self.rule(),
+            self.local.time(),
+            self.from,
+            self.to,
+            self.daylight,
+        )
+    }
+}
+
+// The offset changes of `timezone` in `year`
+fn transitions(timezone: Tz, year: i32) -> Result<Vec<Transition>, DateTimeError> {
+    let (start, end) = (year_start(year)?, year_start(year + 1)?);
+    let mut transitions = vec![];
+    let mut day = start;
+    while day < end {
+        let (from, _) = utc_offset(timezone, day, year)?;
+        let (to, daylight) = utc_offset(timezone, day + 86400, year)?;
+        if from != to {
+            // The second the offset changes
+            let (mut before, mut after) = (day, day + 86400);
+            while after - before > 1 {
+                let middle = (before + after) / 2;
+                if utc_offset(timezone, middle, year)?.0 == from {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            transitions.push(Transition {
+                local: utc(after + from, year)?,
+                from,
+                to,
+                daylight,
+            });
+        }
+        day += 86400;
+    }
+    Ok(transitions)
+}
+
+// A run of transitions following the same rule in consecutive years
+struct Observance {
+    first: Transition,
+    last: Transition,
+    year: i32,
+}
+
+// A VTIMEZONE with the offset changes of `timezone` from `first` to `last`,
+// starting with the year before so that times before the first change in
+// `first` are covered too. Each rule the zone followed in those years is an
+// observance until its last change; the rules in use in `last` repeat yearly
+// from then on.
+pub fn vtimezone(timezone: Tz, first: i32, last: i32) -> Result<TimeZone<'static>, DateTimeError> {
+    let mut observances: Vec<Observance> = vec![];
+    for year in first - 1..=last {
+        for transition in transitions(timezone, year)? {
+            match observances
+                .iter_mut()
+                .find(|o| o.year == year - 1 && o.last.key() == transition.key())
+            {
+                Some(observance) => {
+                    observance.last = transition;
+                    observance.year = year;
+                }
+                None => observances.push(Observance {
+                    first: transition.clone(),
+                    last: transition,
+                    year,
+                }),
+            }
+        }
+    }
+
+    let mut standards = vec![];
+    let mut daylights = vec![];
+    for observance in observances {
+        let Observance {
+            first,
+            last: until,
+            year,
+        } = observance;
+        let dtstart = first.local.format("%Y%m%dT%H%M%S").to_string();
+        let rule = if year == last {
+            Some(first.rule())
+        } else if first.local != until.local {
+            Some(format!(
+                "{};UNTIL={}",
+                first.rule(),
+                until.utc().format("%Y%m%dT%H%M%SZ")
+            ))
+        } else {
+            None
+        };
+        if first.daylight {
+            let mut daylight = Daylight::new(dtstart, offset(first.from), offset(first.to));
+            if let Some(rule) = rule {
+                daylight.push(IcsRRule::new(rule));
+            }
+            daylights.push(daylight);
+        } else {
+            let mut standard = Standard::new(dtstart, offset(first.from), offset(first.to));
+            if let Some(rule) = rule {
+                standard.push(IcsRRule::new(rule));
+            }
+            standards.push(standard);
+        }
+    }
+
+    let mut component = match standards.pop() {
+        Some(standard) => TimeZone::standard(timezone.name(), standard),
+        None => {
+            let fixed = offset(utc_offset(timezone, year_start(first)?, first)?.0);
+            let standard = Standard::new("19700101T000000", fixed.clone(), fixed);
+            TimeZone::standard(timezone.name(), standard)
+        }
+    };
+    for standard in standards {
+        component.add_standard(standard);
+    }
+    for daylight in daylights {
+        component.add_daylight(daylight);
+    }
+    Ok(component)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ics::components::Property;
+
+    #[test]
+    fn test_vtimezone() {
+        let lisbon = vtimezone(Tz::Europe__Lisbon, 2024, 2024)
+            .unwrap()
+            .to_string();
+        assert!(lisbon.contains("TZID:Europe/Lisbon\r\n"));
+        assert!(lisbon.contains(
+            "BEGIN:DAYLIGHT\r\nDTSTART:20230326T010000\r\nTZOFFSETFROM:+0000\r\nTZOFFSETTO:+0100\r\nRRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r\n"
+        ));
+        assert!(lisbon.contains(
+            "BEGIN:STANDARD\r\nDTSTART:20231029T020000\r\nTZOFFSETFROM:+0100\r\nTZOFFSETTO:+0000\r\nRRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r\n"
+        ));
+
+        let new_york = vtimezone(Tz::America__New_York, 2024, 2024)
+            .unwrap()
+            .to_string();
+        assert!(new_york.contains("DTSTART:20230312T020000\r\nTZOFFSETFROM:-0500\r\nTZOFFSETTO:-0400\r\nRRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r\n"));
+        assert!(new_york.contains("RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r\n"));
+
+        let kolkata = vtimezone(Tz::Asia__Kolkata, 2024, 2024)
+            .unwrap()
+            .to_string();
+        assert!(kolkata.contains(
+            "BEGIN:STANDARD\r\nDTSTART:19700101T000000\r\nTZOFFSETFROM:+0530\r\nTZOFFSETTO:+0530\r\n"
+        ));
+        assert!(!kolkata.contains("DAYLIGHT"));
+
+        // The United States changed their rules in 2007
+        let new_york = vtimezone(Tz::America__New_York, 2006, 2008)
+            .unwrap()
+            .to_string();
+        assert!(new_york.contains("BEGIN:DAYLIGHT\r\nDTSTART:20050403T020000\r\nTZOFFSETFROM:-0500\r\nTZOFFSETTO:-0400\r\nRRULE:FREQ=YEARLY;BYMONTH=4;BYDAY=1SU;UNTIL=20060402T070000Z\r\nEND:DAYLIGHT\r\n"));
+        assert!(new_york.contains("BEGIN:STANDARD\r\nDTSTART:20051030T020000\r\nTZOFFSETFROM:-0400\r\nTZOFFSETTO:-0500\r\nRRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU;UNTIL=20061029T060000Z\r\nEND:STANDARD\r\n"));
+        assert!(new_york.contains("BEGIN:DAYLIGHT\r\nDTSTART:20070311T020000\r\nTZOFFSETFROM:-0500\r\nTZOFFSETTO:-0400\r\nRRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r\nEND:DAYLIGHT\r\n"));
+        assert!(new_york.contains("RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r\n"));
+    }
+
+    #[test]
+    fn test_properties() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let time = NaiveTime::from_hms_opt(18, 30, 0).unwrap();
+        let timed = Moment::new(date, Some(time), Some(Tz::Europe__Lisbon));
+        let all_day = Moment::new(date, None, None);
+
+        assert_eq!(
+            "DTSTART;TZID=Europe/Lisbon:20240501T183000\r\n",
+            Property::from(dtstart(&timed, date)).to_string()
+        );
+        assert_eq!(
+            "DUE;VALUE=DATE:20240501\r\n",
+            Property::from(due(&all_day, date)).to_string()
+        );
+
+        let rule = prm::entities::recurrence::parse("FREQ=WEEKLY;UNTIL=20240529", date)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            "RRULE:FREQ=WEEKLY;UNTIL=20240529T173000Z\r\n",
+            Property::from(rrule(&rule, &timed)).to_string()
+        );
+        assert_eq!(
+            "RRULE:FREQ=WEEKLY;UNTIL=20240529\r\n",
+            Property::from(rrule(&rule, &all_day)).to_string()
+        );
+    }
+}
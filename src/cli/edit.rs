@@ -1,16 +1,24 @@
+use chrono_tz::Tz;
 use prm::db::db_interface::DbOperations;
+use prm::db::loader::LoadContext;
 use prm::entities::activity::{Activity, ParseActivityFromEditorData, ACTIVITY_TEMPLATE};
+use prm::entities::datetime::Moment;
 use prm::entities::note::{Note, NOTE_TEMPLATE};
 use prm::entities::person::{Person, PERSON_TEMPLATE};
 use prm::entities::reminder::{ParseReminderFromEditorData, Reminder, REMINDER_TEMPLATE};
 use prm::entities::Entities;
+use prm::helpers::parse_dates;
 extern crate strfmt;
 use rusqlite::Connection;
+use snafu::ResultExt;
 use std::collections::HashMap;
 use strfmt::strfmt;
 
 use prm::CliError;
-use prm::{EditSnafu, EditorParseSnafu, EntitySnafu, NotFoundSnafu, TemplateSnafu};
+use prm::{
+    DatabaseSnafu, DateTimeParseSnafu, EditSnafu, EditorParseSnafu, EditorRunSnafu, EntitySnafu,
+    NotFoundSnafu, TemplateSnafu,
+};
 
 pub fn person(
     conn: &Connection,
@@ -27,7 +35,7 @@ pub fn person(
     let activity_ids: Vec<u64>;
     let reminder_ids: Vec<u64>;
 
-    let person = Person::get_by_id(conn, id);
+    let person = Person::get_by_id(conn, &mut LoadContext::new(true), id);
 
     match person {
         Ok(person) => match person {
@@ -182,15 +190,7 @@ pub fn person(
                 };
                 let edited = match edit::edit(person_str) {
                     Ok(edited) => edited,
-                    Err(e) => {
-                        return {
-                            EditorParseSnafu {
-                                entity: "Person".to_string(),
-                                message: format!("Error editing person: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
+                    Err(e) => return Err(e).context(EditorRunSnafu { entity: "Person" }),
                 };
                 let (n, b, c, a, r) = match Person::parse_from_editor(edited.as_str()) {
                     Ok(d) => (
@@ -201,13 +201,7 @@ pub fn person(
                         d.reminders,
                     ),
                     Err(e) => {
-                        return {
-                            EditorParseSnafu {
-                                entity: "Person".to_string(),
-                                message: format!("Error editing person: {:#?}", e),
-                            }
-                            .fail()
-                        }
+                        return Err(Box::new(e)).context(EditorParseSnafu { entity: "Person" })
                     }
                 };
                 name_str = n;
@@ -216,78 +210,35 @@ pub fn person(
                 activity_ids = a;
                 reminder_ids = r;
 
-                let activities = match Activity::get_by_ids(conn, activity_ids) {
-                    Ok(activities) => activities,
-                    Err(e) => {
-                        return {
-                            EntitySnafu {
-                                entity: "Activity".to_string(),
-                                message: format!("Error fetching activities: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
-                };
+                let activities =
+                    Activity::get_by_ids(conn, &mut LoadContext::new(true), activity_ids)?;
 
-                let reminders = match Reminder::get_by_ids(conn, reminder_ids) {
-                    Ok(reminders) => reminders,
-                    Err(e) => {
-                        return {
-                            EntitySnafu {
-                                entity: "Reminder".to_string(),
-                                message: format!("Error fetching reminders: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
-                };
+                let reminders =
+                    Reminder::get_by_ids(conn, &mut LoadContext::new(true), reminder_ids)?;
 
-                match person.update(
+                person.update(
                     name_str,
                     birthday_str,
                     contact_info_str,
                     activities,
                     reminders,
-                ) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        return {
-                            EditSnafu {
-                                entity: "Person".to_string(),
-                                message: format!("Error editing person: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
-                };
+                )?;
                 match person.save(conn) {
                     Ok(person) => println!("Updated person: {}", person),
-                    Err(e) => {
-                        return {
-                            EditSnafu {
-                                entity: "Person".to_string(),
-                                message: format!("Error editing person: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
+                    Err(e) => return Err(e).context(EditSnafu { entity: "Person" }),
                 }
                 Ok(person)
             }
-            None => {
-                println!("Could not find person id {}", id);
-                NotFoundSnafu {
-                    entity: "Person".to_string(),
-                    id,
-                }
-                .fail()
+            None => NotFoundSnafu {
+                entity: "Person".to_string(),
+                id,
             }
+            .fail(),
         },
-        Err(e) => EntitySnafu {
+        Err(e) => Err(e).context(DatabaseSnafu {
             entity: "Person".to_string(),
-            message: format!("Error fetching person: {:#?}", e),
-        }
-        .fail(),
+            action: "fetching",
+        }),
     }
 }
 pub fn activity(
@@ -297,8 +248,9 @@ pub fn activity(
     activity_type: Option<String>,
     date: Option<String>,
     content: Option<String>,
+    timezone: Tz,
 ) -> Result<Activity, CliError> {
-    let activity = Activity::get_by_id(conn, id);
+    let activity = Activity::get_by_id(conn, &mut LoadContext::new(true), id);
 
     let name_string: String;
     let date_string: String;
@@ -351,7 +303,7 @@ pub fn activity(
                         }
                     };
                 } else if !activity.date.to_string().is_empty() {
-                    date_placeholder = activity.date.clone().to_string();
+                    date_placeholder = activity.moment().to_string();
                 } else {
                     date_placeholder = "".to_string();
                 }
@@ -421,15 +373,7 @@ pub fn activity(
                 };
                 let edited = match edit::edit(activity_str) {
                     Ok(edited) => edited,
-                    Err(e) => {
-                        return {
-                            EditorParseSnafu {
-                                entity: "Activity".to_string(),
-                                message: format!("Error editing activity: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
+                    Err(e) => return Err(e).context(EditorRunSnafu { entity: "Activity" }),
                 };
                 let (n, d, t, c, p) = match Activity::parse_from_editor(edited.as_str()) {
                     Ok(ParseActivityFromEditorData {
@@ -440,13 +384,7 @@ pub fn activity(
                         people,
                     }) => (name, date, activity_type, content, people),
                     Err(e) => {
-                        return {
-                            EditorParseSnafu {
-                                entity: "Activity".to_string(),
-                                message: format!("Error editing activity: {:#?}", e),
-                            }
-                            .fail()
-                        }
+                        return Err(Box::new(e)).context(EditorParseSnafu { entity: "Activity" })
                     }
                 };
                 name_string = n;
@@ -479,36 +417,20 @@ pub fn activity(
                 };
                 people = p;
 
-                match activity.update(
+                activity.update(
                     conn,
                     Some(name_string),
                     Some(activity_type_string),
-                    Some(date_string),
+                    Some(
+                        Moment::parse(&date_string, timezone)
+                            .context(DateTimeParseSnafu { date: &date_string })?,
+                    ),
                     Some(content_string),
                     people,
-                ) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        return {
-                            EditSnafu {
-                                entity: "Activity".to_string(),
-                                message: format!("Error editing activity: {:#?}", e),
-                            }
-                        }
-                        .fail()
-                    }
-                };
+                )?;
                 match activity.save(conn) {
                     Ok(activity) => println!("Updated activity: {:#?}", activity),
-                    Err(e) => {
-                        return {
-                            EditSnafu {
-                                entity: "Activity".to_string(),
-                                message: format!("Error editing activity: {:#?}", e),
-                            }
-                        }
-                        .fail()
-                    }
+                    Err(e) => return Err(e).context(EditSnafu { entity: "Activity" }),
                 }
                 Ok(activity)
             }
@@ -518,14 +440,14 @@ pub fn activity(
             }
             .fail(),
         },
-        Err(e) => EntitySnafu {
+        Err(e) => Err(e).context(DatabaseSnafu {
             entity: "Activity".to_string(),
-            message: format!("Error fetching activity: {:#?}", e),
-        }
-        .fail(),
+            action: "fetching",
+        }),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn reminder(
     conn: &Connection,
     id: u64,
@@ -533,8 +455,11 @@ pub fn reminder(
     date: Option<String>,
     description: Option<String>,
     recurring: Option<String>,
+    exdates: Option<Vec<String>>,
+    timezone: Tz,
 ) -> Result<Reminder, CliError> {
-    let reminder = Reminder::get_by_id(conn, id);
+    let exdates = exdates.map(parse_dates).transpose()?;
+    let reminder = Reminder::get_by_id(conn, &mut LoadContext::new(true), id);
 
     let name_string: String;
     let date_string: String;
@@ -587,7 +512,7 @@ pub fn reminder(
                         }
                     }
                 } else if !reminder.date.to_string().is_empty() {
-                    date_placeholder = reminder.date.clone().to_string();
+                    date_placeholder = reminder.moment().to_string();
                 } else {
                     date_placeholder = "".to_string();
                 }
@@ -611,22 +536,10 @@ pub fn reminder(
                 } else {
                     description_placeholder = "".to_string();
                 }
-                let recurring_placeholder: String;
-                if recurring.is_some() {
-                    recurring_placeholder = match recurring {
-                        Some(recurring) => recurring,
-                        None => {
-                            return Err(CliError::MissingField {
-                                entity: "reminder".to_string(),
-                                field: "recurring type".to_string(),
-                            })
-                        }
-                    };
-                } else if !reminder.recurring.as_ref().is_empty() {
-                    recurring_placeholder = reminder.recurring.as_ref().to_string().to_lowercase();
-                } else {
-                    recurring_placeholder = "".to_string();
-                }
+                let recurring_placeholder = match recurring {
+                    Some(recurring) => recurring,
+                    None => reminder.recurrence(),
+                };
                 let people_placeholder: String = if !reminder.people.is_empty() {
                     reminder
                         .people
@@ -658,15 +571,7 @@ pub fn reminder(
                 };
                 let edited = match edit::edit(reminder_str) {
                     Ok(edited) => edited,
-                    Err(e) => {
-                        return {
-                            EditorParseSnafu {
-                                entity: "Reminder".to_string(),
-                                message: format!("Error editing reminder: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
+                    Err(e) => return Err(e).context(EditorRunSnafu { entity: "Reminder" }),
                 };
                 let (n, da, r, de, p) = match Reminder::parse_from_editor(edited.as_str()) {
                     Ok(ParseReminderFromEditorData {
@@ -677,13 +582,7 @@ pub fn reminder(
                         people,
                     }) => (name, date, recurring_type, description, people),
                     Err(e) => {
-                        return {
-                            EditorParseSnafu {
-                                entity: "Reminder".to_string(),
-                                message: format!("Error parsing reminder: {:#?}", e),
-                            }
-                            .fail()
-                        }
+                        return Err(Box::new(e)).context(EditorParseSnafu { entity: "Reminder" })
                     }
                 };
                 name_string = n;
@@ -716,36 +615,23 @@ pub fn reminder(
                 };
                 people = p;
 
-                match reminder.update(
+                reminder.update(
                     conn,
                     Some(name_string),
-                    Some(date_string),
+                    Some(
+                        Moment::parse(&date_string, timezone)
+                            .context(DateTimeParseSnafu { date: &date_string })?,
+                    ),
                     Some(description_string),
                     Some(recurring_type_string),
                     people,
-                ) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        return {
-                            EditSnafu {
-                                entity: "Reminder".to_string(),
-                                message: format!("Error editing reminder: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
-                };
+                )?;
+                if let Some(exdates) = exdates {
+                    reminder.exdates = exdates;
+                }
                 match reminder.save(conn) {
                     Ok(reminder) => println!("Updated reminder: {:#?}", reminder),
-                    Err(e) => {
-                        return {
-                            EditSnafu {
-                                entity: "Reminder".to_string(),
-                                message: format!("Error editing reminder: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
+                    Err(e) => return Err(e).context(EditSnafu { entity: "Reminder" }),
                 }
                 Ok(reminder)
             }
@@ -755,11 +641,10 @@ pub fn reminder(
             }
             .fail(),
         },
-        Err(e) => EntitySnafu {
+        Err(e) => Err(e).context(DatabaseSnafu {
             entity: "Reminder".to_string(),
-            message: format!("Error fetching reminder: {:#?}", e),
-        }
-        .fail(),
+            action: "fetching",
+        }),
     }
 }
 pub fn note(
@@ -767,8 +652,9 @@ pub fn note(
     id: u64,
     date: Option<String>,
     content: Option<String>,
+    timezone: Tz,
 ) -> Result<Note, CliError> {
-    let note = Note::get_by_id(conn, id);
+    let note = Note::get_by_id(conn, &mut LoadContext::new(true), id);
 
     let date_string: String;
     let content_string: String;
@@ -813,7 +699,7 @@ pub fn note(
                         }
                     }
                 } else if !note.date.to_string().is_empty() {
-                    date_placeholder = note.date.clone().to_string();
+                    date_placeholder = note.moment().to_string();
                 } else {
                     date_placeholder = "".to_string();
                 }
@@ -851,58 +737,30 @@ pub fn note(
                 };
                 let edited = match edit::edit(note_str) {
                     Ok(edited) => edited,
-                    Err(e) => {
-                        return {
-                            EditorParseSnafu {
-                                entity: "Note".to_string(),
-                                message: format!("Error editing note: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
+                    Err(e) => return Err(e).context(EditorRunSnafu { entity: "Note" }),
                 };
                 let (d, c, p) = match Note::parse_from_editor(edited.as_str()) {
                     Ok((date, content, people)) => (date, content, people),
-                    Err(e) => {
-                        return {
-                            EditorParseSnafu {
-                                entity: "Note".to_string(),
-                                message: format!("Error parsing note: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
+                    Err(e) => return Err(Box::new(e)).context(EditorParseSnafu { entity: "Note" }),
                 };
 
                 date_string = d;
                 content_string = c;
                 people = p;
 
-                match note.update(conn, Some(date_string), Some(content_string), people) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        return {
-                            EditSnafu {
-                                entity: "Note".to_string(),
-                                message: format!("Error editing note: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
-                };
+                note.update(
+                    conn,
+                    Some(
+                        Moment::parse(&date_string, timezone)
+                            .context(DateTimeParseSnafu { date: &date_string })?,
+                    ),
+                    Some(content_string),
+                    people,
+                )?;
                 match note.save(conn) {
                     Ok(note) => println!("Updated note: {:#?}", note),
-                    Err(e) => {
-                        return {
-                            EditSnafu {
-                                entity: "Note".to_string(),
-                                message: format!("Error editing note: {:#?}", e),
-                            }
-                            .fail()
-                        }
-                    }
+                    Err(e) => return Err(e).context(EditSnafu { entity: "Note" }),
                 };
-                println!("Updated note: {:#?}", note);
                 Ok(note)
             }
             None => NotFoundSnafu {
@@ -911,10 +769,9 @@ pub fn note(
             }
             .fail(),
         },
-        Err(e) => EntitySnafu {
+        Err(e) => Err(e).context(DatabaseSnafu {
             entity: "Note".to_string(),
-            message: format!("Wrong entity type: {:#?}", e),
-        }
-        .fail(),
+            action: "fetching",
+        }),
     }
 }
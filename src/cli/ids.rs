@@ -0,0 +1,193 @@
+use clap::Args;
+use prm::db::loader::LoadContext;
+use prm::db_interface::DbOperations;
+use prm::entities::activity::Activity;
+use prm::entities::note::Note;
+use prm::entities::person::Person;
+use prm::entities::reminder::Reminder;
+use prm::entities::Entities;
+use prm::helpers::{confirm_on, SelectionError};
+use prm::{CliError, DatabaseSnafu, NotFoundSnafu};
+use rusqlite::Connection;
+use snafu::ResultExt;
+use std::fmt::Display;
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct IdArgs {
+    /// Id of the entity
+    pub id: Option<u64>,
+    /// Comma separated list of ids, e.g. --ids 3,7,9
+    #[arg(long, value_delimiter = ',', conflicts_with = "id")]
+    pub ids: Vec<u64>,
+}
+
+impl IdArgs {
+    // The ids in the order they were given, each once
+    pub fn ids(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = vec![];
+        for id in self.id.into_iter().chain(self.ids.iter().copied()) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+}
+
+// Entities that can be fetched with `DbOperations::get_by_id`
+pub trait ById: DbOperations + Sized {
+    const NAME: &'static str;
+
+    fn from_entities(entity: Entities) -> Option<Self>;
+}
+
+impl ById for Person {
+    const NAME: &'static str = "Person";
+
+    fn from_entities(entity: Entities) -> Option<Person> {
+        match entity {
+            Entities::Person(person) => Some(person),
+            _ => None,
+        }
+    }
+}
+
+impl ById for Activity {
+    const NAME: &'static str = "Activity";
+
+    fn from_entities(entity: Entities) -> Option<Activity> {
+        match entity {
+            Entities::Activity(activity) => Some(activity),
+            _ => None,
+        }
+    }
+}
+
+impl ById for Reminder {
+    const NAME: &'static str = "Reminder";
+
+    fn from_entities(entity: Entities) -> Option<Reminder> {
+        match entity {
+            Entities::Reminder(reminder) => Some(reminder),
+            _ => None,
+        }
+    }
+}
+
+impl ById for Note {
+    const NAME: &'static str = "Note";
+
+    fn from_entities(entity: Entities) -> Option<Note> {
+        match entity {
+            Entities::Note(note) => Some(note),
+            _ => None,
+        }
+    }
+}
+
+// Fetches every id, failing on the first one that does not exist so that
+// bulk operations are all or nothing
+pub fn get_by_ids<T: ById>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    ids: &[u64],
+) -> Result<Vec<T>, CliError> {
+    let mut entities = vec![];
+    for id in ids {
+        match T::get_by_id(conn, load, *id) {
+            Ok(Some(entity)) => match T::from_entities(entity) {
+                Some(entity) => entities.push(entity),
+                None => {
+                    return NotFoundSnafu {
+                        entity: T::NAME,
+                        id: *id,
+                    }
+                    .fail()
+                }
+            },
+            Ok(None) => {
+                return NotFoundSnafu {
+                    entity: T::NAME,
+                    id: *id,
+                }
+                .fail()
+            }
+            Err(e) => {
+                return Err(e).context(DatabaseSnafu {
+                    entity: format!("{} {}", T::NAME, id),
+                    action: "fetching",
+                })
+            }
+        }
+    }
+    Ok(entities)
+}
+
+// Lists the entities about to be removed and asks whether to remove them.
+// Without a terminal to ask on, only `yes` confirms.
+pub fn confirm_removal<T: Display>(
+    entities: &[T],
+    entity: &str,
+    yes: bool,
+    terminal: bool,
+) -> Result<(), SelectionError> {
+    for e in entities {
+        println!("{}", e);
+    }
+    let question = if entities.len() == 1 {
+        format!("Do you want to remove this {}?", entity)
+    } else {
+        format!("Do you want to remove these {} entries?", entities.len())
+    };
+    confirm_on(&question, yes, terminal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_ids() {
+        let args = |id, ids: &[u64]| IdArgs {
+            id,
+            ids: ids.to_vec(),
+        };
+        assert_eq!(vec![3], args(Some(3), &[]).ids());
+        assert_eq!(vec![3, 7, 9], args(None, &[3, 7, 9]).ids());
+        assert_eq!(vec![1, 3, 7], args(Some(1), &[3, 7]).ids());
+        assert_eq!(vec![7, 3], args(None, &[7, 3, 7, 3]).ids());
+        assert!(args(None, &[]).ids().is_empty());
+    }
+
+    #[test]
+    fn test_get_by_ids() {
+        let conn = Connection::open_in_memory().unwrap();
+        prm::db::migrations::migrate(&conn).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        for content in ["a", "b", "c"] {
+            Note::new(0, date, String::from(content), vec![])
+                .add(&conn)
+                .unwrap();
+        }
+
+        let notes = get_by_ids::<Note>(&conn, &mut LoadContext::default(), &[3, 1]).unwrap();
+        assert_eq!(
+            vec!["c", "a"],
+            notes.iter().map(|n| n.content.as_str()).collect::<Vec<_>>()
+        );
+        assert!(get_by_ids::<Note>(&conn, &mut LoadContext::default(), &[])
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            get_by_ids::<Note>(&conn, &mut LoadContext::default(), &[1, 4]),
+            Err(CliError::NotFound { id: 4, .. })
+        ));
+
+        assert!(matches!(
+            confirm_removal(&notes, "note", false, false),
+            Err(SelectionError::NotATerminal { .. })
+        ));
+        assert!(confirm_removal(&notes, "note", true, false).is_ok());
+    }
+}
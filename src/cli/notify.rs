@@ -0,0 +1,42 @@
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use prm::config::NotifyConfig;
+use prm::db::loader::LoadContext;
+use prm::notify::{self, NotifyError};
+use rusqlite::Connection;
+
+// Sends what is due once or, as a daemon, every `interval` seconds until
+// killed. A daemon reports errors and keeps going.
+pub fn run(
+    conn: &Connection,
+    load: &mut LoadContext,
+    config: &NotifyConfig,
+    daemon: bool,
+    interval: Option<u64>,
+    days: Option<u64>,
+) -> Result<(), prm::Error> {
+    let notifiers = notify::notifiers(&config.backends)?;
+    let days = days.unwrap_or(config.days);
+    let interval = Duration::from_secs(interval.unwrap_or(config.interval).max(1));
+    loop {
+        match notify::notify(conn, load, &notifiers, Local::now(), days) {
+            Ok(summary) => {
+                if summary.failed > 0 && !daemon {
+                    return Err(NotifyError::Undelivered {
+                        count: summary.failed,
+                    }
+                    .into());
+                }
+            }
+            Err(e) if daemon => eprintln!("{}", prm::Error::from(e)),
+            Err(e) => return Err(e.into()),
+        }
+        if !daemon {
+            return Ok(());
+        }
+        crate::cli::output::warn_skipped(load);
+        thread::sleep(interval);
+    }
+}
@@ -0,0 +1,147 @@
+use clap::ValueEnum;
+use prm::db::loader::LoadContext;
+use prm::{CliError, OutputSnafu};
+use serde::Serialize;
+
+use crate::cli::table::{self, TableArgs, Tabular};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Format {
+    /// Human readable output
+    #[default]
+    Table,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// A YAML sequence
+    Yaml,
+}
+
+// Renders `items` in the requested format. `display` is only used for
+// `Format::Table`; the other formats serialise the full entity graph.
+pub fn render<T: Serialize>(
+    format: Format,
+    items: &[T],
+    display: impl Fn(&T) -> String,
+) -> Result<String, CliError> {
+    match format {
+        Format::Table => Ok(items
+            .iter()
+            .map(|item| format!("{}\n", display(item)))
+            .collect()),
+        Format::Json => match serde_json::to_string_pretty(items) {
+            Ok(json) => Ok(format!("{}\n", json)),
+            Err(e) => OutputSnafu {
+                message: e.to_string(),
+            }
+            .fail(),
+        },
+        Format::Ndjson => {
+            let mut lines = String::new();
+            for item in items {
+                match serde_json::to_string(item) {
+                    Ok(json) => lines.push_str(&format!("{}\n", json)),
+                    Err(e) => {
+                        return OutputSnafu {
+                            message: e.to_string(),
+                        }
+                        .fail()
+                    }
+                }
+            }
+            Ok(lines)
+        }
+        Format::Yaml => match serde_yaml::to_string(items) {
+            Ok(yaml) => Ok(yaml),
+            Err(e) => OutputSnafu {
+                message: e.to_string(),
+            }
+            .fail(),
+        },
+    }
+}
+
+// Prints `items`, or `empty` when there are none and the output is meant for
+// humans. Machine readable formats always print, so an empty result is still
+// valid JSON/YAML.
+pub fn print<T: Serialize>(
+    format: Format,
+    items: &[T],
+    empty: Option<&str>,
+    display: impl Fn(&T) -> String,
+) -> Result<(), CliError> {
+    if format == Format::Table && items.is_empty() {
+        if let Some(empty) = empty {
+            println!("{}", empty);
+        }
+        return Ok(());
+    }
+    print!("{}", render(format, items, display)?);
+    Ok(())
+}
+
+// Prints a list of entities. `Format::Table` renders an aligned table using
+// the selected columns; sorting applies to every format.
+pub fn print_list<T: Serialize + Tabular>(
+    format: Format,
+    items: &mut [T],
+    args: &TableArgs,
+    date_format: &str,
+) -> Result<(), CliError> {
+    table::sort(items, args)?;
+    if format != Format::Table {
+        return print(format, items, None, |_| String::new());
+    }
+    let columns = table::columns::<T>(args)?;
+    print!(
+        "{}",
+        table::render(items, &columns, date_format, table::terminal_width())
+    );
+    Ok(())
+}
+
+// Prints a warning for every row `load` had to skip since the last call
+pub fn warn_skipped(load: &mut LoadContext) {
+    for row in load.skipped.drain(..) {
+        eprintln!("Warning: {}", row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use prm::entities::recurrence::{Frequency, RRule};
+    use prm::entities::reminder::Reminder;
+
+    #[test]
+    fn test_render() {
+        let reminders = vec![Reminder {
+            id: 1,
+            name: String::from("Call"),
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            time: None,
+            timezone: None,
+            description: None,
+            recurring: Some(RRule::new(Frequency::Monthly)),
+            exdates: vec![],
+            history: vec![],
+            people: vec![],
+            created_at: None,
+            updated_at: None,
+        }];
+
+        assert_eq!(
+            "{\"id\":1,\"name\":\"Call\",\"date\":\"2024-03-01\",\"time\":null,\"timezone\":null,\"description\":null,\"recurring\":\"FREQ=MONTHLY\",\"exdates\":[],\"history\":[],\"people\":[],\"created_at\":null,\"updated_at\":null}\n",
+            render(Format::Ndjson, &reminders, |r| r.to_string()).unwrap()
+        );
+        assert_eq!(
+            "[]\n",
+            render(Format::Json, &Vec::<Reminder>::new(), |r| r.to_string()).unwrap()
+        );
+        assert!(render(Format::Yaml, &reminders, |r| r.to_string())
+            .unwrap()
+            .contains("recurring: FREQ=MONTHLY"));
+    }
+}
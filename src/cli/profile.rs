@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use prm::config::{Config, Profile};
+use prm::db::db_interface::DbOperations;
+use prm::db::journal;
+use prm::db::loader::LoadContext;
+use prm::entities::person::Person;
+use prm::entities::Entities;
+use prm::{CliError, ConfigSnafu, DatabaseSnafu, EntitySnafu, NotFoundSnafu};
+use rusqlite::Connection;
+use snafu::ResultExt;
+
+pub fn list(config: &Config, active: &str) -> Result<(), CliError> {
+    for name in config.profile_names() {
+        let database = config.database_path(&name).context(ConfigSnafu)?;
+        let marker = if name == active { "*" } else { " " };
+        println!("{} {}\t{}", marker, name, database.display());
+    }
+    Ok(())
+}
+
+pub fn create(
+    config: &mut Config,
+    name: String,
+    database: Option<PathBuf>,
+    ics: Option<PathBuf>,
+) -> Result<(), CliError> {
+    config
+        .add_profile(&name, Profile { database, ics })
+        .context(ConfigSnafu)?;
+    config.save().context(ConfigSnafu)?;
+    println!(
+        "Profile {} created with database {}",
+        name,
+        config.database_path(&name).context(ConfigSnafu)?.display()
+    );
+    Ok(())
+}
+
+pub fn switch(config: &mut Config, name: String) -> Result<(), CliError> {
+    config.set_default_profile(&name).context(ConfigSnafu)?;
+    config.save().context(ConfigSnafu)?;
+    println!("Switched to profile {}", name);
+    Ok(())
+}
+
+pub fn delete(config: &mut Config, name: String, purge: bool) -> Result<(), CliError> {
+    let database = config.database_path(&name).context(ConfigSnafu)?;
+    let ics = config.ics_path(&name).context(ConfigSnafu)?;
+    config.remove_profile(&name).context(ConfigSnafu)?;
+    config.save().context(ConfigSnafu)?;
+    if purge {
+        for path in [database, ics] {
+            if path.exists() {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    return EntitySnafu {
+                        entity: "Profile",
+                        message: format!("Error removing {}: {}", path.display(), e),
+                    }
+                    .fail();
+                }
+            }
+        }
+    }
+    println!("Profile {} deleted", name);
+    Ok(())
+}
+
+pub fn copy_person(
+    from: &Connection,
+    to: &Connection,
+    id: u64,
+    journal_retention: u64,
+) -> Result<Person, CliError> {
+    let person = match Person::get_by_id(from, &mut LoadContext::new(true), id) {
+        Ok(Some(Entities::Person(person))) => person,
+        _ => {
+            return NotFoundSnafu {
+                entity: "Person",
+                id,
+            }
+            .fail()
+        }
+    };
+    let copied = person.copy_to(to).context(DatabaseSnafu {
+        entity: "Person",
+        action: "copying",
+    })?;
+    println!("{}\ncopied successfully", copied);
+    journal::prune(to, journal_retention).context(DatabaseSnafu {
+        entity: "journal",
+        action: "pruning",
+    })?;
+    Ok(copied)
+}
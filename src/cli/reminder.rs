@@ -0,0 +1,127 @@
+use chrono::{Local, NaiveDate};
+use prm::db::entity_kind::EntityKind;
+use prm::db::loader::LoadContext;
+use prm::entities::reminder::Reminder;
+use prm::error::{DbSnafu, Operation};
+use prm::{CliError, DateParseSnafu, EntitySnafu};
+use rusqlite::Connection;
+use snafu::ResultExt;
+
+use crate::cli::ids;
+
+fn parse_date(date: &str) -> Result<NaiveDate, CliError> {
+    match prm::helpers::parse_from_str_ymd(date) {
+        Ok(date) => Ok(date),
+        Err(_) => DateParseSnafu { date }.fail(),
+    }
+}
+
+fn get(conn: &Connection, load: &mut LoadContext, id: u64) -> Result<Reminder, CliError> {
+    Ok(ids::get_by_ids::<Reminder>(conn, load, &[id])?.remove(0))
+}
+
+// The occurrence on `date`, or the one the reminder is waiting on
+fn occurrence(
+    reminder: &Reminder,
+    date: Option<String>,
+    today: NaiveDate,
+) -> Result<NaiveDate, CliError> {
+    match date {
+        Some(date) => {
+            let date = parse_date(&date)?;
+            if !reminder.is_occurrence(date) {
+                return EntitySnafu {
+                    entity: "Reminder",
+                    message: format!("Reminder {} does not occur on {}", reminder.id, date),
+                }
+                .fail();
+            }
+            Ok(date)
+        }
+        None => match reminder.due(today) {
+            Some(due) => Ok(due.occurrence),
+            None => EntitySnafu {
+                entity: "Reminder",
+                message: format!("Reminder {} has nothing left to do", reminder.id),
+            }
+            .fail(),
+        },
+    }
+}
+
+// Prints when reminder `id` is due next
+fn report(
+    conn: &Connection,
+    load: &mut LoadContext,
+    id: u64,
+    today: NaiveDate,
+) -> Result<(), CliError> {
+    match get(conn, load, id)?.due(today) {
+        Some(due) => println!("Next due on {} ({})", due.date, due.status.as_ref()),
+        None => println!("Nothing left to do"),
+    }
+    Ok(())
+}
+
+pub fn done(
+    conn: &Connection,
+    load: &mut LoadContext,
+    id: u64,
+    date: Option<String>,
+) -> Result<(), prm::Error> {
+    let today = Local::now().date_naive();
+    let reminder = get(conn, load, id)?;
+    let date = occurrence(&reminder, date, today)?;
+    reminder.complete(conn, date).context(DbSnafu {
+        operation: Operation::Complete,
+        entity: EntityKind::Reminder,
+        id,
+    })?;
+    println!("Marked {} on {} as done", reminder.name, date);
+    Ok(report(conn, load, id, today)?)
+}
+
+pub fn skip(
+    conn: &Connection,
+    load: &mut LoadContext,
+    id: u64,
+    date: Option<String>,
+) -> Result<(), prm::Error> {
+    let today = Local::now().date_naive();
+    let reminder = get(conn, load, id)?;
+    let date = occurrence(&reminder, date, today)?;
+    reminder.skip(conn, date).context(DbSnafu {
+        operation: Operation::Skip,
+        entity: EntityKind::Reminder,
+        id,
+    })?;
+    println!("Skipped {} on {}", reminder.name, date);
+    Ok(report(conn, load, id, today)?)
+}
+
+pub fn snooze(
+    conn: &Connection,
+    load: &mut LoadContext,
+    id: u64,
+    until: String,
+) -> Result<(), prm::Error> {
+    let today = Local::now().date_naive();
+    let reminder = get(conn, load, id)?;
+    let until = parse_date(&until)?;
+    if until <= today {
+        return Err(EntitySnafu {
+            entity: "Reminder",
+            message: format!("Cannot snooze until {}, it is not in the future", until),
+        }
+        .build()
+        .into());
+    }
+    let date = occurrence(&reminder, None, today)?;
+    reminder.snooze(conn, date, until).context(DbSnafu {
+        operation: Operation::Snooze,
+        entity: EntityKind::Reminder,
+        id,
+    })?;
+    println!("Snoozed {} on {} until {}", reminder.name, date, until);
+    Ok(())
+}
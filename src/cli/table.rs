@@ -0,0 +1,596 @@
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use clap::Args;
+use prm::db::audit::AuditEntry;
+use prm::db::db_helpers::search::SearchResult;
+use prm::db::doctor::Problem;
+use prm::db::journal::JournalEntry;
+use prm::db::trash::TrashItem;
+use prm::entities::activity::Activity;
+use prm::entities::event::{Event, EventType};
+use prm::entities::note::Note;
+use prm::entities::person::Person;
+use prm::entities::recurrence;
+use prm::entities::reminder::Reminder;
+use prm::{CliError, UnknownColumnSnafu};
+
+// Columns never shrink below this when fitting the terminal width
+static MIN_COLUMN_WIDTH: usize = 6;
+static COLUMN_SEPARATOR: &str = "  ";
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct TableArgs {
+    /// Comma separated list of columns to show
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Option<Vec<String>>,
+    /// Column to sort by
+    #[arg(long)]
+    pub sort: Option<String>,
+    /// Sort in descending order
+    #[arg(long, requires = "sort")]
+    pub desc: bool,
+}
+
+// A typed cell value, so that ids and dates sort naturally regardless of how
+// they are displayed
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Cell {
+    Int(u64),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    Text(String),
+    Empty,
+}
+
+impl Cell {
+    pub fn render(&self, date_format: &str) -> String {
+        match self {
+            Cell::Int(value) => value.to_string(),
+            Cell::Date(date) => date.format(date_format).to_string(),
+            Cell::DateTime(datetime) => datetime
+                .format(&format!("{} %H:%M", date_format))
+                .to_string(),
+            Cell::Text(text) => text.replace('\n', " "),
+            Cell::Empty => String::new(),
+        }
+    }
+}
+
+impl From<Option<NaiveDate>> for Cell {
+    fn from(date: Option<NaiveDate>) -> Cell {
+        match date {
+            Some(date) => Cell::Date(date),
+            None => Cell::Empty,
+        }
+    }
+}
+
+impl From<Option<NaiveDateTime>> for Cell {
+    fn from(datetime: Option<NaiveDateTime>) -> Cell {
+        match datetime {
+            Some(datetime) => Cell::DateTime(datetime),
+            None => Cell::Empty,
+        }
+    }
+}
+
+impl From<Option<String>> for Cell {
+    fn from(text: Option<String>) -> Cell {
+        match text {
+            Some(text) if !text.is_empty() => Cell::Text(text),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+pub trait Tabular {
+    // Every column the entity can be shown with
+    const COLUMNS: &'static [&'static str];
+    // Columns shown when `--columns` is not given
+    const DEFAULT_COLUMNS: &'static [&'static str];
+
+    // `column` is always one of `COLUMNS`
+    fn cell(&self, column: &str) -> Cell;
+}
+
+fn names(people: &[Person]) -> Cell {
+    Cell::from(Some(
+        people
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", "),
+    ))
+}
+
+impl Tabular for Person {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "birthday",
+        "contact_info",
+        "last_contact",
+        "activities",
+        "reminders",
+        "notes",
+        "created_at",
+        "updated_at",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] =
+        &["id", "name", "birthday", "contact_info", "last_contact"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "id" => Cell::Int(self.id),
+            "name" => Cell::Text(self.name.clone()),
+            "birthday" => Cell::from(self.birthday),
+            "contact_info" => Cell::from(Some(
+                self.contact_info
+                    .iter()
+                    .map(|ci| format!("{}: {}", ci.contact_info_type.as_ref(), ci.details))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )),
+            "last_contact" => Cell::from(self.activities.iter().map(|a| a.date).max()),
+            "activities" => Cell::Int(self.activities.len() as u64),
+            "reminders" => Cell::Int(self.reminders.len() as u64),
+            "notes" => Cell::Int(self.notes.len() as u64),
+            "created_at" => Cell::from(self.created_at),
+            "updated_at" => Cell::from(self.updated_at),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for Activity {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "type",
+        "date",
+        "time",
+        "content",
+        "people",
+        "created_at",
+        "updated_at",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["id", "name", "type", "date", "people"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "id" => Cell::Int(self.id),
+            "name" => Cell::Text(self.name.clone()),
+            "type" => Cell::Text(self.activity_type.as_ref().to_string()),
+            "date" => Cell::Date(self.date),
+            "time" => Cell::from(self.moment().time_of_day()),
+            "content" => Cell::from(Some(self.content.clone())),
+            "people" => names(&self.people),
+            "created_at" => Cell::from(self.created_at),
+            "updated_at" => Cell::from(self.updated_at),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for Reminder {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "date",
+        "time",
+        "next",
+        "due",
+        "status",
+        "recurring",
+        "exdates",
+        "description",
+        "people",
+        "created_at",
+        "updated_at",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] =
+        &["id", "name", "due", "status", "recurring", "people"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "id" => Cell::Int(self.id),
+            "name" => Cell::Text(self.name.clone()),
+            "date" => Cell::Date(self.date),
+            "time" => Cell::from(self.moment().time_of_day()),
+            "next" => Cell::from(self.next_occurrence(Local::now().date_naive())),
+            "due" => Cell::from(self.due(Local::now().date_naive()).map(|due| due.date)),
+            "status" => Cell::Text(self.status(Local::now().date_naive()).as_ref().to_string()),
+            "recurring" => Cell::Text(self.recurrence()),
+            "exdates" => Cell::from(recurrence::format_dates(&self.exdates)),
+            "description" => Cell::from(self.description.clone()),
+            "people" => names(&self.people),
+            "created_at" => Cell::from(self.created_at),
+            "updated_at" => Cell::from(self.updated_at),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for Note {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "date",
+        "time",
+        "content",
+        "people",
+        "created_at",
+        "updated_at",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["id", "date", "content", "people"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "id" => Cell::Int(self.id),
+            "date" => Cell::Date(self.date),
+            "time" => Cell::from(self.moment().time_of_day()),
+            "content" => Cell::from(Some(self.content.clone())),
+            "people" => names(&self.people),
+            "created_at" => Cell::from(self.created_at),
+            "updated_at" => Cell::from(self.updated_at),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for Event {
+    const COLUMNS: &'static [&'static str] = &[
+        "date",
+        "kind",
+        "name",
+        "description",
+        "recurring",
+        "status",
+        "people",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["date", "kind", "name", "description"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match (column, &self.details) {
+            ("date", _) => Cell::Date(self.date),
+            ("kind", _) => Cell::Text(self.kind().to_string()),
+            ("name", EventType::Person(person)) => Cell::Text(person.name.clone()),
+            ("name", EventType::Reminder(reminder)) => Cell::Text(reminder.name.clone()),
+            ("description", EventType::Person(person)) => person.cell("contact_info"),
+            ("description", EventType::Reminder(reminder)) => {
+                Cell::from(reminder.description.clone())
+            }
+            ("recurring", EventType::Reminder(reminder)) => reminder.cell("recurring"),
+            ("status", EventType::Reminder(reminder)) => reminder.cell("status"),
+            ("people", EventType::Reminder(reminder)) => names(&reminder.people),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for SearchResult {
+    // Results come back ordered by rank, which is only included in the
+    // machine readable formats
+    const COLUMNS: &'static [&'static str] = &["entity", "id", "title", "snippet"];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["entity", "id", "title", "snippet"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "entity" => Cell::Text(self.entity.clone()),
+            "id" => Cell::Int(self.id),
+            "title" => Cell::from(Some(self.title.clone())),
+            "snippet" => Cell::from(Some(self.snippet.clone())),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for TrashItem {
+    const COLUMNS: &'static [&'static str] = &["entity", "id", "summary", "deleted_at"];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["entity", "id", "summary", "deleted_at"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "entity" => Cell::Text(self.entity.as_ref().to_string()),
+            "id" => Cell::Int(self.id),
+            "summary" => Cell::from(Some(self.summary.clone())),
+            "deleted_at" => Cell::from(self.deleted_at.clone()),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for JournalEntry {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "created_at",
+        "operation",
+        "entity",
+        "entity_id",
+        "changes",
+        "undone",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &[
+        "id",
+        "created_at",
+        "operation",
+        "entity",
+        "entity_id",
+        "changes",
+    ];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "id" => Cell::Int(self.id),
+            "created_at" => Cell::Text(self.created_at.clone()),
+            "operation" => Cell::Text(self.operation.clone()),
+            "entity" => Cell::Text(self.entity.clone()),
+            "entity_id" => Cell::Int(self.entity_id),
+            "changes" => Cell::from(Some(self.summary())),
+            "undone" => Cell::Text(if self.undone { "yes" } else { "no" }.to_string()),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for AuditEntry {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "journal_id",
+        "changed_at",
+        "operation",
+        "entity",
+        "entity_id",
+        "field",
+        "old_value",
+        "new_value",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] =
+        &["changed_at", "operation", "field", "old_value", "new_value"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "id" => Cell::Int(self.id),
+            "journal_id" => Cell::Int(self.journal_id),
+            "changed_at" => Cell::Text(self.changed_at.clone()),
+            "operation" => Cell::Text(self.operation.clone()),
+            "entity" => Cell::Text(self.entity.clone()),
+            "entity_id" => Cell::Int(self.entity_id),
+            "field" => Cell::Text(self.field.clone()),
+            "old_value" => Cell::from(self.old_value.clone()),
+            "new_value" => Cell::from(self.new_value.clone()),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+impl Tabular for Problem {
+    const COLUMNS: &'static [&'static str] = &["check", "table", "id", "problem", "fix", "fixed"];
+    const DEFAULT_COLUMNS: &'static [&'static str] =
+        &["check", "table", "id", "problem", "fix", "fixed"];
+
+    fn cell(&self, column: &str) -> Cell {
+        match column {
+            "check" => Cell::Text(self.check.clone()),
+            "table" => Cell::from(self.table.clone()),
+            "id" => self.id.map(Cell::Int).unwrap_or(Cell::Empty),
+            "problem" => Cell::Text(self.problem.clone()),
+            "fix" => Cell::Text(self.fix.as_ref().to_string()),
+            "fixed" => Cell::Text(if self.fixed { "yes" } else { "no" }.to_string()),
+            _ => Cell::Empty,
+        }
+    }
+}
+
+fn check_column<T: Tabular>(column: &str) -> Result<(), CliError> {
+    if T::COLUMNS.contains(&column) {
+        return Ok(());
+    }
+    UnknownColumnSnafu {
+        column,
+        available: T::COLUMNS.join(","),
+    }
+    .fail()
+}
+
+// Resolves the requested columns, falling back to the entity defaults
+pub fn columns<T: Tabular>(args: &TableArgs) -> Result<Vec<String>, CliError> {
+    match &args.columns {
+        Some(columns) => {
+            for column in columns {
+                check_column::<T>(column)?;
+            }
+            Ok(columns.clone())
+        }
+        None => Ok(T::DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect()),
+    }
+}
+
+pub fn sort<T: Tabular>(items: &mut [T], args: &TableArgs) -> Result<(), CliError> {
+    let column = match &args.sort {
+        Some(column) => column,
+        None => return Ok(()),
+    };
+    check_column::<T>(column)?;
+    items.sort_by(|a, b| {
+        let ordering = a.cell(column).cmp(&b.cell(column));
+        if args.desc {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    Ok(())
+}
+
+// The width to fit tables into, or `None` when not writing to a terminal
+pub fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok())
+    {
+        return Some(columns);
+    }
+    terminal_size::terminal_size().map(|(width, _)| width.0 as usize)
+}
+
+// Splits `text` into its characters, keeping ANSI escape sequences (used to
+// highlight search matches) attached to the following character so they
+// don't count towards the width
+fn visible_chars(text: &str) -> Vec<String> {
+    let mut chars = vec![];
+    let mut pending = String::new();
+    let mut in_escape = false;
+    for c in text.chars() {
+        if c == '\x1b' {
+            in_escape = true;
+        }
+        if in_escape {
+            pending.push(c);
+            in_escape = !c.is_ascii_alphabetic();
+            continue;
+        }
+        pending.push(c);
+        chars.push(std::mem::take(&mut pending));
+    }
+    if !pending.is_empty() {
+        match chars.last_mut() {
+            Some(last) => last.push_str(&pending),
+            None => chars.push(pending),
+        }
+    }
+    chars
+}
+
+fn visible_len(text: &str) -> usize {
+    let mut len = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        if c == '\x1b' {
+            in_escape = true;
+        } else if in_escape {
+            in_escape = !c.is_ascii_alphabetic();
+        } else {
+            len += 1;
+        }
+    }
+    len
+}
+
+// Truncates and pads `text` to exactly `width` visible characters
+fn fit(text: &str, width: usize) -> String {
+    let len = visible_len(text);
+    if len <= width {
+        return format!("{}{}", text, " ".repeat(width - len));
+    }
+    let chars = visible_chars(text);
+    let mut truncated = chars[..width.saturating_sub(1)].concat();
+    truncated = truncated.trim_end().to_string();
+    truncated.push('…');
+    if text.contains('\x1b') {
+        truncated.push_str("\x1b[0m");
+    }
+    let len = visible_len(&truncated);
+    format!("{}{}", truncated, " ".repeat(width.saturating_sub(len)))
+}
+
+// Shrinks the widest columns one character at a time until the table fits
+fn fit_widths(widths: &mut [usize], max_width: usize) {
+    let separators = COLUMN_SEPARATOR.len() * widths.len().saturating_sub(1);
+    while widths.iter().sum::<usize>() + separators > max_width {
+        let widest = widths
+            .iter()
+            .enumerate()
+            .max_by(|(i, a), (j, b)| a.cmp(b).then(j.cmp(i)))
+            .map(|(i, _)| i);
+        match widest {
+            Some(i) if widths[i] > MIN_COLUMN_WIDTH => widths[i] -= 1,
+            _ => break,
+        }
+    }
+}
+
+pub fn render<T: Tabular>(
+    items: &[T],
+    columns: &[String],
+    date_format: &str,
+    max_width: Option<usize>,
+) -> String {
+    let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+    let rows: Vec<Vec<String>> = items
+        .iter()
+        .map(|item| {
+            columns
+                .iter()
+                .map(|column| item.cell(column).render(date_format))
+                .collect()
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = header.iter().map(|h| visible_len(h)).collect();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(visible_len(cell));
+        }
+    }
+    if let Some(max_width) = max_width {
+        fit_widths(&mut widths, max_width);
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| fit(cell, *width))
+            .collect::<Vec<String>>()
+            .join(COLUMN_SEPARATOR);
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: u64, content: &str) -> Note {
+        Note {
+            id,
+            date: NaiveDate::from_ymd_opt(2024, 1, id as u32).unwrap(),
+            time: None,
+            timezone: None,
+            content: content.to_string(),
+            people: vec![],
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let mut notes = vec![note(2, "second"), note(10, "a much longer note")];
+        let args = TableArgs {
+            columns: Some(vec![String::from("id"), String::from("content")]),
+            sort: Some(String::from("id")),
+            desc: true,
+        };
+
+        sort(&mut notes, &args).unwrap();
+        let selected = columns::<Note>(&args).unwrap();
+
+        assert_eq!(
+            "ID  CONTENT\n10  a much longer note\n2   second\n",
+            render(&notes, &selected, "%Y-%m-%d", None)
+        );
+        assert_eq!(
+            "ID  CONTENT\n10  a much…\n2   second\n",
+            render(&notes, &selected, "%Y-%m-%d", Some(12))
+        );
+        assert_eq!("\x1b[1mab…\x1b[0m", fit("\x1b[1mab\x1b[0mcd", 3));
+        assert!(columns::<Note>(&TableArgs {
+            columns: Some(vec![String::from("birthday")]),
+            ..TableArgs::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,435 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use strum_macros::AsRefStr;
+
+use crate::entities::datetime::system_timezone;
+
+pub static DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+pub static DEFAULT_EVENTS_DAYS: u64 = 90;
+pub static DEFAULT_NOTIFY_DAYS: u64 = 1;
+pub static DEFAULT_NOTIFY_INTERVAL: u64 = 300;
+pub static DEFAULT_SMTP_PORT: u16 = 25;
+pub static DEFAULT_JOURNAL_RETENTION: u64 = crate::db::journal::DEFAULT_RETENTION;
+// The implicit profile backed by the top-level `database` and `ics` settings
+pub static DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum ConfigError {
+    #[snafu(display("Error reading config file {}: {}", path.display(), source))]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Invalid config file {}: {}", path.display(), source))]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[snafu(display("Error writing config file {}: {}", path.display(), source))]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Error serialising config: {}", source))]
+    Serialize { source: toml::ser::Error },
+    #[snafu(display("Could not determine the {} directory", kind))]
+    MissingDirectory { kind: String },
+    #[snafu(display("Unknown profile: {}", name))]
+    UnknownProfile { name: String },
+    #[snafu(display("Profile already exists: {}", name))]
+    DuplicateProfile { name: String },
+    #[snafu(display("Invalid profile name: {}", name))]
+    InvalidProfileName { name: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub database: Option<PathBuf>,
+    pub ics: Option<PathBuf>,
+}
+
+// Where `notify` delivers notifications
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, AsRefStr)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Backend {
+    Stdout,
+    // Run through `sh -c`, with the notification in PRM_* variables
+    Command { command: String },
+    Smtp(SmtpConfig),
+}
+
+// A configured backend. Sent notifications are logged under its name, which
+// defaults to its type, so changing its settings doesn't send them again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackendConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub backend: Backend,
+}
+
+impl BackendConfig {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(self.backend.as_ref())
+    }
+}
+
+impl From<Backend> for BackendConfig {
+    fn from(backend: Backend) -> BackendConfig {
+        BackendConfig {
+            name: None,
+            backend,
+        }
+    }
+}
+
+fn default_smtp_port() -> u16 {
+    DEFAULT_SMTP_PORT
+}
+
+// A mail server accepting mail without authentication, usually a local relay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    // How many days ahead to notify about upcoming events
+    pub days: u64,
+    // Seconds between checks when running as a daemon
+    pub interval: u64,
+    pub backends: Vec<BackendConfig>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> NotifyConfig {
+        NotifyConfig {
+            days: DEFAULT_NOTIFY_DAYS,
+            interval: DEFAULT_NOTIFY_INTERVAL,
+            backends: vec![Backend::Stdout.into()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub database: Option<PathBuf>,
+    pub ics: Option<PathBuf>,
+    // The editor to use when neither VISUAL nor EDITOR is set
+    pub editor: Option<String>,
+    // How dates are shown in lists. Dates are always given as 2024-05-01.
+    pub date_format: String,
+    pub events_days: u64,
+    // The IANA timezone of times given without one, the system's if unset
+    pub timezone: Option<Tz>,
+    pub default_profile: Option<String>,
+    // How many operations `undo` can go back, 0 for all of them
+    pub journal_retention: u64,
+    pub notify: NotifyConfig,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            database: None,
+            ics: None,
+            editor: None,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            events_days: DEFAULT_EVENTS_DAYS,
+            timezone: None,
+            default_profile: None,
+            journal_retention: DEFAULT_JOURNAL_RETENTION,
+            notify: NotifyConfig::default(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+// Resolves an XDG base directory, falling back to `$HOME/<fallback>`.
+fn xdg_dir(var: &str, fallback: &str) -> Result<PathBuf, ConfigError> {
+    if let Some(dir) = env::var_os(var).filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    match env::var_os("HOME").filter(|home| !home.is_empty()) {
+        Some(home) => Ok(PathBuf::from(home).join(fallback)),
+        None => MissingDirectorySnafu { kind: var }.fail(),
+    }
+}
+
+pub fn config_dir() -> Result<PathBuf, ConfigError> {
+    Ok(xdg_dir("XDG_CONFIG_HOME", ".config")?.join("prm"))
+}
+
+pub fn data_dir() -> Result<PathBuf, ConfigError> {
+    Ok(xdg_dir("XDG_DATA_HOME", ".local/share")?.join("prm"))
+}
+
+impl Config {
+    // The timezone of times given without one
+    pub fn default_timezone(&self) -> Tz {
+        self.timezone.unwrap_or_else(system_timezone)
+    }
+
+    pub fn path() -> Result<PathBuf, ConfigError> {
+        Ok(config_dir()?.join("config.toml"))
+    }
+
+    // Loads the config from the XDG config directory. A missing file is not
+    // an error: every setting has a default.
+    pub fn load() -> Result<Config, ConfigError> {
+        Config::load_from(&Config::path()?)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config, ConfigError> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let content = fs::read_to_string(path).context(ReadSnafu { path })?;
+        Config::parse(&content).context(ParseSnafu { path })
+    }
+
+    pub fn parse(content: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    pub fn save(&self) -> Result<(), ConfigError> {
+        self.save_to(&Config::path()?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), ConfigError> {
+        let content = toml::to_string(self).context(SerializeSnafu)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(WriteSnafu { path })?;
+        }
+        fs::write(path, content).context(WriteSnafu { path })
+    }
+
+    // The profile to use when none is requested explicitly
+    pub fn active_profile(&self, requested: Option<&str>) -> String {
+        match requested {
+            Some(profile) => profile.to_string(),
+            None => self
+                .default_profile
+                .clone()
+                .unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+        }
+    }
+
+    fn profile(&self, name: &str) -> Result<Profile, ConfigError> {
+        if name == DEFAULT_PROFILE {
+            return Ok(Profile {
+                database: self.database.clone(),
+                ics: self.ics.clone(),
+            });
+        }
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.clone()),
+            None => UnknownProfileSnafu { name }.fail(),
+        }
+    }
+
+    // Profiles other than the default one get their own data subdirectory
+    fn profile_data_dir(name: &str) -> Result<PathBuf, ConfigError> {
+        if name == DEFAULT_PROFILE {
+            return data_dir();
+        }
+        Ok(data_dir()?.join("profiles").join(name))
+    }
+
+    pub fn database_path(&self, profile: &str) -> Result<PathBuf, ConfigError> {
+        match self.profile(profile)?.database {
+            Some(database) => Ok(database),
+            None => Ok(Config::profile_data_dir(profile)?.join("prm.db")),
+        }
+    }
+
+    pub fn ics_path(&self, profile: &str) -> Result<PathBuf, ConfigError> {
+        match self.profile(profile)?.ics {
+            Some(ics) => Ok(ics),
+            None => Ok(Config::profile_data_dir(profile)?.join("calendar.ics")),
+        }
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names = vec![DEFAULT_PROFILE.to_string()];
+        names.extend(self.profiles.keys().cloned());
+        names
+    }
+
+    pub fn add_profile(&mut self, name: &str, profile: Profile) -> Result<(), ConfigError> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return InvalidProfileNameSnafu { name }.fail();
+        }
+        if name == DEFAULT_PROFILE || self.profiles.contains_key(name) {
+            return DuplicateProfileSnafu { name }.fail();
+        }
+        self.profiles.insert(name.to_string(), profile);
+        Ok(())
+    }
+
+    pub fn remove_profile(&mut self, name: &str) -> Result<Profile, ConfigError> {
+        let profile = match self.profiles.remove(name) {
+            Some(profile) => profile,
+            None => return UnknownProfileSnafu { name }.fail(),
+        };
+        if self.default_profile.as_deref() == Some(name) {
+            self.default_profile = None;
+        }
+        Ok(profile)
+    }
+
+    pub fn set_default_profile(&mut self, name: &str) -> Result<(), ConfigError> {
+        self.profile(name)?;
+        self.default_profile = if name == DEFAULT_PROFILE {
+            None
+        } else {
+            Some(name.to_string())
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            "database = \"/tmp/prm.db\"
+editor = \"vim\"
+events_days = 30
+timezone = \"Europe/Lisbon\"
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Config {
+                database: Some(PathBuf::from("/tmp/prm.db")),
+                ics: None,
+                editor: Some(String::from("vim")),
+                date_format: DEFAULT_DATE_FORMAT.to_string(),
+                events_days: 30,
+                timezone: Some(Tz::Europe__Lisbon),
+                default_profile: None,
+                journal_retention: DEFAULT_JOURNAL_RETENTION,
+                notify: NotifyConfig::default(),
+                profiles: BTreeMap::new(),
+            },
+            config
+        );
+    }
+
+    #[test]
+    fn test_notify() {
+        let config = Config::parse(
+            "[notify]
+days = 3
+
+[[notify.backends]]
+type = \"command\"
+command = \"notify-send \\\"$PRM_TITLE\\\"\"
+
+[[notify.backends]]
+name = \"mail\"
+type = \"smtp\"
+host = \"localhost\"
+from = \"prm@example.com\"
+to = [\"me@example.com\"]
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            NotifyConfig {
+                days: 3,
+                interval: DEFAULT_NOTIFY_INTERVAL,
+                backends: vec![
+                    Backend::Command {
+                        command: String::from("notify-send \"$PRM_TITLE\""),
+                    }
+                    .into(),
+                    BackendConfig {
+                        name: Some(String::from("mail")),
+                        backend: Backend::Smtp(SmtpConfig {
+                            host: String::from("localhost"),
+                            port: DEFAULT_SMTP_PORT,
+                            from: String::from("prm@example.com"),
+                            to: vec![String::from("me@example.com")],
+                        }),
+                    },
+                ],
+            },
+            config.notify
+        );
+        assert_eq!(
+            vec!["command", "mail"],
+            config
+                .notify
+                .backends
+                .iter()
+                .map(|b| b.name())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            config,
+            Config::parse(&toml::to_string(&config).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        let mut config = Config::parse(
+            "default_profile = \"work\"
+
+[profiles.work]
+database = \"/tmp/work.db\"
+",
+        )
+        .unwrap();
+
+        assert_eq!("work", config.active_profile(None));
+        assert_eq!("default", config.active_profile(Some("default")));
+        assert_eq!(
+            PathBuf::from("/tmp/work.db"),
+            config.database_path("work").unwrap()
+        );
+        assert!(config.add_profile("work", Profile::default()).is_err());
+        assert!(config.add_profile("bad name", Profile::default()).is_err());
+
+        config.remove_profile("work").unwrap();
+        assert_eq!(None, config.default_profile);
+        assert!(config.database_path("work").is_err());
+
+        let saved = Config::parse(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config, saved);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let config = Config::load_from(Path::new("/nonexistent/prm/config.toml")).unwrap();
+
+        assert_eq!(Config::default(), config);
+    }
+}
@@ -1,52 +1,188 @@
-use rusqlite::{params, Connection};
+use rusqlite::Connection;
+
+pub mod audit;
+pub mod doctor;
+pub mod entity_kind;
+pub mod journal;
+pub mod loader;
+pub mod migrations;
+pub mod pool;
+pub mod transaction;
+pub mod trash;
 
 pub mod db_interface {
+    use std::error::Error;
+    use std::fmt;
+
+    use crate::db::entity_kind::EntityKind;
+    use crate::db::loader::LoadContext;
     use crate::db::Connection;
 
     #[derive(Debug)]
     pub enum DbOperationsError {
-        DuplicateEntry,
-        GenericError,
+        DuplicateEntry {
+            entity: EntityKind,
+            name: String,
+        },
         InvalidStatement {
             sqlite_error: rusqlite::Error,
         },
-        QueryError,
+        QueryError {
+            sqlite_error: rusqlite::Error,
+        },
         RecordError {
             sqlite_error: Option<rusqlite::Error>,
             strum_error: Option<strum::ParseError>,
         },
+        // Rows that could not be read, by id. `sqlite_error` is the first
+        // row's error.
+        InvalidRows {
+            table: String,
+            ids: Vec<u64>,
+            sqlite_error: Box<rusqlite::Error>,
+        },
+        // A query that always returns a row returned none
+        MissingRow {
+            table: String,
+        },
         InitialisationError {
             action: String,
         },
-        UnexpectedMultipleEntries,
+        MigrationError {
+            version: u32,
+            sqlite_error: rusqlite::Error,
+        },
+        InvalidSearchQuery {
+            query: String,
+            sqlite_error: rusqlite::Error,
+        },
+        UnexpectedMultipleEntries {
+            entity: EntityKind,
+            name: String,
+        },
+        PoolError {
+            pool_error: r2d2::Error,
+        },
+        // A write that has to share a transaction with its bookkeeping was
+        // run outside of one
+        OutsideTransaction {
+            action: String,
+        },
+        // An age too large to turn into a date
+        AgeOutOfRange {
+            days: u64,
+        },
+    }
+
+    impl fmt::Display for DbOperationsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                DbOperationsError::DuplicateEntry { entity, name } => {
+                    write!(f, "A {} named {} already exists", entity.as_ref(), name)
+                }
+                DbOperationsError::InvalidStatement { .. } => {
+                    write!(f, "Invalid SQL statement")
+                }
+                DbOperationsError::QueryError { .. } => write!(f, "Query failed"),
+                DbOperationsError::RecordError { .. } => write!(f, "Invalid record"),
+                DbOperationsError::InvalidRows { table, ids, .. } => write!(
+                    f,
+                    "Invalid rows in {}: {}",
+                    table,
+                    ids.iter()
+                        .map(|id| id.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+                DbOperationsError::MissingRow { table } => {
+                    write!(f, "Expected a row in {}, found none", table)
+                }
+                DbOperationsError::InitialisationError { action } => {
+                    write!(f, "Error initialising the database: {}", action)
+                }
+                DbOperationsError::MigrationError { version, .. } => {
+                    write!(f, "Error migrating the database to version {}", version)
+                }
+                DbOperationsError::InvalidSearchQuery { query, .. } => {
+                    write!(f, "Invalid search query: {}", query)
+                }
+                DbOperationsError::UnexpectedMultipleEntries { entity, name } => {
+                    write!(f, "More than one {} named {}", entity.as_ref(), name)
+                }
+                DbOperationsError::PoolError { .. } => {
+                    write!(f, "No database connection available")
+                }
+                DbOperationsError::OutsideTransaction { action } => {
+                    write!(f, "{} has to run inside a transaction", action)
+                }
+                DbOperationsError::AgeOutOfRange { days } => {
+                    write!(f, "{} days ago is out of the supported date range", days)
+                }
+            }
+        }
+    }
+
+    impl Error for DbOperationsError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                DbOperationsError::InvalidStatement { sqlite_error }
+                | DbOperationsError::QueryError { sqlite_error }
+                | DbOperationsError::MigrationError { sqlite_error, .. }
+                | DbOperationsError::InvalidSearchQuery { sqlite_error, .. } => Some(sqlite_error),
+                DbOperationsError::InvalidRows { sqlite_error, .. } => Some(sqlite_error.as_ref()),
+                DbOperationsError::RecordError {
+                    sqlite_error: Some(sqlite_error),
+                    ..
+                } => Some(sqlite_error),
+                DbOperationsError::RecordError {
+                    strum_error: Some(strum_error),
+                    ..
+                } => Some(strum_error),
+                DbOperationsError::PoolError { pool_error } => Some(pool_error),
+                _ => None,
+            }
+        }
     }
 
+    // Writes are atomic: `add`, `remove` and `save` either apply every
+    // statement or none. Pass a `Transaction` or `Savepoint` as `conn` to
+    // group several writes; see `db::transaction::atomic`.
     pub trait DbOperations {
         fn add(&self, conn: &Connection) -> Result<&Self, DbOperationsError>;
         fn remove(&self, conn: &Connection) -> Result<&Self, DbOperationsError>;
         fn save(&self, conn: &Connection) -> Result<&Self, DbOperationsError>;
         fn get_by_id(
             conn: &Connection,
+            load: &mut LoadContext,
             id: u64,
         ) -> Result<Option<crate::entities::Entities>, DbOperationsError>;
-        fn get_all(conn: &Connection) -> Result<Vec<Box<Self>>, DbOperationsError>;
+        fn get_all(
+            conn: &Connection,
+            load: &mut LoadContext,
+        ) -> Result<Vec<Self>, DbOperationsError>
+        where
+            Self: Sized;
     }
 }
 
 pub mod db_helpers {
-    use crate::db::{params, Connection};
+    use crate::db::Connection;
     use crate::db_interface::DbOperationsError;
 
     pub mod notes {
-        use rusqlite::{params, params_from_iter, Connection};
+        use rusqlite::{params, Connection};
 
+        use crate::db::loader::{self, LoadContext, Relations};
         use crate::{db_interface::DbOperationsError, entities::note::Note};
 
         pub fn get_by_content(
             conn: &Connection,
+            load: &mut LoadContext,
             content: String,
         ) -> Result<Vec<Note>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
+            loader::notes(
+                conn,
+                load,
                 "SELECT
                     *
                 FROM
@@ -55,418 +191,123 @@ pub mod db_helpers {
                     content LIKE '%' || ?1 || '%'
                     AND deleted = 0
                 COLLATE NOCASE",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let mut notes: Vec<Note> = vec![];
-
-            let mut rows = match stmt.query([content]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => {
-                            let id = match row.get(0) {
-                                Ok(id) => id,
-                                Err(e) => {
-                                    return Err(DbOperationsError::RecordError {
-                                        sqlite_error: Some(e),
-                                        strum_error: None,
-                                    })
-                                }
-                            };
-                            let date = row.get::<usize, String>(1);
-                            let date = crate::helpers::parse_from_str_ymd(
-                                date.unwrap_or_default().as_str(),
-                            )
-                            .unwrap_or_default();
-                            let content = match row.get(2) {
-                                Ok(content) => content,
-                                Err(e) => {
-                                    return Err(DbOperationsError::RecordError {
-                                        sqlite_error: Some(e),
-                                        strum_error: None,
-                                    })
-                                }
-                            };
-                            let people = crate::db::db_helpers::people::get_by_note(conn, id)?;
-                            notes.push(Note::new(id, date, content, people))
-                        }
-                        None => return Ok(notes),
-                    },
-                    Err(_) => return Err(DbOperationsError::GenericError),
-                }
-            }
+                params![content],
+                Relations::ALL,
+            )
         }
 
         pub fn get_by_person(
             conn: &Connection,
+            load: &mut LoadContext,
             person_id: u64,
         ) -> Result<Vec<Note>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
+            loader::notes(
+                conn,
+                load,
                 "SELECT
-            *
-        FROM
-            people_notes
-        WHERE
-            person_id = ?
-            AND deleted = 0
-        ",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let mut rows = match stmt.query(params![person_id]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let mut note_ids: Vec<u64> = vec![];
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => match row.get(0) {
-                            Ok(row) => note_ids.push(row),
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        },
-                        None => break,
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                }
-            }
-
-            if note_ids.is_empty() {
-                return Ok(vec![]);
-            }
-
-            let vars = crate::helpers::repeat_vars(note_ids.len());
-            let sql = format!("SELECT * FROM notes WHERE id IN ({}) AND deleted = 0", vars);
-            let mut stmt = match conn.prepare(&sql) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let rows = match stmt.query_map(params_from_iter(note_ids.iter()), |row| {
-                Ok(Note::new(
-                    row.get(0)?,
-                    crate::helpers::parse_from_str_ymd(
-                        row.get::<usize, String>(1).unwrap_or_default().as_str(),
-                    )
-                    .unwrap_or_default(),
-                    row.get(2)?,
-                    vec![],
-                ))
-            }) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            let mut notes = vec![];
-            for note in rows {
-                let note = match note {
-                    Ok(note) => note,
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                };
-                notes.push(note);
-            }
-
-            Ok(notes)
+                    n.*
+                FROM
+                    people_notes l JOIN notes n ON n.id = l.note_id
+                WHERE
+                    l.person_id = ?
+                    AND l.deleted = 0
+                    AND n.deleted = 0
+                ORDER BY n.id",
+                params![person_id],
+                Relations::NONE,
+            )
         }
     }
 
     pub mod reminders {
-        use rusqlite::{params, params_from_iter, Connection};
+        use rusqlite::{params, Connection};
 
-        use crate::{
-            db_interface::DbOperationsError,
-            entities::reminder::{RecurringType, Reminder},
-        };
+        use crate::db::loader::{self, LoadContext, Relations};
+        use crate::{db_interface::DbOperationsError, entities::reminder::Reminder};
 
         pub fn get_by_person(
             conn: &Connection,
+            load: &mut LoadContext,
             person: String,
         ) -> Result<Vec<Reminder>, DbOperationsError> {
-            let mut reminders: Vec<Reminder> = vec![];
-            let mut stmt = match conn
-                .prepare("SELECT id FROM people WHERE name = ?1 AND deleted = 0 COLLATE NOCASE")
-            {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            let mut rows = match stmt.query(params![person]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let person_id: u64;
-            match rows.next() {
-                Ok(row) => {
-                    if let Some(row) = row {
-                        person_id = match row.get(0) {
-                            Ok(person_id) => person_id,
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        };
-                        let reminder_ids: Vec<u8> = get_ids_by_person_id(conn, person_id)?;
-
-                        let vars = crate::helpers::repeat_vars(reminder_ids.len());
-                        let sql = format!(
-                            "SELECT * from reminders WHERE id IN ({}) AND deleted = 0",
-                            vars
-                        );
-                        let mut stmt = match conn.prepare(&sql) {
-                            Ok(stmt) => stmt,
-                            Err(e) => {
-                                return Err(DbOperationsError::InvalidStatement { sqlite_error: e })
-                            }
-                        };
-                        let mut rows = match stmt.query(params_from_iter(reminder_ids.iter())) {
-                            Ok(rows) => rows,
-                            Err(_) => return Err(DbOperationsError::QueryError),
-                        };
-
-                        loop {
-                            match rows.next() {
-                                Ok(row) => match row {
-                                    Some(row) => {
-                                        let reminder = Reminder::build_from_sql(
-                                            conn,
-                                            row.get(0),
-                                            row.get(1),
-                                            row.get(2),
-                                            row.get(3),
-                                            row.get(4),
-                                        )?;
-                                        reminders.push(reminder);
-                                    }
-                                    None => break,
-                                },
-                                Err(_) => return Err(DbOperationsError::GenericError),
-                            }
-                        }
-                    }
-                }
-                Err(_) => return Err(DbOperationsError::GenericError),
-            }
-
-            Ok(reminders)
+            loader::reminders(
+                conn,
+                load,
+                "SELECT
+                    r.*
+                FROM
+                    people_reminders l JOIN reminders r ON r.id = l.reminder_id
+                WHERE
+                    l.person_id = (
+                        SELECT id FROM people WHERE name = ?1 AND deleted = 0 COLLATE NOCASE
+                    )
+                    AND l.deleted = 0
+                    AND r.deleted = 0
+                ORDER BY r.id",
+                params![person],
+                Relations::ALL,
+            )
         }
 
         pub fn get_by_person_reminders(
             conn: &Connection,
+            load: &mut LoadContext,
             person_id: u64,
         ) -> Result<Vec<Reminder>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
+            loader::reminders(
+                conn,
+                load,
                 "SELECT
-            reminder_id
-        FROM
-            people_reminders
-        WHERE
-            person_id = ?
-            AND deleted = 0
-        ",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let mut rows = match stmt.query(params![person_id]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let mut reminder_ids: Vec<u64> = vec![];
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => match row.get(0) {
-                            Ok(row) => reminder_ids.push(row),
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        },
-                        None => break,
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                }
-            }
-
-            if reminder_ids.is_empty() {
-                return Ok(vec![]);
-            }
-
-            let vars = crate::helpers::repeat_vars(reminder_ids.len());
-            let sql = format!(
-                "SELECT * FROM reminders WHERE id IN ({}) AND deleted = FALSE",
-                vars
-            );
-            let mut stmt = match conn.prepare(&sql) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let rows = match stmt.query_map(params_from_iter(reminder_ids.iter()), |row| {
-                let recurring_type = match RecurringType::get_by_id(conn, row.get(4)?) {
-                    Ok(recurring_type) => match recurring_type {
-                        Some(recurring_type) => recurring_type,
-                        None => panic!("Recurring Type cannot be None"),
-                    },
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                Ok(Reminder::new(
-                    row.get(0)?,
-                    row.get(1)?,
-                    crate::helpers::parse_from_str_ymd(
-                        row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                    )
-                    .unwrap_or_default(),
-                    row.get(3)?,
-                    recurring_type,
-                    vec![],
-                ))
-            }) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            let mut reminders = vec![];
-            for reminder in rows {
-                let reminder = match reminder {
-                    Ok(reminder) => reminder,
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                };
-                reminders.push(reminder);
-            }
-
-            Ok(reminders)
+                    r.*
+                FROM
+                    people_reminders l JOIN reminders r ON r.id = l.reminder_id
+                WHERE
+                    l.person_id = ?
+                    AND l.deleted = 0
+                    AND r.deleted = 0
+                ORDER BY r.id",
+                params![person_id],
+                Relations::NONE,
+            )
         }
 
         pub fn get_by_name(
             conn: &Connection,
+            load: &mut LoadContext,
             name: &str,
             person: Option<String>,
         ) -> Result<Vec<Reminder>, DbOperationsError> {
-            let mut reminders: Vec<Reminder> = vec![];
-            let mut stmt = match conn
-            .prepare("SELECT * FROM reminders WHERE name LIKE '%' || ?1 || '%' AND deleted = 0 COLLATE NOCASE")
-        {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-            let mut rows = match stmt.query(params![name]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => {
-                            let reminder = Reminder::build_from_sql(
-                                conn,
-                                row.get(0),
-                                row.get(1),
-                                row.get(2),
-                                row.get(3),
-                                row.get(4),
-                            )?;
-                            if let Some(person) = person.clone() {
-                                let people_name: Vec<String> =
-                                    reminder.people.iter().map(|p| p.name.to_owned()).collect();
-                                if people_name.contains(&person) {
-                                    reminders.push(reminder);
-                                }
-                            } else {
-                                reminders.push(reminder);
-                            }
-                        }
-                        None => return Ok(reminders),
-                    },
-                    Err(_) => return Err(DbOperationsError::GenericError),
-                }
+            let mut reminders = loader::reminders(
+                conn, load,
+                "SELECT * FROM reminders WHERE name LIKE '%' || ?1 || '%' AND deleted = 0 COLLATE NOCASE",
+                params![name],
+                Relations::ALL,
+            )?;
+            if let Some(person) = person {
+                reminders.retain(|reminder| reminder.people.iter().any(|p| p.name == person));
             }
+            Ok(reminders)
         }
 
         pub fn get_by_description(
             conn: &Connection,
+            load: &mut LoadContext,
             description: String,
         ) -> Result<Vec<Reminder>, DbOperationsError> {
-            let mut reminders: Vec<Reminder> = vec![];
-            let mut stmt = match conn.prepare(
+            loader::reminders(
+                conn,
+                load,
                 "SELECT
-                *
-            FROM
-                reminders
-            WHERE
-                description LIKE '%' || ?1 || '%'
-                AND deleted = 0
-            COLLATE NOCASE",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            let mut rows = match stmt.query(params![description]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => {
-                            let reminder = Reminder::build_from_sql(
-                                conn,
-                                row.get(0),
-                                row.get(1),
-                                row.get(2),
-                                row.get(3),
-                                row.get(4),
-                            )?;
-                            reminders.push(reminder);
-                        }
-                        None => return Ok(reminders),
-                    },
-                    Err(_) => return Err(DbOperationsError::GenericError),
-                }
-            }
+                    *
+                FROM
+                    reminders
+                WHERE
+                    description LIKE '%' || ?1 || '%'
+                    AND deleted = 0
+                COLLATE NOCASE",
+                params![description],
+                Relations::ALL,
+            )
         }
 
         pub fn get_ids_by_person_id(
@@ -482,7 +323,7 @@ pub mod db_helpers {
         };
             let mut rows = match stmt.query(params![person_id]) {
                 Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             };
 
             loop {
@@ -515,214 +356,80 @@ pub mod db_helpers {
     }
 
     pub mod contact_info {
-        use rusqlite::{params, Connection};
+        use rusqlite::Connection;
 
-        use crate::{db_interface::DbOperationsError, entities::person::ContactInfoType};
+        use crate::db::loader::{self, LoadContext, Relations};
+        use crate::{
+            db_interface::DbOperationsError,
+            entities::person::{ContactInfo, Person},
+        };
 
         pub fn get_by_person(
             conn: &Connection,
+            load: &mut LoadContext,
             person_id: u64,
-        ) -> Result<Vec<crate::entities::person::ContactInfo>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
-                "SELECT
-                *
-            FROM
-                contact_info
-            WHERE
-                person_id = ?
-                AND deleted = FALSE
-            ",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let mut contact_info_vec: Vec<crate::entities::person::ContactInfo> = vec![];
-            let rows = match stmt.query_map(params![person_id], |row| {
-                let contact_info_type = match ContactInfoType::get_by_id(conn, row.get(2)?) {
-                    Ok(contact_info_type) => match contact_info_type {
-                        Some(contact_info_type) => contact_info_type,
-                        None => panic!("Contact Info Type cannot be None"),
-                    },
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
+        ) -> Result<Vec<ContactInfo>, DbOperationsError> {
+            let mut people = vec![Person::new(
+                person_id,
+                String::new(),
+                None,
+                vec![],
+                vec![],
+                vec![],
+            )];
+            loader::load_people(
+                conn,
+                load,
+                &mut people,
+                Relations {
+                    contact_info: true,
+                    ..Relations::NONE
+                },
+            )?;
+            Ok(people.remove(0).contact_info)
+        }
+    }
 
-                let contact_info_details: String = row.get(3)?;
-                let contact_info = match contact_info_type {
-                    ContactInfoType::Phone(_) => ContactInfoType::Phone(contact_info_details),
-                    ContactInfoType::WhatsApp(_) => ContactInfoType::WhatsApp(contact_info_details),
-                    ContactInfoType::Email(_) => ContactInfoType::Email(contact_info_details),
-                };
+    pub mod activities {
+        use rusqlite::{params, Connection};
 
-                Ok(crate::entities::person::ContactInfo::new(
-                    row.get(0)?,
-                    row.get(1)?,
-                    contact_info,
-                ))
-            }) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            for contact_info in rows {
-                let contact_info = match contact_info {
-                    Ok(contact_info) => contact_info,
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                };
-                contact_info_vec.push(contact_info);
-            }
-
-            Ok(contact_info_vec)
-        }
-    }
-
-    pub mod activities {
-        use rusqlite::{params, params_from_iter, Connection};
-
-        use crate::{db_interface::DbOperationsError, entities::activity::Activity};
+        use crate::db::loader::{self, LoadContext, Relations};
+        use crate::{db_interface::DbOperationsError, entities::activity::Activity};
 
         pub fn get_by_person(
             conn: &Connection,
+            load: &mut LoadContext,
             person_id: u64,
         ) -> Result<Vec<Activity>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
+            loader::activities(
+                conn,
+                load,
                 "SELECT
-                activity_id
-            FROM
-                people_activities
-            WHERE
-                person_id = ?
-                AND deleted = 0
-            ",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let mut rows = match stmt.query(params![person_id]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let mut activity_ids: Vec<u64> = vec![];
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => match row.get(0) {
-                            Ok(row) => activity_ids.push(row),
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        },
-                        None => break,
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                }
-            }
-
-            if activity_ids.is_empty() {
-                return Ok(vec![]);
-            }
-
-            let vars = crate::helpers::repeat_vars(activity_ids.len());
-            let sql = format!(
-                "SELECT * FROM activities WHERE id IN ({}) AND deleted = 0",
-                vars
-            );
-            let mut stmt = match conn.prepare(&sql) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let rows = match stmt.query_map(params_from_iter(activity_ids.iter()), |row| {
-                let activity_id = row.get(0)?;
-                let people =
-                    match crate::db_helpers::people::get_by_activity(conn, activity_id, false) {
-                        Ok(people) => people,
-                        Err(e) => {
-                            let sqlite_error = match e {
-                                DbOperationsError::InvalidStatement { sqlite_error } => {
-                                    sqlite_error
-                                }
-                                other => panic!("Unexpected error type: {:#?}", other),
-                            };
-                            return Err(sqlite_error);
-                        }
-                    };
-                let activity_type =
-                    match crate::entities::activity::ActivityType::get_by_id(conn, row.get(2)?) {
-                        Ok(activity_type) => match activity_type {
-                            Some(activity_type) => activity_type,
-                            None => panic!("Activity type cannot be None"),
-                        },
-                        Err(e) => {
-                            let sqlite_error = match e {
-                                DbOperationsError::InvalidStatement { sqlite_error } => {
-                                    sqlite_error
-                                }
-                                other => panic!("Unexpected error type: {:#?}", other),
-                            };
-                            return Err(sqlite_error);
-                        }
-                    };
-                Ok(Activity::new(
-                    activity_id,
-                    row.get(1)?,
-                    activity_type,
-                    crate::helpers::parse_from_str_ymd(
-                        row.get::<usize, String>(3).unwrap_or_default().as_str(),
-                    )
-                    .unwrap_or_default(),
-                    row.get(4)?,
-                    people,
-                ))
-            }) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            let mut activities = vec![];
-            for activity in rows {
-                let activity = match activity {
-                    Ok(activity) => activity,
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                };
-                activities.push(activity);
-            }
-
-            Ok(activities)
+                    a.*
+                FROM
+                    people_activities l JOIN activities a ON a.id = l.activity_id
+                WHERE
+                    l.person_id = ?
+                    AND l.deleted = 0
+                    AND a.deleted = 0
+                ORDER BY a.id",
+                params![person_id],
+                Relations {
+                    activities: false,
+                    ..Relations::ALL
+                },
+            )
         }
 
         pub fn get_by_name(
             conn: &Connection,
+            load: &mut LoadContext,
             name: String,
             person: Option<String>,
         ) -> Result<Vec<Activity>, DbOperationsError> {
-            let mut activities: Vec<Activity> = vec![];
-            let mut stmt = match conn.prepare(
+            let mut activities = loader::activities(
+                conn,
+                load,
                 "
                     SELECT
                         *
@@ -732,129 +439,38 @@ pub mod db_helpers {
                         name LIKE '%' || ?1 || '%' AND
                         deleted = 0
                     COLLATE NOCASE",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            let mut rows = match stmt.query(params![name]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => {
-                            let activity = Activity::build_from_sql(
-                                conn,
-                                row.get(0),
-                                row.get(1),
-                                row.get(2),
-                                row.get::<usize, String>(3),
-                                row.get(4),
-                            )?;
-                            if let Some(person) = person.clone() {
-                                let people_name: Vec<String> =
-                                    activity.people.iter().map(|p| p.name.to_owned()).collect();
-                                if people_name.contains(&person) {
-                                    activities.push(activity);
-                                }
-                            } else {
-                                activities.push(activity);
-                            }
-                        }
-                        None => return Ok(activities),
-                    },
-                    Err(_) => return Err(DbOperationsError::GenericError),
-                }
+                params![name],
+                Relations::ALL,
+            )?;
+            if let Some(person) = person {
+                activities.retain(|activity| activity.people.iter().any(|p| p.name == person));
             }
+            Ok(activities)
         }
 
         pub fn get_by_person_name(
             conn: &Connection,
+            load: &mut LoadContext,
             person: String,
         ) -> Result<Vec<Activity>, DbOperationsError> {
-            let mut activities: Vec<Activity> = vec![];
-            let mut stmt = match conn.prepare(
+            loader::activities(
+                conn,
+                load,
                 "
                     SELECT
-                        id
+                        a.*
                     FROM
-                        people
+                        people_activities l JOIN activities a ON a.id = l.activity_id
                     WHERE
-                        name = ?1 AND
-                        deleted = 0
-                    COLLATE NOCASE",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            let mut rows = match stmt.query(params![person]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let person_id: u64;
-            match rows.next() {
-                Ok(row) => {
-                    if let Some(row) = row {
-                        person_id = match row.get(0) {
-                            Ok(person_id) => person_id,
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        };
-                        let activity_ids = get_ids_by_person_id(conn, person_id)?;
-
-                        let vars = crate::helpers::repeat_vars(activity_ids.len());
-                        let sql = format!(
-                            "SELECT
-                                *
-                            FROM
-                                activities
-                            WHERE
-                                id IN ({}) AND
-                            deleted = 0",
-                            vars
-                        );
-                        let mut stmt = match conn.prepare(&sql) {
-                            Ok(stmt) => stmt,
-                            Err(e) => {
-                                return Err(DbOperationsError::InvalidStatement { sqlite_error: e })
-                            }
-                        };
-
-                        let mut rows = match stmt.query(params_from_iter(activity_ids.iter())) {
-                            Ok(rows) => rows,
-                            Err(_) => return Err(DbOperationsError::QueryError),
-                        };
-
-                        loop {
-                            match rows.next() {
-                                Ok(row) => match row {
-                                    Some(row) => {
-                                        let activity = Activity::build_from_sql(
-                                            conn,
-                                            row.get(0),
-                                            row.get(1),
-                                            row.get(2),
-                                            row.get::<usize, String>(3),
-                                            row.get(4),
-                                        )?;
-                                        activities.push(activity);
-                                    }
-                                    None => break,
-                                },
-                                Err(_) => return Err(DbOperationsError::GenericError),
-                            }
-                        }
-                    }
-                }
-                Err(_) => return Err(DbOperationsError::GenericError),
-            }
-
-            Ok(activities)
+                        l.person_id = (
+                            SELECT id FROM people WHERE name = ?1 AND deleted = 0 COLLATE NOCASE
+                        ) AND
+                        l.deleted = 0 AND
+                        a.deleted = 0
+                    ORDER BY a.id",
+                params![person],
+                Relations::ALL,
+            )
         }
 
         pub fn get_ids_by_person_id(
@@ -877,7 +493,7 @@ pub mod db_helpers {
             };
             let mut rows = match stmt.query(params![person_id]) {
                 Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             };
 
             loop {
@@ -909,10 +525,12 @@ pub mod db_helpers {
 
         pub fn get_by_content(
             conn: &Connection,
+            load: &mut LoadContext,
             content: String,
         ) -> Result<Vec<Activity>, DbOperationsError> {
-            let mut activities: Vec<Activity> = vec![];
-            let mut stmt = match conn.prepare(
+            loader::activities(
+                conn,
+                load,
                 "
                     SELECT
                         *
@@ -922,200 +540,58 @@ pub mod db_helpers {
                         content LIKE '%' || ?1 || '%' AND
                         deleted = 0
                     COLLATE NOCASE",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            let mut rows = match stmt.query(params![content]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => {
-                            let activity = Activity::build_from_sql(
-                                conn,
-                                row.get(0),
-                                row.get(1),
-                                row.get(2),
-                                row.get::<usize, String>(3),
-                                row.get(4),
-                            )?;
-                            activities.push(activity);
-                        }
-                        None => return Ok(activities),
-                    },
-                    Err(_) => return Err(DbOperationsError::GenericError),
-                }
-            }
+                params![content],
+                Relations::ALL,
+            )
         }
 
         pub fn get_by_activity_type(
             conn: &Connection,
+            load: &mut LoadContext,
             activity_type: String,
         ) -> Result<Vec<Activity>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
-                "
-                SELECT
-                    id
-                FROM
-                    activity_types
-                WHERE
-                    type = ?
-                COLLATE NOCASE",
-            ) {
-                Ok(stmt) => stmt,
-                Err(_) => return Err(DbOperationsError::GenericError),
-            };
-            let mut rows = match stmt.query(params![activity_type]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let mut types: Vec<u32> = Vec::new();
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => match row.get(0) {
-                            Ok(row) => types.push(row),
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        },
-                        None => break,
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                }
-            }
-
-            let mut activities: Vec<Activity> = vec![];
-            let mut stmt = match conn.prepare(
+            loader::activities(
+                conn,
+                load,
                 "
                     SELECT
                         *
                     FROM
                         activities
                     WHERE
-                        type = ?1 AND
-                        deleted = 0
-                    COLLATE NOCASE",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            let mut rows = match stmt.query(params![types[0]]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => {
-                            let activity = Activity::build_from_sql(
-                                conn,
-                                row.get(0),
-                                row.get(1),
-                                row.get(2),
-                                row.get::<usize, String>(3),
-                                row.get(4),
-                            )?;
-                            activities.push(activity);
-                        }
-                        None => return Ok(activities),
-                    },
-                    Err(_) => return Err(DbOperationsError::GenericError),
-                }
-            }
+                        type = (
+                            SELECT id FROM activity_types WHERE type = ?1 COLLATE NOCASE
+                        ) AND
+                        deleted = 0",
+                params![activity_type],
+                Relations::ALL,
+            )
         }
     }
 
     pub mod people {
         use rusqlite::{params, params_from_iter, Connection};
 
+        use crate::db::loader::{self, LoadContext, Relations};
         use crate::{db_interface::DbOperationsError, entities::person::Person};
 
-        use super::{activities, contact_info, notes, reminders};
-
         pub fn get_by_name(
             conn: &Connection,
+            load: &mut LoadContext,
             name: String,
         ) -> Result<Vec<Person>, DbOperationsError> {
-            let mut people: Vec<Person> = vec![];
-            let mut stmt = match conn
-                .prepare("SELECT * FROM people WHERE name LIKE '%' || ?1 || '%' AND deleted = 0")
-            {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            let mut rows = match stmt.query(params![name]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => {
-                            let person_id = match row.get(0) {
-                                Ok(person_id) => person_id,
-                                Err(e) => {
-                                    return Err(DbOperationsError::RecordError {
-                                        sqlite_error: Some(e),
-                                        strum_error: None,
-                                    })
-                                }
-                            };
-                            let name = match row.get(1) {
-                                Ok(name) => name,
-                                Err(e) => {
-                                    return Err(DbOperationsError::RecordError {
-                                        sqlite_error: Some(e),
-                                        strum_error: None,
-                                    })
-                                }
-                            };
-                            let notes = notes::get_by_person(conn, person_id)?;
-                            let reminders = reminders::get_by_person_reminders(conn, person_id)?;
-                            let contact_info = contact_info::get_by_person(conn, person_id)?;
-                            let activities = activities::get_by_person(conn, person_id)?;
-
-                            people.push(Person {
-                                id: person_id,
-                                name,
-                                birthday: Some(
-                                    crate::helpers::parse_from_str_ymd(
-                                        row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                                    )
-                                    .unwrap_or_default(),
-                                ),
-                                contact_info,
-                                activities,
-                                reminders,
-                                notes,
-                            })
-                        }
-                        None => return Ok(people),
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                }
-            }
+            loader::people(
+                conn,
+                load,
+                "SELECT * FROM people WHERE name LIKE '%' || ?1 || '%' AND deleted = 0",
+                params![name],
+                Relations::ALL,
+            )
         }
 
         pub fn get_by_names(
             conn: &Connection,
+            load: &mut LoadContext,
             names: Vec<String>,
         ) -> Result<Vec<Person>, DbOperationsError> {
             if names.is_empty() {
@@ -1127,474 +603,94 @@ pub mod db_helpers {
                 "SELECT * FROM people WHERE name IN ({}) AND deleted = 0 COLLATE NOCASE",
                 vars
             );
-
-            let mut people = vec![];
-            let mut stmt = match conn.prepare(&sql) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            let rows = match stmt.query_map(params_from_iter(names.iter()), |row| {
-                // FIXME populate contact_info and activities
-                Ok(Person::new(
-                    row.get(0)?,
-                    row.get(1)?,
-                    Some(
-                        crate::helpers::parse_from_str_ymd(
-                            row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                        )
-                        .unwrap_or_default(),
-                    ),
-                    vec![],
-                    vec![],
-                    vec![],
-                ))
-            }) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            for person in rows.into_iter() {
-                let person = match person {
-                    Ok(person) => person,
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                };
-                people.push(person);
-            }
-
-            Ok(people)
+            loader::people(
+                conn,
+                load,
+                &sql,
+                params_from_iter(names.iter()),
+                Relations::ALL,
+            )
         }
 
         pub fn get_by_reminder(
             conn: &Connection,
+            load: &mut LoadContext,
             reminder_id: u64,
         ) -> Result<Vec<Person>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
-                "SELECT
-                        person_id
-                    FROM
-                        people_reminders
-                    WHERE
-                        reminder_id = ?
-                        AND deleted = 0
-            ",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let mut rows = match stmt.query(params![reminder_id]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let mut people_ids: Vec<u64> = vec![];
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => match row.get(0) {
-                            Ok(row) => people_ids.push(row),
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        },
-                        None => break,
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                }
-            }
-
-            if people_ids.is_empty() {
-                return Ok(vec![]);
-            }
-
-            let vars = crate::helpers::repeat_vars(people_ids.len());
-            let sql = format!(
-                "SELECT * FROM people WHERE id IN ({}) AND deleted = 0",
-                vars
-            );
-            let mut stmt = match conn.prepare(&sql) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let rows = match stmt.query_map(params_from_iter(people_ids.iter()), |row| {
-                let person_id = row.get(0)?;
-                let notes = match notes::get_by_person(conn, person_id) {
-                    Ok(notes) => notes,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let reminders = match reminders::get_by_person_reminders(conn, person_id) {
-                    Ok(reminders) => reminders,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let contact_info = match contact_info::get_by_person(conn, person_id) {
-                    Ok(contact_info) => contact_info,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let activities = match activities::get_by_person(conn, person_id) {
-                    Ok(activities) => activities,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                Ok(Person {
-                    id: person_id,
-                    name: row.get(1)?,
-                    birthday: Some(
-                        crate::helpers::parse_from_str_ymd(
-                            row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                        )
-                        .unwrap_or_default(),
-                    ),
-                    contact_info,
-                    activities,
-                    reminders,
-                    notes,
-                })
-            }) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            let mut activities = vec![];
-            for activity in rows {
-                let activity = match activity {
-                    Ok(activity) => activity,
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                };
-                activities.push(activity);
-            }
-
-            Ok(activities)
+            by_link(
+                conn,
+                load,
+                "people_reminders",
+                "reminder_id",
+                reminder_id,
+                Relations::ALL,
+            )
         }
 
         pub fn get_by_activity(
             conn: &Connection,
+            load: &mut LoadContext,
             activity_id: u64,
             recurse: bool,
         ) -> Result<Vec<Person>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
-                "SELECT
-                        person_id
-                    FROM
-                        people_activities
-                    WHERE
-                        activity_id = ?
-                        AND deleted = 0
-            ",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let mut rows = match stmt.query(params![activity_id]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let mut people_ids: Vec<u64> = vec![];
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => match row.get(0) {
-                            Ok(row) => people_ids.push(row),
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        },
-                        None => break,
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                }
-            }
-
-            if people_ids.is_empty() {
-                return Ok(vec![]);
-            }
+            let relations = Relations {
+                activities: recurse,
+                ..Relations::ALL
+            };
+            by_link(
+                conn,
+                load,
+                "people_activities",
+                "activity_id",
+                activity_id,
+                relations,
+            )
+        }
 
-            let vars = crate::helpers::repeat_vars(people_ids.len());
+        fn by_link(
+            conn: &Connection,
+            load: &mut LoadContext,
+            link_table: &str,
+            column: &str,
+            id: u64,
+            relations: Relations,
+        ) -> Result<Vec<Person>, DbOperationsError> {
             let sql = format!(
-                "SELECT * FROM people WHERE id IN ({}) AND deleted = 0",
-                vars
+                "SELECT
+                    p.*
+                FROM
+                    {} l JOIN people p ON p.id = l.person_id
+                WHERE
+                    l.{} = ?
+                    AND l.deleted = 0
+                    AND p.deleted = 0
+                ORDER BY p.id",
+                link_table, column
             );
-            let mut stmt = match conn.prepare(&sql) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let rows = match stmt.query_map(params_from_iter(people_ids.iter()), |row| {
-                let person_id = row.get(0)?;
-                let notes = match notes::get_by_person(conn, person_id) {
-                    Ok(notes) => notes,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let reminders = match reminders::get_by_person_reminders(conn, person_id) {
-                    Ok(reminders) => reminders,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let contact_info = match contact_info::get_by_person(conn, person_id) {
-                    Ok(contact_info) => contact_info,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let mut activities: Vec<crate::entities::activity::Activity> = vec![];
-                if recurse {
-                    activities = match activities::get_by_person(conn, person_id) {
-                        Ok(activities) => activities,
-                        Err(e) => {
-                            let sqlite_error = match e {
-                                DbOperationsError::InvalidStatement { sqlite_error } => {
-                                    sqlite_error
-                                }
-                                other => panic!("Unexpected error type: {:#?}", other),
-                            };
-                            return Err(sqlite_error);
-                        }
-                    };
-                }
-                Ok(Person {
-                    id: person_id,
-                    name: row.get(1)?,
-                    birthday: Some(
-                        crate::helpers::parse_from_str_ymd(
-                            row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                        )
-                        .unwrap_or_default(),
-                    ),
-                    contact_info,
-                    activities,
-                    reminders,
-                    notes,
-                })
-            }) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            let mut people = vec![];
-            for person in rows {
-                let person = match person {
-                    Ok(person) => person,
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                };
-                people.push(person);
-            }
-
-            Ok(people)
+            loader::people(conn, load, &sql, params![id], relations)
         }
 
         pub fn get_by_note(
             conn: &Connection,
+            load: &mut LoadContext,
             note_id: u64,
         ) -> Result<Vec<Person>, DbOperationsError> {
-            let mut stmt = match conn.prepare(
-                "SELECT
-                        person_id
-                    FROM
-                        people_notes
-                    WHERE
-                        note_id = ?
-                        AND deleted = 0
-                    ",
-            ) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let mut rows = match stmt.query(params![note_id]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let mut people_ids: Vec<u64> = vec![];
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => match row.get(0) {
-                            Ok(row) => people_ids.push(row),
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        },
-                        None => break,
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                }
-            }
-
-            if people_ids.is_empty() {
-                return Ok(vec![]);
-            }
-
-            let vars = crate::helpers::repeat_vars(people_ids.len());
-            let sql = format!(
-                "SELECT * FROM people WHERE id IN ({}) AND deleted = 0",
-                vars
-            );
-            let mut stmt = match conn.prepare(&sql) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-
-            let rows = match stmt.query_map(params_from_iter(people_ids.iter()), |row| {
-                let person_id = row.get(0)?;
-                let notes = match notes::get_by_person(conn, person_id) {
-                    Ok(notes) => notes,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let reminders = match reminders::get_by_person_reminders(conn, person_id) {
-                    Ok(reminders) => reminders,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let contact_info = match contact_info::get_by_person(conn, person_id) {
-                    Ok(contact_info) => contact_info,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                let activities = match activities::get_by_person(conn, person_id) {
-                    Ok(activities) => activities,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-                Ok(Person {
-                    id: person_id,
-                    name: row.get(1)?,
-                    birthday: Some(
-                        crate::helpers::parse_from_str_ymd(
-                            row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                        )
-                        .unwrap_or_default(),
-                    ),
-                    contact_info,
-                    activities,
-                    reminders,
-                    notes,
-                })
-            }) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-
-            let mut notes = vec![];
-            for note in rows {
-                let note = match note {
-                    Ok(note) => note,
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
-                };
-                notes.push(note);
-            }
-
-            Ok(notes)
+            by_link(
+                conn,
+                load,
+                "people_notes",
+                "note_id",
+                note_id,
+                Relations::ALL,
+            )
         }
 
         pub fn get_by_name_and_birthday(
             conn: &Connection,
+            load: &mut LoadContext,
             name: Option<String>,
             birthday: Option<String>,
         ) -> Result<Vec<Person>, DbOperationsError> {
-            let mut people: Vec<Person> = vec![];
             let mut query = String::from("SELECT * FROM people WHERE deleted = 0");
             let mut name_present: bool = false;
             let mut birthday_present: bool = false;
@@ -1620,10 +716,6 @@ pub mod db_helpers {
             query.push_str(" AND deleted = 0");
             query.push_str(" COLLATE NOCASE");
 
-            let mut stmt = match conn.prepare(query.as_str()) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
             let mut query_params = vec![];
             if name_present {
                 query_params.push(name_some.as_str());
@@ -1631,191 +723,157 @@ pub mod db_helpers {
             if birthday_present {
                 query_params.push(birthday_some.as_str());
             }
-            let mut rows = match stmt.query(params_from_iter(query_params)) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
+            loader::people(
+                conn,
+                load,
+                query.as_str(),
+                params_from_iter(query_params),
+                Relations::ALL,
+            )
+        }
+    }
+
+    pub mod search {
+        use rusqlite::{params, Connection};
+        use serde::Serialize;
+
+        use crate::db_interface::DbOperationsError;
+
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        pub struct SearchResult {
+            pub entity: String,
+            pub id: u64,
+            pub title: String,
+            pub snippet: String,
+            pub rank: f64,
+        }
+
+        // Runs an FTS5 query against every indexed entity, best matches first.
+        // `query` accepts the full FTS5 syntax: "phrases", prefix* and
+        // AND/OR/NOT. Matches in the snippet are wrapped in `highlight`.
+        // Contact info hits are reported as the person they belong to, and
+        // an entity matched several times is only listed at its best rank.
+        pub fn search(
+            conn: &Connection,
+            query: &str,
+            limit: u64,
+            highlight: (&str, &str),
+        ) -> Result<Vec<SearchResult>, DbOperationsError> {
+            let mut stmt = match conn.prepare(
+                // The hits are materialized because snippet() can't run inside an
+                // aggregate; with MIN, SQLite takes the other columns from the best row
+                "WITH hits AS MATERIALIZED (
+                    SELECT
+                        CASE entity WHEN 'contact_info' THEN 'person' ELSE entity END AS entity,
+                        CASE entity
+                            WHEN 'contact_info' THEN
+                                (SELECT person_id FROM contact_info WHERE id = entity_id)
+                            ELSE entity_id
+                        END AS id,
+                        CASE entity
+                            WHEN 'contact_info' THEN
+                                (SELECT people.name FROM people
+                                JOIN contact_info ON contact_info.person_id = people.id
+                                WHERE contact_info.id = entity_id)
+                            ELSE title
+                        END AS title,
+                        snippet(search_index, -1, ?2, ?3, '…', 12) AS snippet,
+                        rank
+                    FROM
+                        search_index
+                    WHERE
+                        search_index MATCH ?1
+                )
+                SELECT entity, id, title, snippet, MIN(rank) AS best
+                FROM hits
+                GROUP BY
+                    entity, id
+                ORDER BY
+                    best
+                LIMIT ?4",
+            ) {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => {
-                            let person_id = match row.get(0) {
-                                Ok(person_id) => person_id,
-                                Err(e) => {
-                                    return Err(DbOperationsError::RecordError {
-                                        sqlite_error: Some(e),
-                                        strum_error: None,
-                                    })
-                                }
-                            };
-                            let name = match row.get(1) {
-                                Ok(name) => name,
-                                Err(e) => {
-                                    return Err(DbOperationsError::RecordError {
-                                        sqlite_error: Some(e),
-                                        strum_error: None,
-                                    })
-                                }
-                            };
-                            let notes = notes::get_by_person(conn, person_id)?;
-                            let reminders = reminders::get_by_person_reminders(conn, person_id)?;
-                            let contact_info = contact_info::get_by_person(conn, person_id)?;
-                            let activities = activities::get_by_person(conn, person_id)?;
 
-                            people.push(Person {
-                                id: person_id,
-                                name,
-                                birthday: Some(
-                                    crate::helpers::parse_from_str_ymd(
-                                        row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                                    )
-                                    .unwrap_or_default(),
-                                ),
-                                contact_info,
-                                activities,
-                                reminders,
-                                notes,
-                            })
-                        }
-                        None => return Ok(people),
-                    },
+            let rows =
+                match stmt.query_map(params![query, highlight.0, highlight.1, limit], |row| {
+                    Ok(SearchResult {
+                        entity: row.get(0)?,
+                        id: row.get(1)?,
+                        title: row.get(2)?,
+                        snippet: row.get(3)?,
+                        rank: row.get(4)?,
+                    })
+                }) {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        return Err(DbOperationsError::InvalidSearchQuery {
+                            query: query.to_string(),
+                            sqlite_error: e,
+                        })
+                    }
+                };
+
+            let mut results = vec![];
+            for row in rows {
+                match row {
+                    Ok(result) => results.push(result),
+                    // FTS5 only reports syntax errors once the query is stepped
                     Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
+                        return Err(DbOperationsError::InvalidSearchQuery {
+                            query: query.to_string(),
+                            sqlite_error: e,
                         })
                     }
                 }
             }
+            Ok(results)
         }
     }
 
     pub fn init_db(conn: &Connection) -> Result<(), DbOperationsError> {
-        let sql_create_statements = vec![
-            "CREATE TABLE people (
-            id INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            birthday TEXT,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE activities (
-            id INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            type INTEGER NOT NULL,
-            date TEXT NOT NULL,
-            content TEXT,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE reminders (
-            id INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            date TEXT NOT NULL,
-            description TEXT,
-            recurring INTEGER NOT NULL,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE notes (
-            id INTEGER PRIMARY KEY,
-            date TEXT NOT NULL,
-            content TEXT NOT NULL,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE contact_info (
-            id INTEGER PRIMARY KEY,
-            person_id INTEGER NOT NULL,
-            contact_info_type_id INTEGER NOT NULL,
-            contact_info_details TEXT,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE contact_info_types (
-            id INTEGER PRIMARY KEY,
-            type TEXT NOT NULL,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE people_activities (
-            id INTEGER PRIMARY KEY,
-            person_id INTEGER NOT NULL,
-            activity_id INTEGER NOT NULL,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE people_reminders (
-            id INTEGER PRIMARY KEY,
-            person_id INTEGER NOT NULL,
-            reminder_id INTEGER NOT NULL,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE people_notes (
-            id INTEGER PRIMARY KEY,
-            person_id INTEGER NOT NULL,
-            note_id INTEGER NOT NULL,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE activity_types (
-            id INTEGER PRIMARY KEY,
-            type TEXT NOT NULL,
-            deleted INTEGER NOT NULL
-        );",
-            "CREATE TABLE recurring_types (
-            id INTEGER PRIMARY KEY,
-            type TEXT NOT NULL,
-            deleted INTEGER NOT NULL
-        );",
-        ];
-        for query in sql_create_statements {
-            let mut stmt = match conn.prepare(query) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            match stmt.execute(params![]) {
-                Ok(_) => println!("Database table created"),
-                Err(error) => {
-                    println!("Error creating database tables: {}", error);
-                    return Err(DbOperationsError::InitialisationError {
-                        action: String::from("create"),
-                    });
-                }
-            };
-        }
-        let sql_populate_statements = vec![
-            "INSERT INTO contact_info_types (type, deleted)
-         VALUES
-            ('Phone', FALSE),
-            ('WhatsApp', FALSE),
-            ('Email', FALSE)
-        ",
-            "INSERT INTO activity_types (type, deleted)
-         VALUES
-            ('Phone', FALSE),
-            ('InPerson', FALSE),
-            ('Online', FALSE)
-        ",
-            "INSERT INTO recurring_types (type, deleted)
-         VALUES
-            ('OneTime', FALSE),
-            ('Daily', FALSE),
-            ('Weekly', FALSE),
-            ('Fortnightly', FALSE),
-            ('Monthly', FALSE),
-            ('Quarterly', FALSE),
-            ('Biannual', FALSE),
-            ('Yearly', FALSE)
-        ",
-        ];
-        for query in sql_populate_statements {
-            let mut stmt = match conn.prepare(query) {
-                Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-            };
-            match stmt.execute(params![]) {
-                Ok(_) => println!("Database table populated"),
-                Err(error) => {
-                    println!("Error populating database tables: {}", error);
-                    return Err(DbOperationsError::InitialisationError {
-                        action: String::from("insert"),
-                    });
-                }
-            };
-        }
+        crate::db::migrations::migrate(conn)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::db_helpers::search::search;
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO people (name, birthday, deleted) VALUES ('Zeh', NULL, FALSE);
+            INSERT INTO contact_info (person_id, contact_info_type_id, contact_info_details, deleted)
+                VALUES (1, 3, 'zeh@lisboa.pt', FALSE);
+            INSERT INTO activities (name, type, date, content, deleted)
+                VALUES ('Dinner', 2, '2024-01-01', 'We talked about Lisbon', FALSE);
+            INSERT INTO notes (date, content, deleted)
+                VALUES ('2024-01-02', 'Lisbon trip ideas', FALSE);
+            UPDATE notes SET deleted = TRUE WHERE id = 1;",
+        )
+        .unwrap();
+
+        let results = search(&conn, "lisbon", 10, ("[", "]")).unwrap();
+        assert_eq!(1, results.len());
+        assert_eq!("activity", results[0].entity);
+        assert_eq!("We talked about [Lisbon]", results[0].snippet);
+
+        // Zeh matches both by name and by contact info
+        let results = search(&conn, "lisb* OR zeh", 10, ("[", "]")).unwrap();
+        let mut found: Vec<&str> = results.iter().map(|r| r.entity.as_str()).collect();
+        found.sort_unstable();
+        assert_eq!(vec!["activity", "person"], found);
+        assert!(results.iter().all(|r| r.id == 1));
+
+        assert!(search(&conn, "\"talked Lisbon\"", 10, ("[", "]"))
+            .unwrap()
+            .is_empty());
+        assert!(search(&conn, "AND", 10, ("[", "]")).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::db::entity_kind::EntityKind;
+use crate::db::journal::Change;
+use crate::db_interface::DbOperationsError;
+
+// Bookkeeping columns that change on most writes and would only add noise
+static IGNORED_FIELDS: &[&str] = &["id", "created_at", "updated_at", "deleted_at"];
+
+// A single field change on an entity
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub journal_id: u64,
+    pub operation: String,
+    pub entity: String,
+    pub entity_id: u64,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub changed_at: String,
+}
+
+fn statement_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::InvalidStatement { sqlite_error: e }
+}
+
+fn record_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::RecordError {
+        sqlite_error: Some(e),
+        strum_error: None,
+    }
+}
+
+fn field_value(row: &Option<serde_json::Value>, field: &str) -> Option<String> {
+    match row.as_ref().and_then(|row| row.get(field)) {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(value)) => Some(value.clone()),
+        Some(value) => Some(value.to_string()),
+    }
+}
+
+// Records the field level differences of `changes`, which were applied in
+// order by a journaled `operation`. A row changed several times, e.g. by a
+// cascading trigger, is diffed once between its first and last state.
+pub(crate) fn record(
+    conn: &Connection,
+    journal_id: u64,
+    operation: &str,
+    changes: &[Change],
+) -> Result<(), DbOperationsError> {
+    let mut rows: Vec<(EntityKind, Change)> = vec![];
+    for change in changes {
+        let entity = match EntityKind::ALL.iter().find(|e| e.table() == change.table) {
+            Some(entity) => *entity,
+            // Join tables are not entities
+            None => continue,
+        };
+        match rows
+            .iter_mut()
+            .find(|(_, row)| row.table == change.table && row.row_id == change.row_id)
+        {
+            Some((_, row)) => row.after = change.after.clone(),
+            None => rows.push((entity, change.clone())),
+        }
+    }
+
+    for (entity, row) in rows {
+        let mut fields: Vec<&String> = vec![];
+        for snapshot in [&row.before, &row.after].into_iter().flatten() {
+            for field in snapshot.as_object().into_iter().flat_map(|o| o.keys()) {
+                if !fields.contains(&field) {
+                    fields.push(field);
+                }
+            }
+        }
+        for field in fields {
+            if IGNORED_FIELDS.contains(&field.as_str()) {
+                continue;
+            }
+            let old_value = field_value(&row.before, field);
+            let new_value = field_value(&row.after, field);
+            if old_value == new_value {
+                continue;
+            }
+            conn.execute(
+                "INSERT INTO audit (
+                    journal_id, operation, entity, entity_id, field, old_value, new_value, changed_at
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'))",
+                params![
+                    journal_id,
+                    operation,
+                    entity.as_ref(),
+                    row.row_id,
+                    field,
+                    old_value,
+                    new_value
+                ],
+            )
+            .map_err(statement_error)?;
+        }
+    }
+    Ok(())
+}
+
+// The field changes of a single entity, newest first
+pub fn for_entity(
+    conn: &Connection,
+    entity: EntityKind,
+    id: u64,
+    limit: u64,
+) -> Result<Vec<AuditEntry>, DbOperationsError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT
+                id, journal_id, operation, entity, entity_id, field, old_value, new_value, changed_at
+            FROM audit
+            WHERE entity = ?1 AND entity_id = ?2
+            ORDER BY id DESC
+            LIMIT ?3",
+        )
+        .map_err(statement_error)?;
+    let rows = stmt
+        .query_map(params![entity.as_ref(), id, limit], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                journal_id: row.get(1)?,
+                operation: row.get(2)?,
+                entity: row.get(3)?,
+                entity_id: row.get(4)?,
+                field: row.get(5)?,
+                old_value: row.get(6)?,
+                new_value: row.get(7)?,
+                changed_at: row.get(8)?,
+            })
+        })
+        .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+    let mut entries = vec![];
+    for row in rows {
+        entries.push(row.map_err(record_error)?);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::loader::LoadContext;
+    use crate::db_interface::DbOperations;
+    use crate::entities::note::Note;
+    use crate::entities::Entities;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_audit_trail() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        Note::new(0, date, String::from("Lisbon"), vec![])
+            .add(&conn)
+            .unwrap();
+        Note::new(1, date, String::from("Porto"), vec![])
+            .save(&conn)
+            .unwrap();
+        crate::db::journal::undo(&conn).unwrap().unwrap();
+
+        let fields = for_entity(&conn, EntityKind::Note, 1, 10)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.operation, e.field, e.old_value, e.new_value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (
+                    String::from("undo"),
+                    String::from("content"),
+                    Some(String::from("Porto")),
+                    Some(String::from("Lisbon"))
+                ),
+                (
+                    String::from("save"),
+                    String::from("content"),
+                    Some(String::from("Lisbon")),
+                    Some(String::from("Porto"))
+                ),
+            ],
+            fields[..2]
+        );
+        assert!(fields[2..]
+            .iter()
+            .all(|(op, _, old, _)| op == "add" && old.is_none()));
+
+        let note = match Note::get_by_id(&conn, &mut LoadContext::default(), 1).unwrap() {
+            Some(Entities::Note(note)) => note,
+            other => panic!("Unexpected entity: {:#?}", other),
+        };
+        assert!(note.created_at.is_some());
+        assert!(note.updated_at >= note.created_at);
+    }
+}
@@ -0,0 +1,291 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use strum_macros::AsRefStr;
+
+use crate::db_interface::DbOperationsError;
+
+// How a problem is repaired by `--fix`
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Fix {
+    // The row is meaningless on its own and is deleted
+    Delete,
+    // The row is moved to the trash, where it can be inspected and restored
+    Trash,
+    // The journal is no longer recording or replaying an operation
+    ResetJournal,
+    // Cannot be repaired automatically
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Problem {
+    pub check: String,
+    pub table: Option<String>,
+    pub id: Option<u64>,
+    pub problem: String,
+    pub fix: Fix,
+    pub fixed: bool,
+}
+
+// A consistency rule: rows of `table` matching `condition` are broken
+struct Rule {
+    check: &'static str,
+    table: &'static str,
+    condition: String,
+    problem: String,
+    fix: Fix,
+}
+
+// Join tables, the column linking them to an entity and the entity's table
+static LINKS: &[(&str, &str, &str)] = &[
+    ("people_activities", "activity_id", "activities"),
+    ("people_reminders", "reminder_id", "reminders"),
+    ("people_notes", "note_id", "notes"),
+];
+
+fn rules() -> Vec<Rule> {
+    let mut rules = vec![];
+    for (table, column, parent) in LINKS {
+        rules.push(Rule {
+            check: "orphaned_link",
+            table,
+            condition: format!(
+                "person_id NOT IN (SELECT id FROM people) OR {} NOT IN (SELECT id FROM {})",
+                column, parent
+            ),
+            problem: format!("Links a person or {} that does not exist", parent),
+            fix: Fix::Delete,
+        });
+        rules.push(Rule {
+            check: "trashed_link",
+            table,
+            condition: format!(
+                "deleted = 0 AND (
+                    person_id IN (SELECT id FROM people WHERE deleted = 1)
+                    OR {} IN (SELECT id FROM {} WHERE deleted = 1)
+                )",
+                column, parent
+            ),
+            problem: format!("Links a person or {} that is in the trash", parent),
+            fix: Fix::Trash,
+        });
+    }
+    rules.push(Rule {
+        check: "orphaned_contact_info",
+        table: "contact_info",
+        condition: String::from("person_id NOT IN (SELECT id FROM people)"),
+        problem: String::from("Belongs to a person that does not exist"),
+        fix: Fix::Delete,
+    });
+    rules.push(Rule {
+        check: "trashed_contact_info",
+        table: "contact_info",
+        condition: String::from(
+            "deleted = 0 AND person_id IN (SELECT id FROM people WHERE deleted = 1)",
+        ),
+        problem: String::from("Belongs to a person that is in the trash"),
+        fix: Fix::Trash,
+    });
+    rules.push(Rule {
+        check: "unknown_contact_info_type",
+        table: "contact_info",
+        condition: String::from(
+            "deleted = 0 AND contact_info_type_id NOT IN (SELECT id FROM contact_info_types)",
+        ),
+        problem: String::from("Has an unknown contact_info_type_id"),
+        fix: Fix::Trash,
+    });
+    rules.push(Rule {
+        check: "unknown_activity_type",
+        table: "activities",
+        condition: String::from("deleted = 0 AND type NOT IN (SELECT id FROM activity_types)"),
+        problem: String::from("Has an unknown activity type"),
+        fix: Fix::Trash,
+    });
+    rules.push(Rule {
+        check: "unknown_recurring_type",
+        table: "reminders",
+        condition: String::from(
+            "deleted = 0 AND recurring NOT IN (SELECT id FROM recurring_types)",
+        ),
+        problem: String::from("Has an unknown recurring type"),
+        fix: Fix::Trash,
+    });
+    rules
+}
+
+fn statement_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::InvalidStatement { sqlite_error: e }
+}
+
+fn record_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::RecordError {
+        sqlite_error: Some(e),
+        strum_error: None,
+    }
+}
+
+fn integrity_check(conn: &Connection) -> Result<Vec<Problem>, DbOperationsError> {
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(statement_error)?;
+    let rows = stmt
+        .query_map([], |row| row.get::<usize, String>(0))
+        .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+    let mut problems = vec![];
+    for row in rows {
+        let message = row.map_err(record_error)?;
+        if message != "ok" {
+            problems.push(Problem {
+                check: String::from("integrity_check"),
+                table: None,
+                id: None,
+                problem: message,
+                fix: Fix::None,
+                fixed: false,
+            });
+        }
+    }
+    Ok(problems)
+}
+
+// An operation that crashed halfway leaves the journal recording into an
+// entry nothing will finish, or replaying, which disables the trash triggers
+fn journal_check(conn: &Connection) -> Result<Vec<Problem>, DbOperationsError> {
+    let (active, replaying): (Option<u64>, bool) = conn
+        .query_row(
+            "SELECT active, replaying FROM journal_state WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(record_error)?;
+    if active.is_none() && !replaying {
+        return Ok(vec![]);
+    }
+    Ok(vec![Problem {
+        check: String::from("journal_state"),
+        table: Some(String::from("journal_state")),
+        id: Some(1),
+        problem: String::from(
+            "The journal was left recording or replaying by an interrupted operation",
+        ),
+        fix: Fix::ResetJournal,
+        fixed: false,
+    }])
+}
+
+fn apply_fix(conn: &Connection, problem: &Problem) -> Result<bool, DbOperationsError> {
+    let (table, id) = match (&problem.table, problem.id) {
+        (Some(table), Some(id)) => (table, id),
+        _ => return Ok(false),
+    };
+    let sql = match problem.fix {
+        Fix::Delete => format!("DELETE FROM {} WHERE id = ?1", table),
+        Fix::Trash => format!("UPDATE {} SET deleted = 1 WHERE id = ?1", table),
+        Fix::ResetJournal => {
+            String::from("UPDATE journal_state SET active = NULL, replaying = 0 WHERE id = ?1")
+        }
+        Fix::None => return Ok(false),
+    };
+    conn.execute(&sql, params![id]).map_err(statement_error)?;
+    Ok(true)
+}
+
+// Runs every check, repairing what can be repaired when `fix` is set. Rules
+// run in order and each one is fixed before the next is checked, so later
+// rules see the repaired rows. All fixes are applied in a single
+// transaction.
+pub fn diagnose(conn: &Connection, fix: bool) -> Result<Vec<Problem>, DbOperationsError> {
+    let mut problems = integrity_check(conn)?;
+    let tx = conn.unchecked_transaction().map_err(statement_error)?;
+
+    let mut found = journal_check(&tx)?;
+    for rule in rules() {
+        let mut stmt = tx
+            .prepare(&format!(
+                "SELECT id FROM {} WHERE {} ORDER BY id",
+                rule.table, rule.condition
+            ))
+            .map_err(statement_error)?;
+        let rows = stmt
+            .query_map([], |row| row.get::<usize, u64>(0))
+            .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+        for row in rows {
+            found.push(Problem {
+                check: rule.check.to_string(),
+                table: Some(rule.table.to_string()),
+                id: Some(row.map_err(record_error)?),
+                problem: rule.problem.clone(),
+                fix: rule.fix,
+                fixed: false,
+            });
+        }
+        if fix {
+            for problem in found.iter_mut() {
+                if !problem.fixed {
+                    problem.fixed = apply_fix(&tx, problem)?;
+                }
+            }
+        }
+        problems.append(&mut found);
+    }
+
+    tx.commit().map_err(statement_error)?;
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO people (name, birthday, deleted) VALUES ('Zeh', NULL, FALSE);
+            INSERT INTO reminders (name, date, description, recurring, deleted)
+                VALUES ('Call', '2024-01-02', NULL, 42, FALSE);
+            INSERT INTO people_reminders (person_id, reminder_id, deleted) VALUES (1, 1, FALSE);
+            PRAGMA foreign_keys = OFF;
+            INSERT INTO people_reminders (person_id, reminder_id, deleted) VALUES (7, 1, FALSE);
+            UPDATE journal_state SET active = 3;",
+        )
+        .unwrap();
+
+        let problems = diagnose(&conn, false).unwrap();
+        assert_eq!(
+            vec![
+                ("journal_state", Some(1)),
+                ("orphaned_link", Some(2)),
+                ("unknown_recurring_type", Some(1)),
+            ],
+            problems
+                .iter()
+                .map(|p| (p.check.as_str(), p.id))
+                .collect::<Vec<(&str, Option<u64>)>>()
+        );
+        assert!(problems.iter().all(|p| !p.fixed));
+
+        let problems = diagnose(&conn, true).unwrap();
+        assert!(problems.iter().all(|p| p.fixed));
+        assert!(diagnose(&conn, false).unwrap().is_empty());
+        let deleted: bool = conn
+            .query_row("SELECT deleted FROM reminders WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(deleted);
+        // The trash cascades to the remaining link
+        let links: u64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM people_reminders WHERE deleted = 0",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(0, links);
+    }
+}
@@ -0,0 +1,66 @@
+use serde::Serialize;
+use strum_macros::{AsRefStr, EnumString};
+
+// The entities that can be trashed and journaled, and the tables they are
+// stored in
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, EnumString, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Person,
+    Activity,
+    Reminder,
+    Note,
+    ContactInfo,
+}
+
+impl EntityKind {
+    pub const ALL: [EntityKind; 5] = [
+        EntityKind::Person,
+        EntityKind::Activity,
+        EntityKind::Reminder,
+        EntityKind::Note,
+        EntityKind::ContactInfo,
+    ];
+
+    pub fn table(&self) -> &'static str {
+        match self {
+            EntityKind::Person => "people",
+            EntityKind::Activity => "activities",
+            EntityKind::Reminder => "reminders",
+            EntityKind::Note => "notes",
+            EntityKind::ContactInfo => "contact_info",
+        }
+    }
+
+    // A one line description of a trashed row
+    pub fn summary_sql(&self) -> &'static str {
+        match self {
+            EntityKind::Person => "name",
+            EntityKind::Activity => "name || ' (' || date || ')'",
+            EntityKind::Reminder => "name || ' (' || date || ')'",
+            EntityKind::Note => "date || ': ' || content",
+            EntityKind::ContactInfo => {
+                "COALESCE((SELECT name FROM people WHERE people.id = person_id), '?')
+                    || ': ' || COALESCE(contact_info_details, '')"
+            }
+        }
+    }
+
+    // Tables whose rows belong to the entity, and the column pointing back at
+    // it. They are soft-deleted together with the entity.
+    pub fn links(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            EntityKind::Person => &[
+                ("people_activities", "person_id"),
+                ("people_reminders", "person_id"),
+                ("people_notes", "person_id"),
+                ("contact_info", "person_id"),
+            ],
+            EntityKind::Activity => &[("people_activities", "activity_id")],
+            EntityKind::Reminder => &[("people_reminders", "reminder_id")],
+            EntityKind::Note => &[("people_notes", "note_id")],
+            EntityKind::ContactInfo => &[],
+        }
+    }
+}
@@ -0,0 +1,571 @@
+use std::cell::Cell;
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::Serialize;
+use strum_macros::{AsRefStr, EnumString};
+
+use crate::db::audit;
+use crate::db::entity_kind::EntityKind;
+use crate::db::transaction::atomic;
+use crate::db_interface::DbOperationsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, EnumString, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Add,
+    Save,
+    Remove,
+    Complete,
+    Skip,
+    Snooze,
+}
+
+// A single row change recorded by the journal triggers. `before` is `None`
+// for inserted rows and `after` is `None` for deleted ones.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub table: String,
+    pub row_id: u64,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub operation: String,
+    pub entity: String,
+    pub entity_id: u64,
+    pub created_at: String,
+    pub undone: bool,
+    pub changes: Vec<Change>,
+}
+
+impl JournalEntry {
+    // A compact description of the changes, e.g. `~people#1 +contact_info#4`
+    pub fn summary(&self) -> String {
+        let mut summary: Vec<String> = vec![];
+        for change in self.changes.iter().map(|c| {
+            let marker = match (&c.before, &c.after) {
+                (None, _) => "+",
+                (_, None) => "-",
+                _ => "~",
+            };
+            format!("{}{}#{}", marker, c.table, c.row_id)
+        }) {
+            // A row touched several times, e.g. by a cascading trigger, is
+            // only listed once
+            if !summary.contains(&change) {
+                summary.push(change);
+            }
+        }
+        summary.join(" ")
+    }
+}
+
+thread_local! {
+    // Whether an operation is being journaled on this thread. Kept outside the
+    // database so that an entry left active by a crashed process is not
+    // mistaken for an outer operation.
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+}
+
+// Records changes against a journal entry until it is finished. Nested
+// operations, e.g. a `save` that adds linked entities, are recorded against
+// the outermost entry so that they are undone together.
+pub struct JournalGuard<'a> {
+    conn: &'a Connection,
+    id: Option<i64>,
+    operation: Operation,
+}
+
+impl JournalGuard<'_> {
+    // Completes the entry: fills in its entity id and audits its changes.
+    // It has to run inside the operation's transaction, before it commits,
+    // so that a failure here rolls back the operation too.
+    pub fn finish(mut self) -> Result<(), DbOperationsError> {
+        match self.id.take() {
+            Some(id) => {
+                RECORDING.with(|recording| recording.set(false));
+                finish(self.conn, id, self.operation)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for JournalGuard<'_> {
+    // An entry that was never finished, e.g. because the operation failed, is
+    // discarded so that undo never replays half an operation
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            RECORDING.with(|recording| recording.set(false));
+            // Errors cannot be reported from `drop`; a failed operation
+            // rolls back its transaction, and the entry with it, anyway
+            let _ = discard(self.conn, id);
+        }
+    }
+}
+
+fn statement_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::InvalidStatement { sqlite_error: e }
+}
+
+fn record_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::RecordError {
+        sqlite_error: Some(e),
+        strum_error: None,
+    }
+}
+
+// How many operations are kept for undo unless configured otherwise
+pub const DEFAULT_RETENTION: u64 = 1000;
+
+// Keeps only the `retention` most recent operations, undone or not, with
+// their changes. 0 keeps all of them. Run it after writing; it only takes
+// the write lock when there is something to drop.
+pub fn prune(conn: &Connection, retention: u64) -> Result<(), DbOperationsError> {
+    if retention == 0 {
+        return Ok(());
+    }
+    let entries: u64 = conn
+        .query_row("SELECT COUNT(*) FROM journal", [], |row| row.get(0))
+        .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+    if entries <= retention {
+        return Ok(());
+    }
+    atomic(conn, || {
+        conn.execute(
+            "DELETE FROM journal_changes WHERE journal_id IN (
+                SELECT id FROM journal ORDER BY id DESC LIMIT -1 OFFSET ?1
+            )",
+            params![retention],
+        )
+        .and_then(|_| {
+            conn.execute(
+                "DELETE FROM journal WHERE id IN (
+                    SELECT id FROM journal ORDER BY id DESC LIMIT -1 OFFSET ?1
+                )",
+                params![retention],
+            )
+        })
+        .map_err(statement_error)?;
+        Ok(())
+    })
+}
+
+// Starts recording changes for `operation` on an entity. `entity_id` may be
+// 0 for entities that are not stored yet: it is filled in from the recorded
+// changes once the operation finishes.
+//
+// The entry being recorded is kept in `journal_state`, which every
+// connection shares, so `begin` has to run inside the transaction of the
+// operation (see `transaction::atomic`): other writers then wait until it
+// is finished and never record against it.
+pub fn begin(
+    conn: &Connection,
+    operation: Operation,
+    entity: EntityKind,
+    entity_id: u64,
+) -> Result<JournalGuard<'_>, DbOperationsError> {
+    if conn.is_autocommit() {
+        return Err(DbOperationsError::OutsideTransaction {
+            action: format!("Journaling {} {}", operation.as_ref(), entity.as_ref()),
+        });
+    }
+    if RECORDING.with(|recording| recording.get()) {
+        return Ok(JournalGuard {
+            conn,
+            id: None,
+            operation,
+        });
+    }
+
+    // A new operation invalidates whatever could be redone
+    conn.execute_batch(
+        "DELETE FROM journal_changes
+            WHERE journal_id IN (SELECT id FROM journal WHERE undone = 1);
+        DELETE FROM journal WHERE undone = 1;",
+    )
+    .map_err(statement_error)?;
+    conn.execute(
+        "INSERT INTO journal (operation, entity, entity_id, created_at)
+            VALUES (?1, ?2, ?3, datetime('now'))",
+        params![operation.as_ref(), entity.as_ref(), entity_id],
+    )
+    .map_err(statement_error)?;
+    let id = conn.last_insert_rowid();
+    conn.execute("UPDATE journal_state SET active = ?1", params![id])
+        .map_err(statement_error)?;
+    RECORDING.with(|recording| recording.set(true));
+    Ok(JournalGuard {
+        conn,
+        id: Some(id),
+        operation,
+    })
+}
+
+fn discard(conn: &Connection, id: i64) -> Result<(), DbOperationsError> {
+    conn.execute("UPDATE journal_state SET active = NULL", [])
+        .and_then(|_| conn.execute("DELETE FROM journal_changes WHERE journal_id = ?1", [id]))
+        .and_then(|_| conn.execute("DELETE FROM journal WHERE id = ?1", [id]))
+        .map_err(statement_error)?;
+    Ok(())
+}
+
+fn finish(conn: &Connection, id: i64, operation: Operation) -> Result<(), DbOperationsError> {
+    conn.execute("UPDATE journal_state SET active = NULL", [])
+        .map_err(statement_error)?;
+    // Operations that failed before changing anything leave no trace
+    conn.execute(
+        "DELETE FROM journal
+            WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM journal_changes WHERE journal_id = ?1)",
+        params![id],
+    )
+    .map_err(statement_error)?;
+    for entity in EntityKind::ALL {
+        conn.execute(
+            "UPDATE journal SET entity_id = (
+                SELECT row_id FROM journal_changes
+                WHERE journal_id = ?1 AND table_name = ?2
+                ORDER BY id LIMIT 1
+            )
+            WHERE id = ?1 AND entity_id = 0 AND entity = ?3 AND EXISTS (
+                SELECT 1 FROM journal_changes WHERE journal_id = ?1 AND table_name = ?2
+            )",
+            params![id, entity.table(), entity.as_ref()],
+        )
+        .map_err(statement_error)?;
+    }
+    audit::record(
+        conn,
+        id as u64,
+        operation.as_ref(),
+        &changes(conn, id as u64)?,
+    )
+}
+
+fn parse_snapshot(snapshot: Option<String>) -> Option<serde_json::Value> {
+    snapshot.and_then(|s| serde_json::from_str(&s).ok())
+}
+
+fn changes(conn: &Connection, journal_id: u64) -> Result<Vec<Change>, DbOperationsError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT table_name, row_id, before, after FROM journal_changes
+            WHERE journal_id = ?1 ORDER BY id",
+        )
+        .map_err(statement_error)?;
+    let rows = stmt
+        .query_map(params![journal_id], |row| {
+            Ok(Change {
+                table: row.get(0)?,
+                row_id: row.get(1)?,
+                before: parse_snapshot(row.get(2)?),
+                after: parse_snapshot(row.get(3)?),
+            })
+        })
+        .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+    let mut changes = vec![];
+    for row in rows {
+        changes.push(row.map_err(record_error)?);
+    }
+    Ok(changes)
+}
+
+fn entries(
+    conn: &Connection,
+    condition: &str,
+    params: &[&dyn rusqlite::ToSql],
+) -> Result<Vec<JournalEntry>, DbOperationsError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, operation, entity, entity_id, created_at, undone FROM journal {}",
+            condition
+        ))
+        .map_err(statement_error)?;
+    let rows = stmt
+        .query_map(params, |row| {
+            Ok(JournalEntry {
+                id: row.get(0)?,
+                operation: row.get(1)?,
+                entity: row.get(2)?,
+                entity_id: row.get(3)?,
+                created_at: row.get(4)?,
+                undone: row.get(5)?,
+                changes: vec![],
+            })
+        })
+        .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+    let mut entries = vec![];
+    for row in rows {
+        let mut entry = row.map_err(record_error)?;
+        entry.changes = changes(conn, entry.id)?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+// The most recent journal entries, newest first
+pub fn history(conn: &Connection, limit: u64) -> Result<Vec<JournalEntry>, DbOperationsError> {
+    entries(conn, "ORDER BY id DESC LIMIT ?1", &[&limit])
+}
+
+fn row_exists(conn: &Connection, table: &str, id: u64) -> Result<bool, DbOperationsError> {
+    conn.query_row(
+        &format!("SELECT 1 FROM {} WHERE id = ?1", table),
+        params![id],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|row| row.is_some())
+    .map_err(record_error)
+}
+
+fn json_to_sql(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+// Puts a row back into the state of `snapshot`, deleting it when the
+// snapshot is `None`
+fn apply(
+    conn: &Connection,
+    table: &str,
+    id: u64,
+    snapshot: &Option<serde_json::Value>,
+) -> Result<(), DbOperationsError> {
+    let row = match snapshot.as_ref().and_then(|s| s.as_object()) {
+        Some(row) => row,
+        None => {
+            conn.execute(&format!("DELETE FROM {} WHERE id = ?1", table), params![id])
+                .map_err(statement_error)?;
+            return Ok(());
+        }
+    };
+    let columns: Vec<&String> = row.keys().collect();
+    let values: Vec<Value> = row.values().map(json_to_sql).collect();
+    let sql = if row_exists(conn, table, id)? {
+        format!(
+            "UPDATE {} SET {} WHERE id = {}",
+            table,
+            columns
+                .iter()
+                .enumerate()
+                .map(|(i, c)| format!("{} = ?{}", c, i + 1))
+                .collect::<Vec<String>>()
+                .join(", "),
+            id
+        )
+    } else {
+        format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            columns
+                .iter()
+                .map(|c| c.as_str())
+                .collect::<Vec<&str>>()
+                .join(", "),
+            crate::helpers::repeat_vars(columns.len())
+        )
+    };
+    conn.execute(&sql, params_from_iter(values))
+        .map_err(statement_error)?;
+    Ok(())
+}
+
+fn replay(conn: &Connection, entry: &JournalEntry, undo: bool) -> Result<(), DbOperationsError> {
+    let tx = conn.unchecked_transaction().map_err(statement_error)?;
+    tx.execute("UPDATE journal_state SET replaying = 1", [])
+        .map_err(statement_error)?;
+    // The changes in the order they are applied, from the current state of
+    // each row to the one being restored
+    let applied: Vec<Change> = if undo {
+        entry
+            .changes
+            .iter()
+            .rev()
+            .map(|change| Change {
+                table: change.table.clone(),
+                row_id: change.row_id,
+                before: change.after.clone(),
+                after: change.before.clone(),
+            })
+            .collect()
+    } else {
+        entry.changes.clone()
+    };
+    for change in applied.iter() {
+        apply(&tx, &change.table, change.row_id, &change.after)?;
+    }
+    audit::record(&tx, entry.id, if undo { "undo" } else { "redo" }, &applied)?;
+    tx.execute(
+        "UPDATE journal SET undone = ?1 WHERE id = ?2",
+        params![undo, entry.id],
+    )
+    .map_err(statement_error)?;
+    tx.execute("UPDATE journal_state SET replaying = 0", [])
+        .map_err(statement_error)?;
+    tx.commit().map_err(statement_error)
+}
+
+// Reverts the most recent operation that has not been undone yet. Returns
+// `None` when there is nothing to undo.
+pub fn undo(conn: &Connection) -> Result<Option<JournalEntry>, DbOperationsError> {
+    let entry = entries(conn, "WHERE undone = 0 ORDER BY id DESC LIMIT 1", &[])?.pop();
+    if let Some(entry) = &entry {
+        replay(conn, entry, true)?;
+    }
+    Ok(entry)
+}
+
+// Re-applies the most recently undone operation. Returns `None` when there
+// is nothing to redo.
+pub fn redo(conn: &Connection) -> Result<Option<JournalEntry>, DbOperationsError> {
+    let entry = entries(conn, "WHERE undone = 1 ORDER BY id LIMIT 1", &[])?.pop();
+    if let Some(entry) = &entry {
+        replay(conn, entry, false)?;
+    }
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM people WHERE deleted = 0 ORDER BY id")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+
+        atomic(&conn, || {
+            let journal = begin(&conn, Operation::Add, EntityKind::Person, 0)?;
+            conn.execute_batch(
+                "INSERT INTO people (name, birthday, deleted) VALUES ('Zeh', NULL, FALSE);
+                INSERT INTO contact_info (person_id, contact_info_type_id, contact_info_details, deleted)
+                    VALUES (1, 3, 'zeh@example.com', FALSE);",
+            )
+            .map_err(statement_error)?;
+            journal.finish()
+        })
+        .unwrap();
+        atomic(&conn, || {
+            let journal = begin(&conn, Operation::Remove, EntityKind::Person, 1)?;
+            // Nested operations join the outer entry
+            let nested = begin(&conn, Operation::Save, EntityKind::Person, 1)?;
+            conn.execute("UPDATE people SET deleted = TRUE WHERE id = 1", [])
+                .map_err(statement_error)?;
+            nested.finish()?;
+            journal.finish()
+        })
+        .unwrap();
+
+        let entries = history(&conn, 10).unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!("remove", entries[0].operation);
+        assert_eq!(1, entries[1].entity_id);
+        assert_eq!("+people#1 +contact_info#1", entries[1].summary());
+        assert!(names(&conn).is_empty());
+
+        undo(&conn).unwrap().unwrap();
+        assert_eq!(vec!["Zeh"], names(&conn));
+        let deleted: bool = conn
+            .query_row("SELECT deleted FROM contact_info WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(!deleted);
+
+        undo(&conn).unwrap().unwrap();
+        assert!(undo(&conn).unwrap().is_none());
+        let rows: u64 = conn
+            .query_row("SELECT COUNT(*) FROM people", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, rows);
+
+        redo(&conn).unwrap().unwrap();
+        assert_eq!(vec!["Zeh"], names(&conn));
+        redo(&conn).unwrap().unwrap();
+        assert!(names(&conn).is_empty());
+        assert!(redo(&conn).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_unfinished() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+
+        atomic(&conn, || {
+            let _journal = begin(&conn, Operation::Add, EntityKind::Person, 0)?;
+            conn.execute(
+                "INSERT INTO people (name, birthday, deleted) VALUES ('Zeh', NULL, FALSE)",
+                [],
+            )
+            .map_err(statement_error)?;
+            Ok(())
+        })
+        .unwrap();
+        assert!(history(&conn, 10).unwrap().is_empty());
+        let active: Option<i64> = conn
+            .query_row("SELECT active FROM journal_state", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(None, active);
+    }
+
+    #[test]
+    fn test_retention() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+
+        let add = |name: &str| {
+            atomic(&conn, || {
+                let journal = begin(&conn, Operation::Add, EntityKind::Person, 0)?;
+                conn.execute(
+                    "INSERT INTO people (name, birthday, deleted) VALUES (?1, NULL, FALSE)",
+                    [name],
+                )
+                .map_err(statement_error)?;
+                journal.finish()
+            })
+            .unwrap();
+        };
+        for name in ["Ana", "Bo", "Cy"] {
+            add(name);
+        }
+        prune(&conn, 0).unwrap();
+        assert_eq!(3, history(&conn, 10).unwrap().len());
+        prune(&conn, 2).unwrap();
+        let entries = history(&conn, 10).unwrap();
+        assert_eq!(
+            vec![3, 2],
+            entries.iter().map(|e| e.entity_id).collect::<Vec<_>>()
+        );
+        let changes: u64 = conn
+            .query_row("SELECT COUNT(*) FROM journal_changes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(2, changes);
+
+        match begin(&conn, Operation::Add, EntityKind::Person, 0) {
+            Err(DbOperationsError::OutsideTransaction { .. }) => (),
+            _ => panic!("expected a journal outside of a transaction to fail"),
+        };
+    }
+}
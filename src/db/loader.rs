@@ -0,0 +1,1097 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use rusqlite::types::Type;
+use rusqlite::{params_from_iter, Connection, Params, Row};
+
+use crate::db_interface::DbOperationsError;
+use crate::entities::activity::{Activity, ActivityType};
+use crate::entities::datetime::{DateTimeError, Moment};
+use crate::entities::note::Note;
+use crate::entities::occurrence::{Occurrence, OccurrenceStatus};
+use crate::entities::person::{ContactInfo, ContactInfoType, Person};
+use crate::entities::recurrence::RRule;
+use crate::entities::reminder::Reminder;
+use crate::entities::shallow::{ShallowActivity, ShallowNote, ShallowPerson, ShallowReminder};
+
+// Ids bound in a single `IN (...)` list, well under SQLite's variable limit
+const BATCH_SIZE: usize = 500;
+
+// A row that could not be read and was left out of a load
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedRow {
+    pub table: String,
+    pub id: u64,
+    pub error: String,
+}
+
+impl fmt::Display for SkippedRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "skipping invalid row {} of {}: {}",
+            self.id, self.table, self.error
+        )
+    }
+}
+
+// How a load treats rows that cannot be read: a strict load fails with
+// their ids, any other skips them and keeps them in `skipped`. Writes that
+// load what they link to use a strict context so nothing is dropped silently.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadContext {
+    pub strict: bool,
+    pub skipped: Vec<SkippedRow>,
+}
+
+impl LoadContext {
+    pub fn new(strict: bool) -> LoadContext {
+        LoadContext {
+            strict,
+            skipped: vec![],
+        }
+    }
+}
+
+// Which relations are loaded along with an entity. A person's relations are
+// its contact info, activities, reminders and notes; `people` are the
+// people linked to an activity, reminder or note. Relations of related
+// entities are loaded one level deep: the activities of a person come with
+// their people, but those people come without their own relations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Relations {
+    pub contact_info: bool,
+    pub activities: bool,
+    pub reminders: bool,
+    pub notes: bool,
+    pub people: bool,
+}
+
+impl Relations {
+    pub const ALL: Relations = Relations {
+        contact_info: true,
+        activities: true,
+        reminders: true,
+        notes: true,
+        people: true,
+    };
+    pub const NONE: Relations = Relations {
+        contact_info: false,
+        activities: false,
+        reminders: false,
+        notes: false,
+        people: false,
+    };
+}
+
+impl Default for Relations {
+    fn default() -> Self {
+        Relations::ALL
+    }
+}
+
+fn statement_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::InvalidStatement { sqlite_error: e }
+}
+
+fn record_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::RecordError {
+        sqlite_error: Some(e),
+        strum_error: None,
+    }
+}
+
+// A date column. Missing dates are stored as NULL or, by older versions, as
+// an empty string; anything else that doesn't parse is a bad column.
+fn optional_date(row: &Row, column: &str) -> rusqlite::Result<Option<NaiveDate>> {
+    let value: Option<String> = row.get(column)?;
+    match value.as_deref() {
+        None | Some("") => Ok(None),
+        Some(value) => match crate::helpers::parse_from_str_ymd(value) {
+            Ok(date) => Ok(Some(date)),
+            Err(e) => Err(rusqlite::Error::FromSqlConversionFailure(
+                row.as_ref().column_index(column)?,
+                Type::Text,
+                Box::new(e),
+            )),
+        },
+    }
+}
+
+// A date, or an RFC 3339 timestamp, with the IANA timezone its time is in
+fn moment(row: &Row, column: &str, timezone_column: &str) -> rusqlite::Result<Moment> {
+    let timezone: Option<String> = row.get(timezone_column)?;
+    let timezone = match timezone.as_deref() {
+        None | Some("") => None,
+        Some(name) => match Tz::from_str(name) {
+            Ok(timezone) => Some(timezone),
+            Err(_) => {
+                return Err(rusqlite::Error::FromSqlConversionFailure(
+                    row.as_ref().column_index(timezone_column)?,
+                    Type::Text,
+                    Box::new(DateTimeError::UnknownTimezone {
+                        timezone: name.to_string(),
+                    }),
+                ))
+            }
+        },
+    };
+    let value: String = row.get(column)?;
+    Moment::from_rfc3339(&value, timezone).map_err(|e| match row.as_ref().column_index(column) {
+        Ok(index) => rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)),
+        Err(e) => e,
+    })
+}
+
+fn date(row: &Row, column: &str) -> rusqlite::Result<NaiveDate> {
+    match optional_date(row, column)? {
+        Some(date) => Ok(date),
+        None => Err(rusqlite::Error::InvalidColumnType(
+            row.as_ref().column_index(column)?,
+            column.to_string(),
+            Type::Null,
+        )),
+    }
+}
+
+// A reminder's RRULE, NULL when it doesn't recur
+fn rule(row: &Row, column: &str) -> rusqlite::Result<Option<RRule>> {
+    let value: Option<String> = row.get(column)?;
+    match value {
+        None => Ok(None),
+        Some(value) => match RRule::from_str(&value) {
+            Ok(rule) => Ok(Some(rule)),
+            Err(e) => Err(rusqlite::Error::FromSqlConversionFailure(
+                row.as_ref().column_index(column)?,
+                Type::Text,
+                Box::new(e),
+            )),
+        },
+    }
+}
+
+// Comma separated dates, NULL when there are none
+fn dates(row: &Row, column: &str) -> rusqlite::Result<Vec<NaiveDate>> {
+    let value: Option<String> = row.get(column)?;
+    let mut dates = vec![];
+    for date in value
+        .iter()
+        .flat_map(|v| v.split(','))
+        .filter(|d| !d.is_empty())
+    {
+        match crate::helpers::parse_from_str_ymd(date) {
+            Ok(date) => dates.push(date),
+            Err(e) => {
+                return Err(rusqlite::Error::FromSqlConversionFailure(
+                    row.as_ref().column_index(column)?,
+                    Type::Text,
+                    Box::new(e),
+                ))
+            }
+        }
+    }
+    Ok(dates)
+}
+
+// A type table (`activity_types`, `contact_info_types`...) by id, so rows can
+// be resolved without a query each
+fn types<T: FromStr<Err = strum::ParseError>>(
+    conn: &Connection,
+    table: &str,
+) -> Result<HashMap<u64, T>, DbOperationsError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT id, type FROM {}", table))
+        .map_err(statement_error)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<usize, u64>(0)?, row.get::<usize, String>(1)?))
+        })
+        .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+    let mut types = HashMap::new();
+    for row in rows {
+        let (id, name) = row.map_err(record_error)?;
+        let value = T::from_str(&name).map_err(|e| DbOperationsError::RecordError {
+            sqlite_error: None,
+            strum_error: Some(e),
+        })?;
+        types.insert(id, value);
+    }
+    Ok(types)
+}
+
+// A row's type id missing from its type table, reported like a bad column
+fn resolve<T: Clone>(types: &HashMap<u64, T>, row: &Row, column: &str) -> rusqlite::Result<T> {
+    let id: u64 = row.get(column)?;
+    match types.get(&id) {
+        Some(value) => Ok(value.clone()),
+        None => Err(rusqlite::Error::InvalidColumnType(
+            row.as_ref().column_index(column)?,
+            column.to_string(),
+            rusqlite::types::Type::Integer,
+        )),
+    }
+}
+
+fn person_from_row(row: &Row) -> rusqlite::Result<Person> {
+    Ok(Person {
+        created_at: crate::helpers::get_timestamp(row, "created_at"),
+        updated_at: crate::helpers::get_timestamp(row, "updated_at"),
+        ..Person::new(
+            row.get("id")?,
+            row.get("name")?,
+            optional_date(row, "birthday")?,
+            vec![],
+            vec![],
+            vec![],
+        )
+    })
+}
+
+fn activity_from_row(row: &Row, types: &HashMap<u64, ActivityType>) -> rusqlite::Result<Activity> {
+    let moment = moment(row, "date", "timezone")?;
+    Ok(Activity {
+        time: moment.time,
+        timezone: moment.timezone,
+        created_at: crate::helpers::get_timestamp(row, "created_at"),
+        updated_at: crate::helpers::get_timestamp(row, "updated_at"),
+        ..Activity::new(
+            row.get("id")?,
+            row.get("name")?,
+            resolve(types, row, "type")?,
+            moment.date,
+            row.get("content")?,
+            vec![],
+        )
+    })
+}
+
+fn reminder_from_row(row: &Row) -> rusqlite::Result<Reminder> {
+    let moment = moment(row, "date", "timezone")?;
+    Ok(Reminder {
+        time: moment.time,
+        timezone: moment.timezone,
+        exdates: dates(row, "exdates")?,
+        created_at: crate::helpers::get_timestamp(row, "created_at"),
+        updated_at: crate::helpers::get_timestamp(row, "updated_at"),
+        ..Reminder::new(
+            row.get("id")?,
+            row.get("name")?,
+            moment.date,
+            row.get("description")?,
+            rule(row, "rrule")?,
+            vec![],
+        )
+    })
+}
+
+fn occurrence_from_row(row: &Row) -> rusqlite::Result<Occurrence> {
+    let status: String = row.get("status")?;
+    let status = match OccurrenceStatus::from_str(&status) {
+        Ok(status) => status,
+        Err(e) => {
+            return Err(rusqlite::Error::FromSqlConversionFailure(
+                row.as_ref().column_index("status")?,
+                Type::Text,
+                Box::new(e),
+            ))
+        }
+    };
+    Ok(Occurrence {
+        date: date(row, "date")?,
+        status,
+        snoozed_until: optional_date(row, "snoozed_until")?,
+        created_at: crate::helpers::get_timestamp(row, "created_at"),
+    })
+}
+
+fn note_from_row(row: &Row) -> rusqlite::Result<Note> {
+    let moment = moment(row, "date", "timezone")?;
+    Ok(Note {
+        time: moment.time,
+        timezone: moment.timezone,
+        created_at: crate::helpers::get_timestamp(row, "created_at"),
+        updated_at: crate::helpers::get_timestamp(row, "updated_at"),
+        ..Note::new(row.get("id")?, moment.date, row.get("content")?, vec![])
+    })
+}
+
+fn contact_info_from_row(
+    row: &Row,
+    types: &HashMap<u64, ContactInfoType>,
+) -> rusqlite::Result<ContactInfo> {
+    let details: String = row.get("contact_info_details")?;
+    let contact_info_type = match resolve(types, row, "contact_info_type_id")? {
+        ContactInfoType::Phone(_) => ContactInfoType::Phone(details),
+        ContactInfoType::WhatsApp(_) => ContactInfoType::WhatsApp(details),
+        ContactInfoType::Email(_) => ContactInfoType::Email(details),
+    };
+    Ok(ContactInfo::new(
+        row.get("id")?,
+        row.get("person_id")?,
+        contact_info_type,
+    ))
+}
+
+// Rows of `table` that `map` could not read are skipped and kept in `load`,
+// or fail the query with their ids when it is strict
+fn skip_invalid(
+    load: &mut LoadContext,
+    table: &str,
+    mut invalid: Vec<(u64, rusqlite::Error)>,
+) -> Result<(), DbOperationsError> {
+    if invalid.is_empty() {
+        return Ok(());
+    }
+    invalid.sort_by_key(|(id, _)| *id);
+    invalid.dedup_by_key(|(id, _)| *id);
+    if load.strict {
+        let ids = invalid.iter().map(|(id, _)| *id).collect();
+        let (_, sqlite_error) = invalid.swap_remove(0);
+        return Err(DbOperationsError::InvalidRows {
+            table: table.to_string(),
+            ids,
+            sqlite_error: Box::new(sqlite_error),
+        });
+    }
+    load.skipped
+        .extend(invalid.into_iter().map(|(id, e)| SkippedRow {
+            table: table.to_string(),
+            id,
+            error: e.to_string(),
+        }));
+    Ok(())
+}
+
+// The rows selected by `sql` from `table`, which must include its `id`
+fn query<T, P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    table: &str,
+    sql: &str,
+    params: P,
+    mut map: impl FnMut(&Row) -> rusqlite::Result<T>,
+) -> Result<Vec<T>, DbOperationsError> {
+    let mut values = vec![];
+    let mut invalid = vec![];
+    {
+        let mut stmt = conn.prepare(sql).map_err(statement_error)?;
+        let mut rows = stmt
+            .query(params)
+            .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+        while let Some(row) = rows.next().map_err(record_error)? {
+            match map(row) {
+                Ok(value) => values.push(value),
+                Err(e) => invalid.push((row.get::<&str, u64>("id").map_err(record_error)?, e)),
+            }
+        }
+    }
+    skip_invalid(load, table, invalid)?;
+    Ok(values)
+}
+
+fn unique_ids(ids: impl Iterator<Item = u64>) -> Vec<u64> {
+    let mut ids: Vec<u64> = ids.collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+// Runs `sql`, whose `{}` is replaced by a list of ids, once per batch of
+// `ids`. Rows are returned with the id they were loaded for, which `sql`
+// selects as `link_id`.
+fn batched<T>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    table: &str,
+    sql: &str,
+    ids: &[u64],
+    mut map: impl FnMut(&Row) -> rusqlite::Result<T>,
+) -> Result<Vec<(u64, T)>, DbOperationsError> {
+    let mut values = vec![];
+    for batch in ids.chunks(BATCH_SIZE) {
+        let sql = sql.replace("{}", &crate::helpers::repeat_vars(batch.len()));
+        values.append(&mut query(
+            conn,
+            load,
+            table,
+            &sql,
+            params_from_iter(batch),
+            |row| Ok((row.get("link_id")?, map(row)?)),
+        )?);
+    }
+    Ok(values)
+}
+
+fn group<K: Eq + Hash, T>(keys: Vec<K>, values: Vec<T>) -> HashMap<K, Vec<T>> {
+    let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+    for (key, value) in keys.into_iter().zip(values) {
+        groups.entry(key).or_default().push(value);
+    }
+    groups
+}
+
+// The people linked to each of `ids` through `link_table`
+fn linked_people(
+    conn: &Connection,
+    load: &mut LoadContext,
+    link_table: &str,
+    column: &str,
+    ids: &[u64],
+    relations: Relations,
+) -> Result<HashMap<u64, Vec<Person>>, DbOperationsError> {
+    let sql = format!(
+        "SELECT p.*, l.{column} AS link_id
+        FROM {link_table} l JOIN people p ON p.id = l.person_id
+        WHERE l.{column} IN ({{}}) AND l.deleted = 0 AND p.deleted = 0
+        ORDER BY p.id"
+    );
+    let (links, mut people): (Vec<u64>, Vec<Person>) =
+        batched(conn, load, "people", &sql, ids, person_from_row)?
+            .into_iter()
+            .unzip();
+    load_people(
+        conn,
+        load,
+        &mut people,
+        Relations {
+            people: false,
+            ..relations
+        },
+    )?;
+    Ok(group(links, people))
+}
+
+// Loads `relations` of every person with one query per relation
+pub fn load_people(
+    conn: &Connection,
+    load: &mut LoadContext,
+    people: &mut [Person],
+    relations: Relations,
+) -> Result<(), DbOperationsError> {
+    let ids = unique_ids(people.iter().map(|p| p.id));
+    if ids.is_empty() {
+        return Ok(());
+    }
+    // Whatever a person links to comes with its people, but not theirs
+    let nested = Relations {
+        people: relations.people,
+        ..Relations::NONE
+    };
+
+    if relations.contact_info {
+        let types = types(conn, "contact_info_types")?;
+        let (links, contact_info): (Vec<u64>, Vec<ContactInfo>) = batched(
+            conn,
+            load,
+            "contact_info",
+            "SELECT *, person_id AS link_id
+            FROM contact_info
+            WHERE person_id IN ({}) AND deleted = 0
+            ORDER BY id",
+            &ids,
+            |row| contact_info_from_row(row, &types),
+        )?
+        .into_iter()
+        .unzip();
+        let contact_info = group(links, contact_info);
+        for person in people.iter_mut() {
+            person.contact_info = contact_info.get(&person.id).cloned().unwrap_or_default();
+        }
+    }
+    if relations.activities {
+        let types = types(conn, "activity_types")?;
+        let (links, mut activities): (Vec<u64>, Vec<Activity>) = batched(
+            conn,
+            load,
+            "activities",
+            "SELECT a.*, l.person_id AS link_id
+            FROM people_activities l JOIN activities a ON a.id = l.activity_id
+            WHERE l.person_id IN ({}) AND l.deleted = 0 AND a.deleted = 0
+            ORDER BY a.id",
+            &ids,
+            |row| activity_from_row(row, &types),
+        )?
+        .into_iter()
+        .unzip();
+        load_activities(conn, load, &mut activities, nested)?;
+        let activities = group(links, activities);
+        for person in people.iter_mut() {
+            person.activities = activities.get(&person.id).cloned().unwrap_or_default();
+        }
+    }
+    if relations.reminders {
+        let (links, mut reminders): (Vec<u64>, Vec<Reminder>) = batched(
+            conn,
+            load,
+            "reminders",
+            "SELECT r.*, l.person_id AS link_id
+            FROM people_reminders l JOIN reminders r ON r.id = l.reminder_id
+            WHERE l.person_id IN ({}) AND l.deleted = 0 AND r.deleted = 0
+            ORDER BY r.id",
+            &ids,
+            reminder_from_row,
+        )?
+        .into_iter()
+        .unzip();
+        load_reminders(conn, load, &mut reminders, nested)?;
+        let reminders = group(links, reminders);
+        for person in people.iter_mut() {
+            person.reminders = reminders.get(&person.id).cloned().unwrap_or_default();
+        }
+    }
+    if relations.notes {
+        let (links, mut notes): (Vec<u64>, Vec<Note>) = batched(
+            conn,
+            load,
+            "notes",
+            "SELECT n.*, l.person_id AS link_id
+            FROM people_notes l JOIN notes n ON n.id = l.note_id
+            WHERE l.person_id IN ({}) AND l.deleted = 0 AND n.deleted = 0
+            ORDER BY n.id",
+            &ids,
+            note_from_row,
+        )?
+        .into_iter()
+        .unzip();
+        load_notes(conn, load, &mut notes, nested)?;
+        let notes = group(links, notes);
+        for person in people.iter_mut() {
+            person.notes = notes.get(&person.id).cloned().unwrap_or_default();
+        }
+    }
+    Ok(())
+}
+
+// Loads the people of every activity, along with the people's `relations`
+pub fn load_activities(
+    conn: &Connection,
+    load: &mut LoadContext,
+    activities: &mut [Activity],
+    relations: Relations,
+) -> Result<(), DbOperationsError> {
+    let ids = unique_ids(activities.iter().map(|a| a.id));
+    if ids.is_empty() || !relations.people {
+        return Ok(());
+    }
+    let people = linked_people(
+        conn,
+        load,
+        "people_activities",
+        "activity_id",
+        &ids,
+        relations,
+    )?;
+    for activity in activities.iter_mut() {
+        activity.people = people.get(&activity.id).cloned().unwrap_or_default();
+    }
+    Ok(())
+}
+
+// Loads the history of every reminder and its people, along with the
+// people's `relations`. The history belongs to the reminder, so it is loaded
+// regardless of `relations`.
+pub fn load_reminders(
+    conn: &Connection,
+    load: &mut LoadContext,
+    reminders: &mut [Reminder],
+    relations: Relations,
+) -> Result<(), DbOperationsError> {
+    let ids = unique_ids(reminders.iter().map(|r| r.id));
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let (links, history): (Vec<u64>, Vec<Occurrence>) = batched(
+        conn,
+        load,
+        "reminder_occurrences",
+        "SELECT *, reminder_id AS link_id FROM reminder_occurrences
+        WHERE reminder_id IN ({}) ORDER BY date",
+        &ids,
+        occurrence_from_row,
+    )?
+    .into_iter()
+    .unzip();
+    let history = group(links, history);
+    for reminder in reminders.iter_mut() {
+        reminder.history = history.get(&reminder.id).cloned().unwrap_or_default();
+    }
+    if !relations.people {
+        return Ok(());
+    }
+    let people = linked_people(
+        conn,
+        load,
+        "people_reminders",
+        "reminder_id",
+        &ids,
+        relations,
+    )?;
+    for reminder in reminders.iter_mut() {
+        reminder.people = people.get(&reminder.id).cloned().unwrap_or_default();
+    }
+    Ok(())
+}
+
+// Loads the people of every note, along with the people's `relations`
+pub fn load_notes(
+    conn: &Connection,
+    load: &mut LoadContext,
+    notes: &mut [Note],
+    relations: Relations,
+) -> Result<(), DbOperationsError> {
+    let ids = unique_ids(notes.iter().map(|n| n.id));
+    if ids.is_empty() || !relations.people {
+        return Ok(());
+    }
+    let people = linked_people(conn, load, "people_notes", "note_id", &ids, relations)?;
+    for note in notes.iter_mut() {
+        note.people = people.get(&note.id).cloned().unwrap_or_default();
+    }
+    Ok(())
+}
+
+// The people selected by `sql`, which must select every column of `people`
+pub fn people<P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    sql: &str,
+    params: P,
+    relations: Relations,
+) -> Result<Vec<Person>, DbOperationsError> {
+    let mut people = query(conn, load, "people", sql, params, person_from_row)?;
+    load_people(conn, load, &mut people, relations)?;
+    Ok(people)
+}
+
+// The activities selected by `sql`, which must select every column of
+// `activities`
+pub fn activities<P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    sql: &str,
+    params: P,
+    relations: Relations,
+) -> Result<Vec<Activity>, DbOperationsError> {
+    let types = types(conn, "activity_types")?;
+    let mut activities = query(conn, load, "activities", sql, params, |row| {
+        activity_from_row(row, &types)
+    })?;
+    load_activities(conn, load, &mut activities, relations)?;
+    Ok(activities)
+}
+
+// The reminders selected by `sql`, which must select every column of
+// `reminders`
+pub fn reminders<P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    sql: &str,
+    params: P,
+    relations: Relations,
+) -> Result<Vec<Reminder>, DbOperationsError> {
+    let mut reminders = query(conn, load, "reminders", sql, params, reminder_from_row)?;
+    load_reminders(conn, load, &mut reminders, relations)?;
+    Ok(reminders)
+}
+
+// The notes selected by `sql`, which must select every column of `notes`
+pub fn notes<P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    sql: &str,
+    params: P,
+    relations: Relations,
+) -> Result<Vec<Note>, DbOperationsError> {
+    let mut notes = query(conn, load, "notes", sql, params, note_from_row)?;
+    load_notes(conn, load, &mut notes, relations)?;
+    Ok(notes)
+}
+
+// Entities by id, in id order. Ids that are missing or in the trash are
+// skipped.
+fn by_id<T>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    table: &str,
+    ids: &[u64],
+    map: impl FnMut(&Row) -> rusqlite::Result<T>,
+) -> Result<Vec<T>, DbOperationsError> {
+    let sql = format!(
+        "SELECT *, id AS link_id FROM {} WHERE id IN ({{}}) AND deleted = 0 ORDER BY id",
+        table
+    );
+    let ids = unique_ids(ids.iter().copied());
+    Ok(batched(conn, load, table, &sql, &ids, map)?
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect())
+}
+
+pub fn people_by_id(
+    conn: &Connection,
+    load: &mut LoadContext,
+    ids: &[u64],
+    relations: Relations,
+) -> Result<Vec<Person>, DbOperationsError> {
+    let mut people = by_id(conn, load, "people", ids, person_from_row)?;
+    load_people(conn, load, &mut people, relations)?;
+    Ok(people)
+}
+
+pub fn activities_by_id(
+    conn: &Connection,
+    load: &mut LoadContext,
+    ids: &[u64],
+    relations: Relations,
+) -> Result<Vec<Activity>, DbOperationsError> {
+    let types = types(conn, "activity_types")?;
+    let mut activities = by_id(conn, load, "activities", ids, |row| {
+        activity_from_row(row, &types)
+    })?;
+    load_activities(conn, load, &mut activities, relations)?;
+    Ok(activities)
+}
+
+pub fn reminders_by_id(
+    conn: &Connection,
+    load: &mut LoadContext,
+    ids: &[u64],
+    relations: Relations,
+) -> Result<Vec<Reminder>, DbOperationsError> {
+    let mut reminders = by_id(conn, load, "reminders", ids, reminder_from_row)?;
+    load_reminders(conn, load, &mut reminders, relations)?;
+    Ok(reminders)
+}
+
+pub fn notes_by_id(
+    conn: &Connection,
+    load: &mut LoadContext,
+    ids: &[u64],
+    relations: Relations,
+) -> Result<Vec<Note>, DbOperationsError> {
+    let mut notes = by_id(conn, load, "notes", ids, note_from_row)?;
+    load_notes(conn, load, &mut notes, relations)?;
+    Ok(notes)
+}
+
+// The ids of the entities linked to each of `ids` through `link_table`, from
+// its `from` column to its `to` column. `to_table` is checked so trashed
+// entities are left out.
+fn linked_ids(
+    conn: &Connection,
+    load: &mut LoadContext,
+    link_table: &str,
+    from: &str,
+    to: &str,
+    to_table: &str,
+    ids: &[u64],
+) -> Result<HashMap<u64, Vec<u64>>, DbOperationsError> {
+    let sql = format!(
+        "SELECT l.id, l.{from} AS link_id, l.{to}
+        FROM {link_table} l JOIN {to_table} t ON t.id = l.{to}
+        WHERE l.{from} IN ({{}}) AND l.deleted = 0 AND t.deleted = 0
+        ORDER BY l.{to}"
+    );
+    let (links, linked): (Vec<u64>, Vec<u64>) =
+        batched(conn, load, link_table, &sql, ids, |row| {
+            row.get::<&str, u64>(to)
+        })?
+        .into_iter()
+        .unzip();
+    Ok(group(links, linked))
+}
+
+// The people selected by `sql` with their contact info, and the ids of
+// everything else they link to
+pub fn shallow_people<P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    sql: &str,
+    params: P,
+) -> Result<Vec<ShallowPerson>, DbOperationsError> {
+    let people = people(
+        conn,
+        load,
+        sql,
+        params,
+        Relations {
+            contact_info: true,
+            ..Relations::NONE
+        },
+    )?;
+    let ids = unique_ids(people.iter().map(|p| p.id));
+    let activities = linked_ids(
+        conn,
+        load,
+        "people_activities",
+        "person_id",
+        "activity_id",
+        "activities",
+        &ids,
+    )?;
+    let reminders = linked_ids(
+        conn,
+        load,
+        "people_reminders",
+        "person_id",
+        "reminder_id",
+        "reminders",
+        &ids,
+    )?;
+    let notes = linked_ids(
+        conn,
+        load,
+        "people_notes",
+        "person_id",
+        "note_id",
+        "notes",
+        &ids,
+    )?;
+    Ok(people
+        .into_iter()
+        .map(|person| ShallowPerson {
+            activity_ids: activities.get(&person.id).cloned().unwrap_or_default(),
+            reminder_ids: reminders.get(&person.id).cloned().unwrap_or_default(),
+            note_ids: notes.get(&person.id).cloned().unwrap_or_default(),
+            id: person.id,
+            name: person.name,
+            birthday: person.birthday,
+            contact_info: person.contact_info,
+            created_at: person.created_at,
+            updated_at: person.updated_at,
+        })
+        .collect())
+}
+
+// The ids of the people linked to each of `ids` through `link_table`
+fn person_ids(
+    conn: &Connection,
+    load: &mut LoadContext,
+    link_table: &str,
+    column: &str,
+    ids: &[u64],
+) -> Result<HashMap<u64, Vec<u64>>, DbOperationsError> {
+    linked_ids(conn, load, link_table, column, "person_id", "people", ids)
+}
+
+// The activities selected by `sql`, with the ids of their people
+pub fn shallow_activities<P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    sql: &str,
+    params: P,
+) -> Result<Vec<ShallowActivity>, DbOperationsError> {
+    let activities = activities(conn, load, sql, params, Relations::NONE)?;
+    let ids = unique_ids(activities.iter().map(|a| a.id));
+    let people = person_ids(conn, load, "people_activities", "activity_id", &ids)?;
+    Ok(activities
+        .into_iter()
+        .map(|activity| ShallowActivity {
+            person_ids: people.get(&activity.id).cloned().unwrap_or_default(),
+            id: activity.id,
+            name: activity.name,
+            activity_type: activity.activity_type,
+            date: activity.date,
+            time: activity.time,
+            timezone: activity.timezone,
+            content: activity.content,
+            created_at: activity.created_at,
+            updated_at: activity.updated_at,
+        })
+        .collect())
+}
+
+// The reminders selected by `sql`, with the ids of their people
+pub fn shallow_reminders<P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    sql: &str,
+    params: P,
+) -> Result<Vec<ShallowReminder>, DbOperationsError> {
+    let reminders = reminders(conn, load, sql, params, Relations::NONE)?;
+    let ids = unique_ids(reminders.iter().map(|r| r.id));
+    let people = person_ids(conn, load, "people_reminders", "reminder_id", &ids)?;
+    Ok(reminders
+        .into_iter()
+        .map(|reminder| ShallowReminder {
+            person_ids: people.get(&reminder.id).cloned().unwrap_or_default(),
+            id: reminder.id,
+            name: reminder.name,
+            date: reminder.date,
+            time: reminder.time,
+            timezone: reminder.timezone,
+            description: reminder.description,
+            recurring: reminder.recurring,
+            exdates: reminder.exdates,
+            history: reminder.history,
+            created_at: reminder.created_at,
+            updated_at: reminder.updated_at,
+        })
+        .collect())
+}
+
+// The notes selected by `sql`, with the ids of their people
+pub fn shallow_notes<P: Params>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    sql: &str,
+    params: P,
+) -> Result<Vec<ShallowNote>, DbOperationsError> {
+    let notes = notes(conn, load, sql, params, Relations::NONE)?;
+    let ids = unique_ids(notes.iter().map(|n| n.id));
+    let people = person_ids(conn, load, "people_notes", "note_id", &ids)?;
+    Ok(notes
+        .into_iter()
+        .map(|note| ShallowNote {
+            person_ids: people.get(&note.id).cloned().unwrap_or_default(),
+            id: note.id,
+            date: note.date,
+            time: note.time,
+            timezone: note.timezone,
+            content: note.content,
+            created_at: note.created_at,
+            updated_at: note.updated_at,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_people() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        let mut sql = String::new();
+        for id in 1..=50 {
+            sql.push_str(&format!(
+                "INSERT INTO people (name, birthday, deleted) VALUES ('Person {id}', NULL, FALSE);
+                INSERT INTO contact_info (person_id, contact_info_type_id, contact_info_details, deleted)
+                    VALUES ({id}, 3, 'p{id}@example.com', FALSE);
+                INSERT INTO notes (date, content, deleted) VALUES ('2024-01-02', 'Note {id}', FALSE);
+                INSERT INTO people_notes (person_id, note_id, deleted) VALUES ({id}, {id}, FALSE);
+                INSERT INTO people_notes (person_id, note_id, deleted) VALUES ({}, {id}, FALSE);",
+                id % 50 + 1
+            ));
+        }
+        conn.execute_batch(&sql).unwrap();
+
+        let loaded = people(
+            &conn,
+            &mut LoadContext::default(),
+            "SELECT * FROM people",
+            [],
+            Relations::ALL,
+        )
+        .unwrap();
+        assert_eq!(50, loaded.len());
+        let first = &loaded[0];
+        assert_eq!(
+            vec![String::from("p1@example.com")],
+            first
+                .contact_info
+                .iter()
+                .map(|ci| ci.details.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![String::from("Note 1"), String::from("Note 50")],
+            first
+                .notes
+                .iter()
+                .map(|n| n.content.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, first.notes[0].people.len());
+        assert!(first.notes[0].people[0].notes.is_empty());
+
+        let loaded = people(
+            &conn,
+            &mut LoadContext::default(),
+            "SELECT * FROM people WHERE id = ?1",
+            [1],
+            Relations {
+                notes: false,
+                ..Relations::ALL
+            },
+        )
+        .unwrap();
+        assert!(loaded[0].notes.is_empty());
+        assert_eq!(1, loaded[0].contact_info.len());
+    }
+
+    #[test]
+    fn test_invalid_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO people (name, birthday, deleted) VALUES ('Ana', NULL, FALSE);
+            INSERT INTO people (name, birthday, deleted) VALUES ('Bo', '', FALSE);
+            INSERT INTO people (name, birthday, deleted) VALUES ('Cy', 'soon', FALSE);
+            INSERT INTO people (name, birthday, deleted) VALUES ('Di', '2000-01-02', FALSE);
+            INSERT INTO reminders (name, date, description, recurring, rrule, deleted)
+                VALUES ('Call', '2024-01-02', NULL, 9, 'FREQ=SOMETIMES', FALSE);
+            INSERT INTO reminders (name, date, description, recurring, rrule, deleted)
+                VALUES ('Write', '2024-01-02', NULL, 9, 'FREQ=WEEKLY;BYDAY=MO,TH', FALSE);
+            INSERT INTO reminders (name, date, timezone, recurring, deleted)
+                VALUES ('Meet', '2024-05-01T18:30:00+01:00', 'Europe/Lisbon', 1, FALSE);
+            INSERT INTO reminders (name, date, timezone, recurring, deleted)
+                VALUES ('Land', '2024-05-01T18:30:00+01:00', 'Mars/Olympus', 1, FALSE);",
+        )
+        .unwrap();
+
+        let mut load = LoadContext::default();
+        let loaded = people(&conn, &mut load, "SELECT * FROM people", [], Relations::ALL).unwrap();
+        assert_eq!(
+            vec![
+                ("Ana", None),
+                ("Bo", None),
+                ("Di", NaiveDate::from_ymd_opt(2000, 1, 2))
+            ],
+            loaded
+                .iter()
+                .map(|p| (p.name.as_str(), p.birthday))
+                .collect::<Vec<_>>()
+        );
+        let loaded = reminders(
+            &conn,
+            &mut load,
+            "SELECT * FROM reminders",
+            [],
+            Relations::ALL,
+        )
+        .unwrap();
+        assert_eq!(vec![2, 3], loaded.iter().map(|r| r.id).collect::<Vec<_>>());
+        assert_eq!(
+            "2024-05-01 18:30 Europe/Lisbon",
+            loaded[1].moment().to_string()
+        );
+
+        assert_eq!(
+            vec![("people", 3), ("reminders", 1), ("reminders", 4)],
+            load.skipped
+                .iter()
+                .map(|s| (s.table.as_str(), s.id))
+                .collect::<Vec<_>>()
+        );
+
+        match people(
+            &conn,
+            &mut LoadContext::new(true),
+            "SELECT * FROM people",
+            [],
+            Relations::ALL,
+        ) {
+            Err(DbOperationsError::InvalidRows { table, ids, .. }) => {
+                assert_eq!("people", table);
+                assert_eq!(vec![3], ids);
+            }
+            _ => panic!("expected invalid rows"),
+        }
+    }
+}
@@ -0,0 +1,1575 @@
+use rusqlite::{params, Connection};
+
+use crate::db_interface::DbOperationsError;
+
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+// Migrations are applied in order and must never be edited once released:
+// add a new entry with the next version number instead.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: "
+        CREATE TABLE people (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            birthday TEXT,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE activities (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            type INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            content TEXT,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE reminders (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            date TEXT NOT NULL,
+            description TEXT,
+            recurring INTEGER NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE notes (
+            id INTEGER PRIMARY KEY,
+            date TEXT NOT NULL,
+            content TEXT NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE contact_info (
+            id INTEGER PRIMARY KEY,
+            person_id INTEGER NOT NULL,
+            contact_info_type_id INTEGER NOT NULL,
+            contact_info_details TEXT,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE contact_info_types (
+            id INTEGER PRIMARY KEY,
+            type TEXT NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE people_activities (
+            id INTEGER PRIMARY KEY,
+            person_id INTEGER NOT NULL,
+            activity_id INTEGER NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE people_reminders (
+            id INTEGER PRIMARY KEY,
+            person_id INTEGER NOT NULL,
+            reminder_id INTEGER NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE people_notes (
+            id INTEGER PRIMARY KEY,
+            person_id INTEGER NOT NULL,
+            note_id INTEGER NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE activity_types (
+            id INTEGER PRIMARY KEY,
+            type TEXT NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        CREATE TABLE recurring_types (
+            id INTEGER PRIMARY KEY,
+            type TEXT NOT NULL,
+            deleted INTEGER NOT NULL
+        );
+        INSERT INTO contact_info_types (type, deleted)
+        VALUES
+            ('Phone', FALSE),
+            ('WhatsApp', FALSE),
+            ('Email', FALSE);
+        INSERT INTO activity_types (type, deleted)
+        VALUES
+            ('Phone', FALSE),
+            ('InPerson', FALSE),
+            ('Online', FALSE);
+        INSERT INTO recurring_types (type, deleted)
+        VALUES
+            ('OneTime', FALSE),
+            ('Daily', FALSE),
+            ('Weekly', FALSE),
+            ('Fortnightly', FALSE),
+            ('Monthly', FALSE),
+            ('Quarterly', FALSE),
+            ('Biannual', FALSE),
+            ('Yearly', FALSE);
+    ",
+    },
+    // A single FTS5 index over every searchable field, kept in sync with the
+    // source tables by triggers. Soft-deleted rows are dropped from the index.
+    // Contact info only indexes the details: the person's name is already
+    // indexed on its own row.
+    Migration {
+        version: 2,
+        name: "full_text_search",
+        sql: "
+        CREATE VIRTUAL TABLE search_index USING fts5(
+            entity UNINDEXED,
+            entity_id UNINDEXED,
+            title,
+            body,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        INSERT INTO search_index (entity, entity_id, title, body)
+            SELECT 'person', id, name, COALESCE(birthday, '') FROM people WHERE deleted = 0;
+        INSERT INTO search_index (entity, entity_id, title, body)
+            SELECT 'activity', id, name, COALESCE(content, '') FROM activities WHERE deleted = 0;
+        INSERT INTO search_index (entity, entity_id, title, body)
+            SELECT 'reminder', id, name, COALESCE(description, '') FROM reminders WHERE deleted = 0;
+        INSERT INTO search_index (entity, entity_id, title, body)
+            SELECT 'note', id, '', content FROM notes WHERE deleted = 0;
+        INSERT INTO search_index (entity, entity_id, title, body)
+            SELECT 'contact_info', id, '', COALESCE(contact_info_details, '')
+            FROM contact_info WHERE deleted = 0;
+
+        CREATE TRIGGER people_search_insert AFTER INSERT ON people WHEN NEW.deleted = 0 BEGIN
+            INSERT INTO search_index (entity, entity_id, title, body)
+                VALUES ('person', NEW.id, NEW.name, COALESCE(NEW.birthday, ''));
+        END;
+        CREATE TRIGGER people_search_update AFTER UPDATE ON people BEGIN
+            DELETE FROM search_index WHERE entity = 'person' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity, entity_id, title, body)
+                SELECT 'person', NEW.id, NEW.name, COALESCE(NEW.birthday, '') WHERE NEW.deleted = 0;
+        END;
+        CREATE TRIGGER people_search_delete AFTER DELETE ON people BEGIN
+            DELETE FROM search_index WHERE entity = 'person' AND entity_id = OLD.id;
+        END;
+
+        CREATE TRIGGER activities_search_insert AFTER INSERT ON activities WHEN NEW.deleted = 0 BEGIN
+            INSERT INTO search_index (entity, entity_id, title, body)
+                VALUES ('activity', NEW.id, NEW.name, COALESCE(NEW.content, ''));
+        END;
+        CREATE TRIGGER activities_search_update AFTER UPDATE ON activities BEGIN
+            DELETE FROM search_index WHERE entity = 'activity' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity, entity_id, title, body)
+                SELECT 'activity', NEW.id, NEW.name, COALESCE(NEW.content, '') WHERE NEW.deleted = 0;
+        END;
+        CREATE TRIGGER activities_search_delete AFTER DELETE ON activities BEGIN
+            DELETE FROM search_index WHERE entity = 'activity' AND entity_id = OLD.id;
+        END;
+
+        CREATE TRIGGER reminders_search_insert AFTER INSERT ON reminders WHEN NEW.deleted = 0 BEGIN
+            INSERT INTO search_index (entity, entity_id, title, body)
+                VALUES ('reminder', NEW.id, NEW.name, COALESCE(NEW.description, ''));
+        END;
+        CREATE TRIGGER reminders_search_update AFTER UPDATE ON reminders BEGIN
+            DELETE FROM search_index WHERE entity = 'reminder' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity, entity_id, title, body)
+                SELECT 'reminder', NEW.id, NEW.name, COALESCE(NEW.description, '') WHERE NEW.deleted = 0;
+        END;
+        CREATE TRIGGER reminders_search_delete AFTER DELETE ON reminders BEGIN
+            DELETE FROM search_index WHERE entity = 'reminder' AND entity_id = OLD.id;
+        END;
+
+        CREATE TRIGGER notes_search_insert AFTER INSERT ON notes WHEN NEW.deleted = 0 BEGIN
+            INSERT INTO search_index (entity, entity_id, title, body)
+                VALUES ('note', NEW.id, '', NEW.content);
+        END;
+        CREATE TRIGGER notes_search_update AFTER UPDATE ON notes BEGIN
+            DELETE FROM search_index WHERE entity = 'note' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity, entity_id, title, body)
+                SELECT 'note', NEW.id, '', NEW.content WHERE NEW.deleted = 0;
+        END;
+        CREATE TRIGGER notes_search_delete AFTER DELETE ON notes BEGIN
+            DELETE FROM search_index WHERE entity = 'note' AND entity_id = OLD.id;
+        END;
+
+        CREATE TRIGGER contact_info_search_insert AFTER INSERT ON contact_info WHEN NEW.deleted = 0 BEGIN
+            INSERT INTO search_index (entity, entity_id, title, body)
+                VALUES ('contact_info', NEW.id, '', COALESCE(NEW.contact_info_details, ''));
+        END;
+        CREATE TRIGGER contact_info_search_update AFTER UPDATE ON contact_info BEGIN
+            DELETE FROM search_index WHERE entity = 'contact_info' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity, entity_id, title, body)
+                SELECT 'contact_info', NEW.id, '', COALESCE(NEW.contact_info_details, '')
+                WHERE NEW.deleted = 0;
+        END;
+        CREATE TRIGGER contact_info_search_delete AFTER DELETE ON contact_info BEGIN
+            DELETE FROM search_index WHERE entity = 'contact_info' AND entity_id = OLD.id;
+        END;
+    ",
+    },
+    // Records when rows were soft-deleted. Deleting an entity also
+    // soft-deletes its join rows with the same timestamp, which is how
+    // `trash::restore` knows which links to bring back.
+    Migration {
+        version: 3,
+        name: "trash",
+        sql: "
+        ALTER TABLE people ADD COLUMN deleted_at TEXT;
+        ALTER TABLE activities ADD COLUMN deleted_at TEXT;
+        ALTER TABLE reminders ADD COLUMN deleted_at TEXT;
+        ALTER TABLE notes ADD COLUMN deleted_at TEXT;
+        ALTER TABLE contact_info ADD COLUMN deleted_at TEXT;
+        ALTER TABLE people_activities ADD COLUMN deleted_at TEXT;
+        ALTER TABLE people_reminders ADD COLUMN deleted_at TEXT;
+        ALTER TABLE people_notes ADD COLUMN deleted_at TEXT;
+
+        UPDATE people SET deleted_at = datetime('now') WHERE deleted = 1;
+        UPDATE activities SET deleted_at = datetime('now') WHERE deleted = 1;
+        UPDATE reminders SET deleted_at = datetime('now') WHERE deleted = 1;
+        UPDATE notes SET deleted_at = datetime('now') WHERE deleted = 1;
+        UPDATE contact_info SET deleted_at = datetime('now') WHERE deleted = 1;
+        UPDATE people_activities SET deleted_at = datetime('now') WHERE deleted = 1;
+        UPDATE people_reminders SET deleted_at = datetime('now') WHERE deleted = 1;
+        UPDATE people_notes SET deleted_at = datetime('now') WHERE deleted = 1;
+
+        CREATE TRIGGER people_soft_delete AFTER UPDATE OF deleted ON people
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0 BEGIN
+            UPDATE people SET deleted_at = datetime('now') WHERE id = NEW.id;
+            UPDATE people_activities SET deleted = 1, deleted_at = datetime('now')
+                WHERE person_id = NEW.id AND deleted = 0;
+            UPDATE people_reminders SET deleted = 1, deleted_at = datetime('now')
+                WHERE person_id = NEW.id AND deleted = 0;
+            UPDATE people_notes SET deleted = 1, deleted_at = datetime('now')
+                WHERE person_id = NEW.id AND deleted = 0;
+            UPDATE contact_info SET deleted = 1, deleted_at = datetime('now')
+                WHERE person_id = NEW.id AND deleted = 0;
+        END;
+        CREATE TRIGGER people_undelete AFTER UPDATE OF deleted ON people
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1 BEGIN
+            UPDATE people SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER activities_soft_delete AFTER UPDATE OF deleted ON activities
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0 BEGIN
+            UPDATE activities SET deleted_at = datetime('now') WHERE id = NEW.id;
+            UPDATE people_activities SET deleted = 1, deleted_at = datetime('now')
+                WHERE activity_id = NEW.id AND deleted = 0;
+        END;
+        CREATE TRIGGER activities_undelete AFTER UPDATE OF deleted ON activities
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1 BEGIN
+            UPDATE activities SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER reminders_soft_delete AFTER UPDATE OF deleted ON reminders
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0 BEGIN
+            UPDATE reminders SET deleted_at = datetime('now') WHERE id = NEW.id;
+            UPDATE people_reminders SET deleted = 1, deleted_at = datetime('now')
+                WHERE reminder_id = NEW.id AND deleted = 0;
+        END;
+        CREATE TRIGGER reminders_undelete AFTER UPDATE OF deleted ON reminders
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1 BEGIN
+            UPDATE reminders SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER notes_soft_delete AFTER UPDATE OF deleted ON notes
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0 BEGIN
+            UPDATE notes SET deleted_at = datetime('now') WHERE id = NEW.id;
+            UPDATE people_notes SET deleted = 1, deleted_at = datetime('now')
+                WHERE note_id = NEW.id AND deleted = 0;
+        END;
+        CREATE TRIGGER notes_undelete AFTER UPDATE OF deleted ON notes
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1 BEGIN
+            UPDATE notes SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER contact_info_soft_delete AFTER UPDATE OF deleted ON contact_info
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0 BEGIN
+            UPDATE contact_info SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER contact_info_undelete AFTER UPDATE OF deleted ON contact_info
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1 BEGIN
+            UPDATE contact_info SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER people_activities_soft_delete AFTER UPDATE OF deleted ON people_activities
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0 BEGIN
+            UPDATE people_activities SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER people_activities_undelete AFTER UPDATE OF deleted ON people_activities
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1 BEGIN
+            UPDATE people_activities SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER people_reminders_soft_delete AFTER UPDATE OF deleted ON people_reminders
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0 BEGIN
+            UPDATE people_reminders SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER people_reminders_undelete AFTER UPDATE OF deleted ON people_reminders
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1 BEGIN
+            UPDATE people_reminders SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER people_notes_soft_delete AFTER UPDATE OF deleted ON people_notes
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0 BEGIN
+            UPDATE people_notes SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER people_notes_undelete AFTER UPDATE OF deleted ON people_notes
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1 BEGIN
+            UPDATE people_notes SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+    ",
+    },
+    // Records row level before/after snapshots of every change made by
+    // `DbOperations::add`, `save` and `remove`, so they can be undone and
+    // redone. Updates and deletes are recorded by BEFORE triggers so that
+    // changes made by other triggers are journaled after the change that
+    // caused them. The trash triggers are recreated to stand aside while a
+    // journal entry is replayed.
+    Migration {
+        version: 4,
+        name: "journal",
+        sql: "
+        CREATE TABLE journal (
+            id INTEGER PRIMARY KEY,
+            operation TEXT NOT NULL,
+            entity TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            undone INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE journal_changes (
+            id INTEGER PRIMARY KEY,
+            journal_id INTEGER NOT NULL,
+            table_name TEXT NOT NULL,
+            row_id INTEGER NOT NULL,
+            before TEXT,
+            after TEXT
+        );
+        CREATE INDEX journal_changes_journal_id ON journal_changes (journal_id);
+        -- `active` is the journal entry changes are currently recorded
+        -- against; `replaying` is set while undoing or redoing
+        CREATE TABLE journal_state (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            active INTEGER,
+            replaying INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO journal_state (id, active, replaying) VALUES (1, NULL, 0);
+
+        CREATE TRIGGER people_journal_insert AFTER INSERT ON people
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'birthday', NEW.birthday, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_journal_update BEFORE UPDATE ON people
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'birthday', OLD.birthday, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'birthday', NEW.birthday, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_journal_delete BEFORE DELETE ON people
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'birthday', OLD.birthday, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        CREATE TRIGGER activities_journal_insert AFTER INSERT ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'type', NEW.type, 'date', NEW.date, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER activities_journal_update BEFORE UPDATE ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'type', OLD.type, 'date', OLD.date, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'type', NEW.type, 'date', NEW.date, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER activities_journal_delete BEFORE DELETE ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'type', OLD.type, 'date', OLD.date, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        CREATE TRIGGER reminders_journal_insert AFTER INSERT ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'date', NEW.date, 'description', NEW.description, 'recurring', NEW.recurring, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER reminders_journal_update BEFORE UPDATE ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'date', OLD.date, 'description', OLD.description, 'recurring', OLD.recurring, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'date', NEW.date, 'description', NEW.description, 'recurring', NEW.recurring, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER reminders_journal_delete BEFORE DELETE ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'date', OLD.date, 'description', OLD.description, 'recurring', OLD.recurring, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        CREATE TRIGGER notes_journal_insert AFTER INSERT ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', NEW.id, NULL,
+                    json_object('id', NEW.id, 'date', NEW.date, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER notes_journal_update BEFORE UPDATE ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', NEW.id,
+                    json_object('id', OLD.id, 'date', OLD.date, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'date', NEW.date, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER notes_journal_delete BEFORE DELETE ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', OLD.id,
+                    json_object('id', OLD.id, 'date', OLD.date, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        CREATE TRIGGER contact_info_journal_insert AFTER INSERT ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'contact_info_type_id', NEW.contact_info_type_id, 'contact_info_details', NEW.contact_info_details, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER contact_info_journal_update BEFORE UPDATE ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'contact_info_type_id', OLD.contact_info_type_id, 'contact_info_details', OLD.contact_info_details, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'contact_info_type_id', NEW.contact_info_type_id, 'contact_info_details', NEW.contact_info_details, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER contact_info_journal_delete BEFORE DELETE ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'contact_info_type_id', OLD.contact_info_type_id, 'contact_info_details', OLD.contact_info_details, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        CREATE TRIGGER people_activities_journal_insert AFTER INSERT ON people_activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_activities', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'activity_id', NEW.activity_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_activities_journal_update BEFORE UPDATE ON people_activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_activities', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'activity_id', OLD.activity_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'activity_id', NEW.activity_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_activities_journal_delete BEFORE DELETE ON people_activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_activities', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'activity_id', OLD.activity_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        CREATE TRIGGER people_reminders_journal_insert AFTER INSERT ON people_reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_reminders', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'reminder_id', NEW.reminder_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_reminders_journal_update BEFORE UPDATE ON people_reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_reminders', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'reminder_id', OLD.reminder_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'reminder_id', NEW.reminder_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_reminders_journal_delete BEFORE DELETE ON people_reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_reminders', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'reminder_id', OLD.reminder_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        CREATE TRIGGER people_notes_journal_insert AFTER INSERT ON people_notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_notes', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'note_id', NEW.note_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_notes_journal_update BEFORE UPDATE ON people_notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_notes', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'note_id', OLD.note_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'note_id', NEW.note_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_notes_journal_delete BEFORE DELETE ON people_notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_notes', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'note_id', OLD.note_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        -- Replaying a journal entry restores the cascaded rows itself
+        DROP TRIGGER people_soft_delete;
+        CREATE TRIGGER people_soft_delete AFTER UPDATE OF deleted ON people
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people SET deleted_at = datetime('now') WHERE id = NEW.id;
+            UPDATE people_activities SET deleted = 1, deleted_at = datetime('now')
+                WHERE person_id = NEW.id AND deleted = 0;
+            UPDATE people_reminders SET deleted = 1, deleted_at = datetime('now')
+                WHERE person_id = NEW.id AND deleted = 0;
+            UPDATE people_notes SET deleted = 1, deleted_at = datetime('now')
+                WHERE person_id = NEW.id AND deleted = 0;
+            UPDATE contact_info SET deleted = 1, deleted_at = datetime('now')
+                WHERE person_id = NEW.id AND deleted = 0;
+        END;
+        DROP TRIGGER activities_soft_delete;
+        CREATE TRIGGER activities_soft_delete AFTER UPDATE OF deleted ON activities
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE activities SET deleted_at = datetime('now') WHERE id = NEW.id;
+            UPDATE people_activities SET deleted = 1, deleted_at = datetime('now')
+                WHERE activity_id = NEW.id AND deleted = 0;
+        END;
+        DROP TRIGGER reminders_soft_delete;
+        CREATE TRIGGER reminders_soft_delete AFTER UPDATE OF deleted ON reminders
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE reminders SET deleted_at = datetime('now') WHERE id = NEW.id;
+            UPDATE people_reminders SET deleted = 1, deleted_at = datetime('now')
+                WHERE reminder_id = NEW.id AND deleted = 0;
+        END;
+        DROP TRIGGER notes_soft_delete;
+        CREATE TRIGGER notes_soft_delete AFTER UPDATE OF deleted ON notes
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE notes SET deleted_at = datetime('now') WHERE id = NEW.id;
+            UPDATE people_notes SET deleted = 1, deleted_at = datetime('now')
+                WHERE note_id = NEW.id AND deleted = 0;
+        END;
+        DROP TRIGGER contact_info_soft_delete;
+        CREATE TRIGGER contact_info_soft_delete AFTER UPDATE OF deleted ON contact_info
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE contact_info SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        DROP TRIGGER people_activities_soft_delete;
+        CREATE TRIGGER people_activities_soft_delete AFTER UPDATE OF deleted ON people_activities
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_activities SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        DROP TRIGGER people_reminders_soft_delete;
+        CREATE TRIGGER people_reminders_soft_delete AFTER UPDATE OF deleted ON people_reminders
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_reminders SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        DROP TRIGGER people_notes_soft_delete;
+        CREATE TRIGGER people_notes_soft_delete AFTER UPDATE OF deleted ON people_notes
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_notes SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        DROP TRIGGER people_undelete;
+        CREATE TRIGGER people_undelete AFTER UPDATE OF deleted ON people
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+        DROP TRIGGER activities_undelete;
+        CREATE TRIGGER activities_undelete AFTER UPDATE OF deleted ON activities
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE activities SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+        DROP TRIGGER reminders_undelete;
+        CREATE TRIGGER reminders_undelete AFTER UPDATE OF deleted ON reminders
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE reminders SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+        DROP TRIGGER notes_undelete;
+        CREATE TRIGGER notes_undelete AFTER UPDATE OF deleted ON notes
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE notes SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+        DROP TRIGGER contact_info_undelete;
+        CREATE TRIGGER contact_info_undelete AFTER UPDATE OF deleted ON contact_info
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE contact_info SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+        DROP TRIGGER people_activities_undelete;
+        CREATE TRIGGER people_activities_undelete AFTER UPDATE OF deleted ON people_activities
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_activities SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+        DROP TRIGGER people_reminders_undelete;
+        CREATE TRIGGER people_reminders_undelete AFTER UPDATE OF deleted ON people_reminders
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_reminders SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+        DROP TRIGGER people_notes_undelete;
+        CREATE TRIGGER people_notes_undelete AFTER UPDATE OF deleted ON people_notes
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_notes SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+    ",
+    },
+    Migration {
+        version: 5,
+        name: "timestamps_and_audit",
+        sql: "
+        ALTER TABLE people ADD COLUMN created_at TEXT;
+        ALTER TABLE people ADD COLUMN updated_at TEXT;
+        ALTER TABLE activities ADD COLUMN created_at TEXT;
+        ALTER TABLE activities ADD COLUMN updated_at TEXT;
+        ALTER TABLE reminders ADD COLUMN created_at TEXT;
+        ALTER TABLE reminders ADD COLUMN updated_at TEXT;
+        ALTER TABLE notes ADD COLUMN created_at TEXT;
+        ALTER TABLE notes ADD COLUMN updated_at TEXT;
+        ALTER TABLE contact_info ADD COLUMN created_at TEXT;
+        ALTER TABLE contact_info ADD COLUMN updated_at TEXT;
+
+        -- Field level changes, derived from the journal when an operation
+        -- finishes or is undone/redone
+        CREATE TABLE audit (
+            id INTEGER PRIMARY KEY,
+            journal_id INTEGER NOT NULL,
+            operation TEXT NOT NULL,
+            entity TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            field TEXT NOT NULL,
+            old_value TEXT,
+            new_value TEXT,
+            changed_at TEXT NOT NULL
+        );
+        CREATE INDEX audit_entity ON audit (entity, entity_id);
+
+        -- The journal snapshots have to include the new columns
+
+        DROP TRIGGER people_journal_insert;
+        CREATE TRIGGER people_journal_insert AFTER INSERT ON people
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'birthday', NEW.birthday, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER people_journal_update;
+        CREATE TRIGGER people_journal_update BEFORE UPDATE ON people
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'birthday', OLD.birthday, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'birthday', NEW.birthday, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER people_journal_delete;
+        CREATE TRIGGER people_journal_delete BEFORE DELETE ON people
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'birthday', OLD.birthday, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        DROP TRIGGER activities_journal_insert;
+        CREATE TRIGGER activities_journal_insert AFTER INSERT ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'type', NEW.type, 'date', NEW.date, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER activities_journal_update;
+        CREATE TRIGGER activities_journal_update BEFORE UPDATE ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'type', OLD.type, 'date', OLD.date, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'type', NEW.type, 'date', NEW.date, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER activities_journal_delete;
+        CREATE TRIGGER activities_journal_delete BEFORE DELETE ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'type', OLD.type, 'date', OLD.date, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        DROP TRIGGER reminders_journal_insert;
+        CREATE TRIGGER reminders_journal_insert AFTER INSERT ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'date', NEW.date, 'description', NEW.description, 'recurring', NEW.recurring, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER reminders_journal_update;
+        CREATE TRIGGER reminders_journal_update BEFORE UPDATE ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'date', OLD.date, 'description', OLD.description, 'recurring', OLD.recurring, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'date', NEW.date, 'description', NEW.description, 'recurring', NEW.recurring, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER reminders_journal_delete;
+        CREATE TRIGGER reminders_journal_delete BEFORE DELETE ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'date', OLD.date, 'description', OLD.description, 'recurring', OLD.recurring, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        DROP TRIGGER notes_journal_insert;
+        CREATE TRIGGER notes_journal_insert AFTER INSERT ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', NEW.id, NULL,
+                    json_object('id', NEW.id, 'date', NEW.date, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER notes_journal_update;
+        CREATE TRIGGER notes_journal_update BEFORE UPDATE ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', NEW.id,
+                    json_object('id', OLD.id, 'date', OLD.date, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'date', NEW.date, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER notes_journal_delete;
+        CREATE TRIGGER notes_journal_delete BEFORE DELETE ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', OLD.id,
+                    json_object('id', OLD.id, 'date', OLD.date, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        DROP TRIGGER contact_info_journal_insert;
+        CREATE TRIGGER contact_info_journal_insert AFTER INSERT ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'contact_info_type_id', NEW.contact_info_type_id, 'contact_info_details', NEW.contact_info_details, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER contact_info_journal_update;
+        CREATE TRIGGER contact_info_journal_update BEFORE UPDATE ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'contact_info_type_id', OLD.contact_info_type_id, 'contact_info_details', OLD.contact_info_details, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'contact_info_type_id', NEW.contact_info_type_id, 'contact_info_details', NEW.contact_info_details, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER contact_info_journal_delete;
+        CREATE TRIGGER contact_info_journal_delete BEFORE DELETE ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'contact_info_type_id', OLD.contact_info_type_id, 'contact_info_details', OLD.contact_info_details, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+    ",
+    },
+    Migration {
+        version: 6,
+        name: "foreign_keys",
+        sql: "
+        -- Remove rows that would violate the new constraints: links to rows
+        -- that do not exist, and duplicate links, keeping the active one
+        DELETE FROM contact_info
+            WHERE person_id NOT IN (SELECT id FROM people)
+            OR contact_info_type_id NOT IN (SELECT id FROM contact_info_types);
+        DELETE FROM people_activities
+            WHERE person_id NOT IN (SELECT id FROM people)
+            OR activity_id NOT IN (SELECT id FROM activities);
+        DELETE FROM people_activities WHERE id IN (
+            SELECT id FROM (
+                SELECT id, ROW_NUMBER() OVER (
+                    PARTITION BY person_id, activity_id ORDER BY deleted, id
+                ) AS n
+                FROM people_activities
+            )
+            WHERE n > 1
+        );
+        DELETE FROM people_reminders
+            WHERE person_id NOT IN (SELECT id FROM people)
+            OR reminder_id NOT IN (SELECT id FROM reminders);
+        DELETE FROM people_reminders WHERE id IN (
+            SELECT id FROM (
+                SELECT id, ROW_NUMBER() OVER (
+                    PARTITION BY person_id, reminder_id ORDER BY deleted, id
+                ) AS n
+                FROM people_reminders
+            )
+            WHERE n > 1
+        );
+        DELETE FROM people_notes
+            WHERE person_id NOT IN (SELECT id FROM people)
+            OR note_id NOT IN (SELECT id FROM notes);
+        DELETE FROM people_notes WHERE id IN (
+            SELECT id FROM (
+                SELECT id, ROW_NUMBER() OVER (
+                    PARTITION BY person_id, note_id ORDER BY deleted, id
+                ) AS n
+                FROM people_notes
+            )
+            WHERE n > 1
+        );
+
+        -- SQLite cannot add constraints to existing tables, so they are
+        -- recreated. Dropping a table drops its triggers, which are restored
+        -- below. Triggers on other tables still refer to the dropped tables
+        -- until the new ones are renamed, which only legacy renames allow.
+        PRAGMA legacy_alter_table = ON;
+        CREATE TABLE contact_info_new (
+            id INTEGER PRIMARY KEY,
+            person_id INTEGER NOT NULL
+                REFERENCES people (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+            contact_info_type_id INTEGER NOT NULL
+                REFERENCES contact_info_types (id) DEFERRABLE INITIALLY DEFERRED,
+            contact_info_details TEXT,
+            deleted INTEGER NOT NULL,
+            deleted_at TEXT,
+            created_at TEXT,
+            updated_at TEXT
+        );
+        INSERT INTO contact_info_new
+            SELECT id, person_id, contact_info_type_id, contact_info_details, deleted,
+                deleted_at, created_at, updated_at
+            FROM contact_info;
+        DROP TABLE contact_info;
+        ALTER TABLE contact_info_new RENAME TO contact_info;
+        CREATE TABLE people_activities_new (
+            id INTEGER PRIMARY KEY,
+            person_id INTEGER NOT NULL
+                REFERENCES people (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+            activity_id INTEGER NOT NULL
+                REFERENCES activities (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+            deleted INTEGER NOT NULL,
+            deleted_at TEXT,
+            UNIQUE (person_id, activity_id)
+        );
+        INSERT INTO people_activities_new
+            SELECT id, person_id, activity_id, deleted, deleted_at FROM people_activities;
+        DROP TABLE people_activities;
+        ALTER TABLE people_activities_new RENAME TO people_activities;
+        CREATE TABLE people_reminders_new (
+            id INTEGER PRIMARY KEY,
+            person_id INTEGER NOT NULL
+                REFERENCES people (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+            reminder_id INTEGER NOT NULL
+                REFERENCES reminders (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+            deleted INTEGER NOT NULL,
+            deleted_at TEXT,
+            UNIQUE (person_id, reminder_id)
+        );
+        INSERT INTO people_reminders_new
+            SELECT id, person_id, reminder_id, deleted, deleted_at FROM people_reminders;
+        DROP TABLE people_reminders;
+        ALTER TABLE people_reminders_new RENAME TO people_reminders;
+        CREATE TABLE people_notes_new (
+            id INTEGER PRIMARY KEY,
+            person_id INTEGER NOT NULL
+                REFERENCES people (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+            note_id INTEGER NOT NULL
+                REFERENCES notes (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+            deleted INTEGER NOT NULL,
+            deleted_at TEXT,
+            UNIQUE (person_id, note_id)
+        );
+        INSERT INTO people_notes_new
+            SELECT id, person_id, note_id, deleted, deleted_at FROM people_notes;
+        DROP TABLE people_notes;
+        ALTER TABLE people_notes_new RENAME TO people_notes;
+        PRAGMA legacy_alter_table = OFF;
+
+        CREATE INDEX contact_info_person_id ON contact_info (person_id);
+        CREATE INDEX people_activities_activity_id ON people_activities (activity_id);
+        CREATE INDEX people_reminders_reminder_id ON people_reminders (reminder_id);
+        CREATE INDEX people_notes_note_id ON people_notes (note_id);
+        CREATE INDEX people_name ON people (name COLLATE NOCASE);
+        CREATE INDEX activities_type ON activities (type);
+        CREATE INDEX reminders_name ON reminders (name COLLATE NOCASE);
+
+        CREATE TRIGGER contact_info_search_insert AFTER INSERT ON contact_info WHEN NEW.deleted = 0 BEGIN
+            INSERT INTO search_index (entity, entity_id, title, body)
+                VALUES ('contact_info', NEW.id, '', COALESCE(NEW.contact_info_details, ''));
+        END;
+        CREATE TRIGGER contact_info_search_update AFTER UPDATE ON contact_info BEGIN
+            DELETE FROM search_index WHERE entity = 'contact_info' AND entity_id = OLD.id;
+            INSERT INTO search_index (entity, entity_id, title, body)
+                SELECT 'contact_info', NEW.id, '', COALESCE(NEW.contact_info_details, '')
+                WHERE NEW.deleted = 0;
+        END;
+        CREATE TRIGGER contact_info_search_delete AFTER DELETE ON contact_info BEGIN
+            DELETE FROM search_index WHERE entity = 'contact_info' AND entity_id = OLD.id;
+        END;
+        CREATE TRIGGER contact_info_soft_delete AFTER UPDATE OF deleted ON contact_info
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE contact_info SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER contact_info_undelete AFTER UPDATE OF deleted ON contact_info
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE contact_info SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER contact_info_journal_insert AFTER INSERT ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'contact_info_type_id', NEW.contact_info_type_id, 'contact_info_details', NEW.contact_info_details, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER contact_info_journal_update BEFORE UPDATE ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'contact_info_type_id', OLD.contact_info_type_id, 'contact_info_details', OLD.contact_info_details, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'contact_info_type_id', NEW.contact_info_type_id, 'contact_info_details', NEW.contact_info_details, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER contact_info_journal_delete BEFORE DELETE ON contact_info
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'contact_info', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'contact_info_type_id', OLD.contact_info_type_id, 'contact_info_details', OLD.contact_info_details, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        CREATE TRIGGER people_activities_journal_insert AFTER INSERT ON people_activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_activities', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'activity_id', NEW.activity_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_activities_journal_update BEFORE UPDATE ON people_activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_activities', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'activity_id', OLD.activity_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'activity_id', NEW.activity_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_activities_journal_delete BEFORE DELETE ON people_activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_activities', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'activity_id', OLD.activity_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_activities_soft_delete AFTER UPDATE OF deleted ON people_activities
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_activities SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER people_activities_undelete AFTER UPDATE OF deleted ON people_activities
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_activities SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER people_reminders_journal_insert AFTER INSERT ON people_reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_reminders', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'reminder_id', NEW.reminder_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_reminders_journal_update BEFORE UPDATE ON people_reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_reminders', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'reminder_id', OLD.reminder_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'reminder_id', NEW.reminder_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_reminders_journal_delete BEFORE DELETE ON people_reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_reminders', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'reminder_id', OLD.reminder_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_reminders_soft_delete AFTER UPDATE OF deleted ON people_reminders
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_reminders SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER people_reminders_undelete AFTER UPDATE OF deleted ON people_reminders
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_reminders SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+
+        CREATE TRIGGER people_notes_journal_insert AFTER INSERT ON people_notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_notes', NEW.id, NULL,
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'note_id', NEW.note_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_notes_journal_update BEFORE UPDATE ON people_notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_notes', NEW.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'note_id', OLD.note_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    json_object('id', NEW.id, 'person_id', NEW.person_id, 'note_id', NEW.note_id, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_notes_journal_delete BEFORE DELETE ON people_notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'people_notes', OLD.id,
+                    json_object('id', OLD.id, 'person_id', OLD.person_id, 'note_id', OLD.note_id, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at),
+                    NULL
+                FROM journal_state;
+        END;
+        CREATE TRIGGER people_notes_soft_delete AFTER UPDATE OF deleted ON people_notes
+        WHEN NEW.deleted = 1 AND OLD.deleted = 0
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_notes SET deleted_at = datetime('now') WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER people_notes_undelete AFTER UPDATE OF deleted ON people_notes
+        WHEN NEW.deleted = 0 AND OLD.deleted = 1
+            AND NOT (SELECT replaying FROM journal_state) BEGIN
+            UPDATE people_notes SET deleted_at = NULL WHERE id = NEW.id;
+        END;
+    ",
+    },
+    Migration {
+        version: 7,
+        name: "recurrence_rules",
+        sql: "
+        -- Reminders recur by an RRULE, with exception dates. The recurring
+        -- type is kept next to it, Custom for rules no type stands for.
+        ALTER TABLE reminders ADD COLUMN rrule TEXT;
+        ALTER TABLE reminders ADD COLUMN exdates TEXT;
+        INSERT INTO recurring_types (type, deleted) VALUES ('Custom', FALSE);
+
+        -- The rules of the recurring types. Month based ones starting after
+        -- the 28th pick the last day they can, as the types did.
+        UPDATE reminders SET rrule = (
+            SELECT
+                CASE t.type
+                    WHEN 'Daily' THEN 'FREQ=DAILY'
+                    WHEN 'Weekly' THEN 'FREQ=WEEKLY'
+                    WHEN 'Fortnightly' THEN 'FREQ=WEEKLY;INTERVAL=2'
+                    WHEN 'Monthly' THEN 'FREQ=MONTHLY'
+                    WHEN 'Quarterly' THEN 'FREQ=MONTHLY;INTERVAL=3'
+                    WHEN 'Biannual' THEN 'FREQ=MONTHLY;INTERVAL=6'
+                    WHEN 'Yearly' THEN 'FREQ=YEARLY'
+                END
+                || CASE
+                    WHEN t.type IN ('Monthly', 'Quarterly', 'Biannual', 'Yearly')
+                        AND CAST(strftime('%d', reminders.date) AS INTEGER) > 28
+                    THEN
+                        CASE t.type
+                            WHEN 'Yearly'
+                            THEN ';BYMONTH=' || CAST(strftime('%m', reminders.date) AS INTEGER)
+                            ELSE ''
+                        END
+                        || ';BYMONTHDAY='
+                        || CASE CAST(strftime('%d', reminders.date) AS INTEGER)
+                            WHEN 29 THEN '28,29'
+                            WHEN 30 THEN '28,29,30'
+                            ELSE '28,29,30,31'
+                        END
+                        || ';BYSETPOS=-1'
+                    ELSE ''
+                END
+            FROM recurring_types t
+            WHERE t.id = reminders.recurring
+        );
+
+        DROP TRIGGER reminders_journal_insert;
+        CREATE TRIGGER reminders_journal_insert AFTER INSERT ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'date', NEW.date, 'description', NEW.description, 'recurring', NEW.recurring, 'rrule', NEW.rrule, 'exdates', NEW.exdates, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER reminders_journal_update;
+        CREATE TRIGGER reminders_journal_update BEFORE UPDATE ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'date', OLD.date, 'description', OLD.description, 'recurring', OLD.recurring, 'rrule', OLD.rrule, 'exdates', OLD.exdates, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'date', NEW.date, 'description', NEW.description, 'recurring', NEW.recurring, 'rrule', NEW.rrule, 'exdates', NEW.exdates, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER reminders_journal_delete;
+        CREATE TRIGGER reminders_journal_delete BEFORE DELETE ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'date', OLD.date, 'description', OLD.description, 'recurring', OLD.recurring, 'rrule', OLD.rrule, 'exdates', OLD.exdates, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+    ",
+    },
+    Migration {
+        version: 8,
+        name: "reminder_occurrences",
+        sql: "
+        -- Occurrences of reminders that were done, skipped or snoozed. Rows
+        -- go with their reminder when it is purged.
+        CREATE TABLE reminder_occurrences (
+            id INTEGER PRIMARY KEY,
+            reminder_id INTEGER NOT NULL
+                REFERENCES reminders (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+            date TEXT NOT NULL,
+            status TEXT NOT NULL CHECK (status IN ('done', 'skipped', 'snoozed')),
+            snoozed_until TEXT,
+            created_at TEXT,
+            UNIQUE (reminder_id, date)
+        );
+
+        CREATE TRIGGER reminder_occurrences_journal_insert AFTER INSERT ON reminder_occurrences
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminder_occurrences', NEW.id, NULL,
+                    json_object('id', NEW.id, 'reminder_id', NEW.reminder_id, 'date', NEW.date, 'status', NEW.status, 'snoozed_until', NEW.snoozed_until, 'created_at', NEW.created_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER reminder_occurrences_journal_update BEFORE UPDATE ON reminder_occurrences
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminder_occurrences', NEW.id,
+                    json_object('id', OLD.id, 'reminder_id', OLD.reminder_id, 'date', OLD.date, 'status', OLD.status, 'snoozed_until', OLD.snoozed_until, 'created_at', OLD.created_at),
+                    json_object('id', NEW.id, 'reminder_id', NEW.reminder_id, 'date', NEW.date, 'status', NEW.status, 'snoozed_until', NEW.snoozed_until, 'created_at', NEW.created_at)
+                FROM journal_state;
+        END;
+        CREATE TRIGGER reminder_occurrences_journal_delete BEFORE DELETE ON reminder_occurrences
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminder_occurrences', OLD.id,
+                    json_object('id', OLD.id, 'reminder_id', OLD.reminder_id, 'date', OLD.date, 'status', OLD.status, 'snoozed_until', OLD.snoozed_until, 'created_at', OLD.created_at),
+                    NULL
+                FROM journal_state;
+        END;
+    ",
+    },
+    Migration {
+        version: 9,
+        name: "timezones",
+        sql: "
+        -- Dates with a time of day are stored as RFC 3339 timestamps, at the
+        -- offset of the IANA timezone stored next to them. Dates without a
+        -- time stay full dates and have no timezone.
+        ALTER TABLE activities ADD COLUMN timezone TEXT;
+        ALTER TABLE reminders ADD COLUMN timezone TEXT;
+        ALTER TABLE notes ADD COLUMN timezone TEXT;
+
+        DROP TRIGGER activities_journal_insert;
+        CREATE TRIGGER activities_journal_insert AFTER INSERT ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'type', NEW.type, 'date', NEW.date, 'timezone', NEW.timezone, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER activities_journal_update;
+        CREATE TRIGGER activities_journal_update BEFORE UPDATE ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'type', OLD.type, 'date', OLD.date, 'timezone', OLD.timezone, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'type', NEW.type, 'date', NEW.date, 'timezone', NEW.timezone, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER activities_journal_delete;
+        CREATE TRIGGER activities_journal_delete BEFORE DELETE ON activities
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'activities', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'type', OLD.type, 'date', OLD.date, 'timezone', OLD.timezone, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        DROP TRIGGER reminders_journal_insert;
+        CREATE TRIGGER reminders_journal_insert AFTER INSERT ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', NEW.id, NULL,
+                    json_object('id', NEW.id, 'name', NEW.name, 'date', NEW.date, 'timezone', NEW.timezone, 'description', NEW.description, 'recurring', NEW.recurring, 'rrule', NEW.rrule, 'exdates', NEW.exdates, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER reminders_journal_update;
+        CREATE TRIGGER reminders_journal_update BEFORE UPDATE ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', NEW.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'date', OLD.date, 'timezone', OLD.timezone, 'description', OLD.description, 'recurring', OLD.recurring, 'rrule', OLD.rrule, 'exdates', OLD.exdates, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'name', NEW.name, 'date', NEW.date, 'timezone', NEW.timezone, 'description', NEW.description, 'recurring', NEW.recurring, 'rrule', NEW.rrule, 'exdates', NEW.exdates, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER reminders_journal_delete;
+        CREATE TRIGGER reminders_journal_delete BEFORE DELETE ON reminders
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'reminders', OLD.id,
+                    json_object('id', OLD.id, 'name', OLD.name, 'date', OLD.date, 'timezone', OLD.timezone, 'description', OLD.description, 'recurring', OLD.recurring, 'rrule', OLD.rrule, 'exdates', OLD.exdates, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+
+        DROP TRIGGER notes_journal_insert;
+        CREATE TRIGGER notes_journal_insert AFTER INSERT ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', NEW.id, NULL,
+                    json_object('id', NEW.id, 'date', NEW.date, 'timezone', NEW.timezone, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER notes_journal_update;
+        CREATE TRIGGER notes_journal_update BEFORE UPDATE ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', NEW.id,
+                    json_object('id', OLD.id, 'date', OLD.date, 'timezone', OLD.timezone, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    json_object('id', NEW.id, 'date', NEW.date, 'timezone', NEW.timezone, 'content', NEW.content, 'deleted', NEW.deleted, 'deleted_at', NEW.deleted_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                FROM journal_state;
+        END;
+        DROP TRIGGER notes_journal_delete;
+        CREATE TRIGGER notes_journal_delete BEFORE DELETE ON notes
+        WHEN (SELECT active FROM journal_state) IS NOT NULL BEGIN
+            INSERT INTO journal_changes (journal_id, table_name, row_id, before, after)
+                SELECT active, 'notes', OLD.id,
+                    json_object('id', OLD.id, 'date', OLD.date, 'timezone', OLD.timezone, 'content', OLD.content, 'deleted', OLD.deleted, 'deleted_at', OLD.deleted_at, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at),
+                    NULL
+                FROM journal_state;
+        END;
+    ",
+    },
+    Migration {
+        version: 10,
+        name: "notifications",
+        sql: "
+        -- Notifications already delivered by each backend, so that `notify`
+        -- fires every one once. They are not part of the journal.
+        CREATE TABLE notifications (
+            id INTEGER PRIMARY KEY,
+            kind TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            stage TEXT NOT NULL CHECK (stage IN ('upcoming', 'due')),
+            backend TEXT NOT NULL,
+            fired_at TEXT NOT NULL,
+            UNIQUE (kind, entity_id, date, stage, backend)
+        );
+    ",
+    },
+];
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, DbOperationsError> {
+    let mut stmt = match conn
+        .prepare("SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)")
+    {
+        Ok(stmt) => stmt,
+        Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+    };
+    match stmt.query_row(params![table], |row| row.get::<usize, bool>(0)) {
+        Ok(exists) => Ok(exists),
+        Err(e) => Err(DbOperationsError::RecordError {
+            sqlite_error: Some(e),
+            strum_error: None,
+        }),
+    }
+}
+
+fn create_version_table(conn: &Connection) -> Result<(), DbOperationsError> {
+    match conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        );",
+    ) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(DbOperationsError::MigrationError {
+            version: 0,
+            sqlite_error: e,
+        }),
+    }
+}
+
+fn record_version(conn: &Connection, migration: &Migration) -> Result<(), DbOperationsError> {
+    match conn.execute(
+        "INSERT INTO schema_version (version, name, applied_at)
+            VALUES (?1, ?2, datetime('now'))",
+        params![migration.version, migration.name],
+    ) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(DbOperationsError::MigrationError {
+            version: migration.version,
+            sqlite_error: e,
+        }),
+    }
+}
+
+pub fn current_version(conn: &Connection) -> Result<u32, DbOperationsError> {
+    if !table_exists(conn, "schema_version")? {
+        // Databases created by the old `init_db` have the initial schema
+        // but no version table.
+        if table_exists(conn, "people")? {
+            return Ok(1);
+        }
+        return Ok(0);
+    }
+    match conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    ) {
+        Ok(version) => Ok(version),
+        Err(e) => Err(DbOperationsError::RecordError {
+            sqlite_error: Some(e),
+            strum_error: None,
+        }),
+    }
+}
+
+pub fn pending(conn: &Connection) -> Result<Vec<&'static Migration>, DbOperationsError> {
+    let version = current_version(conn)?;
+    Ok(MIGRATIONS.iter().filter(|m| m.version > version).collect())
+}
+
+// Brings the database up to the latest schema version. All pending migrations
+// run inside a single transaction, so a failure leaves the database untouched.
+// Returns the versions that were applied.
+pub fn migrate(conn: &Connection) -> Result<Vec<u32>, DbOperationsError> {
+    let legacy = !table_exists(conn, "schema_version")? && table_exists(conn, "people")?;
+    let pending = pending(conn)?;
+    if pending.is_empty() && !legacy {
+        return Ok(vec![]);
+    }
+
+    let tx = match conn.unchecked_transaction() {
+        Ok(tx) => tx,
+        Err(e) => {
+            return Err(DbOperationsError::MigrationError {
+                version: 0,
+                sqlite_error: e,
+            })
+        }
+    };
+
+    create_version_table(&tx)?;
+    if legacy {
+        record_version(&tx, &MIGRATIONS[0])?;
+    }
+
+    let mut applied = vec![];
+    for migration in pending {
+        if let Err(e) = tx.execute_batch(migration.sql) {
+            return Err(DbOperationsError::MigrationError {
+                version: migration.version,
+                sqlite_error: e,
+            });
+        }
+        record_version(&tx, migration)?;
+        applied.push(migration.version);
+    }
+
+    match tx.commit() {
+        Ok(_) => Ok(applied),
+        Err(e) => Err(DbOperationsError::MigrationError {
+            version: latest_version(),
+            sqlite_error: e,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_fresh_database() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        let applied = migrate(&conn).unwrap();
+
+        assert_eq!(
+            MIGRATIONS.iter().map(|m| m.version).collect::<Vec<u32>>(),
+            applied
+        );
+        assert_eq!(latest_version(), current_version(&conn).unwrap());
+        assert!(migrate(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_legacy_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        conn.execute(
+            "INSERT INTO people (name, birthday, deleted) VALUES ('Zeh', '2000-01-01', FALSE)",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(1, current_version(&conn).unwrap());
+        migrate(&conn).unwrap();
+
+        assert_eq!(latest_version(), current_version(&conn).unwrap());
+        let name: String = conn
+            .query_row("SELECT name FROM people", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!("Zeh", name);
+    }
+
+    #[test]
+    fn test_migrate_enforces_integrity() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", true).unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        conn.execute_batch(
+            "INSERT INTO people (name, birthday, deleted) VALUES ('Zeh', NULL, FALSE);
+            INSERT INTO notes (date, content, deleted) VALUES ('2024-01-02', 'Lisbon', FALSE);
+            INSERT INTO people_notes (person_id, note_id, deleted) VALUES (1, 1, TRUE);
+            INSERT INTO people_notes (person_id, note_id, deleted) VALUES (1, 1, FALSE);
+            INSERT INTO people_notes (person_id, note_id, deleted) VALUES (1, 7, FALSE);
+            INSERT INTO contact_info (person_id, contact_info_type_id, contact_info_details, deleted)
+                VALUES (9, 3, 'nobody@example.com', FALSE);",
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+
+        let links: Vec<(u64, bool)> = conn
+            .prepare("SELECT id, deleted FROM people_notes")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(vec![(2, false)], links);
+        let contact_info: u64 = conn
+            .query_row("SELECT COUNT(*) FROM contact_info", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, contact_info);
+
+        assert!(conn
+            .execute(
+                "INSERT INTO people_notes (person_id, note_id, deleted) VALUES (1, 1, FALSE)",
+                [],
+            )
+            .is_err());
+        assert!(conn
+            .execute(
+                "INSERT INTO people_notes (person_id, note_id, deleted) VALUES (1, 7, FALSE)",
+                [],
+            )
+            .is_err());
+        // Soft deletes still cascade to the recreated tables
+        conn.execute("UPDATE people SET deleted = TRUE WHERE id = 1", [])
+            .unwrap();
+        let deleted: bool = conn
+            .query_row("SELECT deleted FROM people_notes WHERE id = 2", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(deleted);
+    }
+
+    #[test]
+    fn test_migrate_recurring_types() {
+        use crate::entities::reminder::RecurringType;
+        use std::str::FromStr;
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        for date in ["2024-01-15", "2024-01-30", "2024-02-29"] {
+            for recurring in 1..=8 {
+                conn.execute(
+                    "INSERT INTO reminders (name, date, description, recurring, deleted)
+                        VALUES ('Call', ?1, NULL, ?2, FALSE)",
+                    params![date, recurring],
+                )
+                .unwrap();
+            }
+        }
+
+        migrate(&conn).unwrap();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT r.date, t.type, r.rrule
+                FROM reminders r JOIN recurring_types t ON t.id = r.recurring",
+            )
+            .unwrap();
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<(String, String, Option<String>)>>();
+        assert_eq!(24, rows.len());
+        for (date, recurring, rrule) in rows {
+            let date = crate::helpers::parse_from_str_ymd(&date).unwrap();
+            let rule = RecurringType::from_str(&recurring).unwrap().rule(date);
+            assert_eq!(rule.map(|rule| rule.to_string()), rrule);
+        }
+    }
+}
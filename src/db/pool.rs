@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rusqlite::Connection;
+
+// How long a connection waits for another one to finish writing before
+// giving up with `SQLITE_BUSY`
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Opens the SQLite connections of an r2d2 pool, configured the way prm
+// expects: foreign keys on and writers waiting for each other.
+#[derive(Debug, Clone)]
+pub struct ConnectionManager {
+    // `None` opens an in-memory database, which only lives as long as its
+    // connection, so the pool must keep a single connection open.
+    path: Option<PathBuf>,
+}
+
+impl ConnectionManager {
+    pub fn file(path: impl Into<PathBuf>) -> ConnectionManager {
+        ConnectionManager {
+            path: Some(path.into()),
+        }
+    }
+
+    pub fn memory() -> ConnectionManager {
+        ConnectionManager { path: None }
+    }
+}
+
+impl r2d2::ManageConnection for ConnectionManager {
+    type Connection = Connection;
+    type Error = rusqlite::Error;
+
+    fn connect(&self) -> Result<Connection, rusqlite::Error> {
+        let conn = match &self.path {
+            Some(path) => Connection::open(path)?,
+            None => Connection::open_in_memory()?,
+        };
+        // Foreign keys are off by default and can only be enabled per connection
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(conn)
+    }
+
+    fn is_valid(&self, conn: &mut Connection) -> Result<(), rusqlite::Error> {
+        conn.execute_batch("SELECT 1")
+    }
+
+    fn has_broken(&self, _: &mut Connection) -> bool {
+        false
+    }
+}
@@ -0,0 +1,94 @@
+use rusqlite::Connection;
+
+use crate::db_interface::DbOperationsError;
+
+// Runs `write` inside a savepoint, rolling back everything it did if it
+// fails. Savepoints nest, so `write` may itself call other atomic writes,
+// and callers can group several writes by passing a `Transaction` (or
+// wrapping them in `atomic` too) as the connection.
+//
+// Outside of a transaction the write lock is taken up front, so concurrent
+// writers on other connections wait for each other instead of failing when
+// they try to upgrade a read lock.
+pub fn atomic<T>(
+    conn: &Connection,
+    write: impl FnOnce() -> Result<T, DbOperationsError>,
+) -> Result<T, DbOperationsError> {
+    let (begin, commit, rollback) = if conn.is_autocommit() {
+        ("BEGIN IMMEDIATE", "COMMIT", "ROLLBACK")
+    } else {
+        (
+            "SAVEPOINT prm_write",
+            "RELEASE prm_write",
+            // `ROLLBACK TO` leaves the savepoint open, so it still has to be
+            // released
+            "ROLLBACK TO prm_write; RELEASE prm_write",
+        )
+    };
+    if let Err(e) = conn.execute_batch(begin) {
+        return Err(DbOperationsError::InvalidStatement { sqlite_error: e });
+    }
+    match write() {
+        Ok(value) => match conn.execute_batch(commit) {
+            Ok(_) => Ok(value),
+            Err(e) => {
+                let _ = conn.execute_batch(rollback);
+                Err(DbOperationsError::InvalidStatement { sqlite_error: e })
+            }
+        },
+        Err(e) => {
+            // The original error is more useful than a failure to roll back,
+            // which SQLite only reports if the connection is gone
+            let _ = conn.execute_batch(rollback);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_interface::DbOperations;
+    use crate::entities::note::Note;
+    use chrono::NaiveDate;
+
+    fn count(conn: &Connection, table: &str) -> u64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_atomic() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        let note = Note::new(
+            0,
+            NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            String::from("Lisbon"),
+            vec![],
+        );
+
+        let failed: Result<(), DbOperationsError> = atomic(&conn, || {
+            note.add(&conn)?;
+            note.add(&conn)?;
+            Err(DbOperationsError::MissingRow {
+                table: String::from("notes"),
+            })
+        });
+        assert!(failed.is_err());
+        assert_eq!(0, count(&conn, "notes"));
+        assert_eq!(0, count(&conn, "journal"));
+
+        // Writes nest inside a caller's transaction
+        let tx = conn.unchecked_transaction().unwrap();
+        atomic(&tx, || note.add(&tx).map(|_| ())).unwrap();
+        note.add(&tx).unwrap();
+        tx.rollback().unwrap();
+        assert_eq!(0, count(&conn, "notes"));
+
+        atomic(&conn, || note.add(&conn).map(|_| ())).unwrap();
+        assert_eq!(1, count(&conn, "notes"));
+    }
+}
@@ -0,0 +1,224 @@
+use chrono::{Datelike, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::db::entity_kind::EntityKind;
+use crate::db_interface::DbOperationsError;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrashItem {
+    pub entity: EntityKind,
+    pub id: u64,
+    pub summary: String,
+    pub deleted_at: Option<String>,
+}
+
+fn record_error(e: rusqlite::Error) -> DbOperationsError {
+    DbOperationsError::RecordError {
+        sqlite_error: Some(e),
+        strum_error: None,
+    }
+}
+
+// Lists soft-deleted rows, most recently deleted first
+pub fn list(conn: &Connection) -> Result<Vec<TrashItem>, DbOperationsError> {
+    let mut items = vec![];
+    for entity in EntityKind::ALL {
+        let mut stmt = match conn.prepare(&format!(
+            "SELECT id, {}, deleted_at FROM {} WHERE deleted = 1",
+            entity.summary_sql(),
+            entity.table()
+        )) {
+            Ok(stmt) => stmt,
+            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+        };
+        let rows = match stmt.query_map([], |row| {
+            Ok(TrashItem {
+                entity,
+                id: row.get(0)?,
+                summary: row.get::<usize, Option<String>>(1)?.unwrap_or_default(),
+                deleted_at: row.get(2)?,
+            })
+        }) {
+            Ok(rows) => rows,
+            Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+        };
+        for row in rows {
+            items.push(row.map_err(record_error)?);
+        }
+    }
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(items)
+}
+
+// Undeletes a trashed row and the join rows that were deleted with it.
+// Returns false if the row is not in the trash.
+pub fn restore(conn: &Connection, entity: EntityKind, id: u64) -> Result<bool, DbOperationsError> {
+    let deleted_at: Option<String> = match conn.query_row(
+        &format!(
+            "SELECT deleted_at FROM {} WHERE id = ?1 AND deleted = 1",
+            entity.table()
+        ),
+        params![id],
+        |row| row.get(0),
+    ) {
+        Ok(deleted_at) => deleted_at,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+        Err(e) => return Err(record_error(e)),
+    };
+
+    let tx = match conn.unchecked_transaction() {
+        Ok(tx) => tx,
+        Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+    };
+    if let Some(deleted_at) = deleted_at {
+        for (table, column) in entity.links() {
+            if let Err(e) = tx.execute(
+                &format!(
+                    "UPDATE {} SET deleted = 0 WHERE {} = ?1 AND deleted = 1 AND deleted_at = ?2",
+                    table, column
+                ),
+                params![id, deleted_at],
+            ) {
+                return Err(DbOperationsError::InvalidStatement { sqlite_error: e });
+            }
+        }
+    }
+    if let Err(e) = tx.execute(
+        &format!("UPDATE {} SET deleted = 0 WHERE id = ?1", entity.table()),
+        params![id],
+    ) {
+        return Err(DbOperationsError::InvalidStatement { sqlite_error: e });
+    }
+    match tx.commit() {
+        Ok(_) => Ok(true),
+        Err(e) => Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+    }
+}
+
+// The deletion time a row needs to be at or before to be `days` old, in
+// the format of `deleted_at`. Ages before year 0 can't be compared as text.
+fn cutoff(days: u64) -> Result<String, DbOperationsError> {
+    i64::try_from(days)
+        .ok()
+        .and_then(Duration::try_days)
+        .and_then(|age| Utc::now().naive_utc().checked_sub_signed(age))
+        .filter(|cutoff| cutoff.year() >= 0)
+        .map(|cutoff| cutoff.format("%Y-%m-%d %H:%M:%S").to_string())
+        .ok_or(DbOperationsError::AgeOutOfRange { days })
+}
+
+// Permanently deletes trashed rows, and every join row pointing at them.
+// With `older_than_days`, only rows deleted at least that many days ago are
+// purged; rows without a deletion time are only purged without it.
+// Returns the number of entities purged.
+pub fn purge(conn: &Connection, older_than_days: Option<u64>) -> Result<usize, DbOperationsError> {
+    let cutoff = cutoff(older_than_days.unwrap_or(0))?;
+    let any_age = older_than_days.is_none();
+    let tx = match conn.unchecked_transaction() {
+        Ok(tx) => tx,
+        Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+    };
+
+    let mut purged = 0;
+    for entity in EntityKind::ALL {
+        let condition = format!(
+            "SELECT id FROM {} WHERE deleted = 1
+                AND (deleted_at <= ?1 OR (deleted_at IS NULL AND ?2))",
+            entity.table()
+        );
+        for (table, column) in entity.links() {
+            if let Err(e) = tx.execute(
+                &format!("DELETE FROM {} WHERE {} IN ({})", table, column, condition),
+                params![cutoff, any_age],
+            ) {
+                return Err(DbOperationsError::InvalidStatement { sqlite_error: e });
+            }
+        }
+        match tx.execute(
+            &format!("DELETE FROM {} WHERE id IN ({})", entity.table(), condition),
+            params![cutoff, any_age],
+        ) {
+            Ok(deleted) => purged += deleted,
+            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+        }
+    }
+    // Unlinked join rows
+    for table in ["people_activities", "people_reminders", "people_notes"] {
+        if let Err(e) = tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE deleted = 1
+                    AND (deleted_at <= ?1 OR (deleted_at IS NULL AND ?2))",
+                table
+            ),
+            params![cutoff, any_age],
+        ) {
+            return Err(DbOperationsError::InvalidStatement { sqlite_error: e });
+        }
+    }
+
+    match tx.commit() {
+        Ok(_) => Ok(purged),
+        Err(e) => Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_and_purge() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO people (name, birthday, deleted) VALUES ('Zeh', NULL, FALSE);
+            INSERT INTO notes (date, content, deleted) VALUES ('2024-01-02', 'Lisbon', FALSE);
+            INSERT INTO people_notes (person_id, note_id, deleted) VALUES (1, 1, FALSE);
+            INSERT INTO contact_info (person_id, contact_info_type_id, contact_info_details, deleted)
+                VALUES (1, 3, 'zeh@example.com', FALSE);
+            UPDATE people SET deleted = TRUE WHERE id = 1;",
+        )
+        .unwrap();
+
+        let items = list(&conn).unwrap();
+        assert_eq!(
+            vec![EntityKind::Person, EntityKind::ContactInfo],
+            items.iter().map(|i| i.entity).collect::<Vec<EntityKind>>()
+        );
+        assert!(items.iter().all(|i| i.deleted_at.is_some()));
+
+        assert!(restore(&conn, EntityKind::Person, 1).unwrap());
+        assert!(!restore(&conn, EntityKind::Person, 1).unwrap());
+        assert!(list(&conn).unwrap().is_empty());
+        let links: u64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM people_notes WHERE deleted = 0",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(1, links);
+
+        conn.execute("UPDATE notes SET deleted = TRUE WHERE id = 1", [])
+            .unwrap();
+        assert_eq!(0, purge(&conn, Some(90)).unwrap());
+        assert!(matches!(
+            purge(&conn, Some(10_000 * 365)),
+            Err(DbOperationsError::AgeOutOfRange { days: 3_650_000 })
+        ));
+        assert!(matches!(
+            purge(&conn, Some(u64::MAX)),
+            Err(DbOperationsError::AgeOutOfRange { .. })
+        ));
+        // Without a deletion time, a row is only old enough without an age
+        conn.execute("UPDATE notes SET deleted_at = NULL WHERE id = 1", [])
+            .unwrap();
+        assert_eq!(0, purge(&conn, Some(0)).unwrap());
+        assert_eq!(1, purge(&conn, None).unwrap());
+        let links: u64 = conn
+            .query_row("SELECT COUNT(*) FROM people_notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, links);
+    }
+}
@@ -1,8 +1,12 @@
 pub mod activity;
+pub mod datetime;
 pub mod event;
 pub mod note;
+pub mod occurrence;
 pub mod person;
+pub mod recurrence;
 pub mod reminder;
+pub mod shallow;
 
 use crate::entities::activity::Activity;
 use crate::entities::note::Note;
@@ -1,13 +1,21 @@
 use chrono::prelude::*;
+use chrono_tz::Tz;
 use rusqlite::params;
+use serde::Serialize;
 use std::{convert::AsRef, fmt, str::FromStr};
 use strum_macros::{AsRefStr, EnumString};
 
+use crate::db::entity_kind::EntityKind;
+use crate::db::journal::{self, Operation};
+use crate::db::loader::{self, LoadContext, Relations};
+use crate::db::transaction::atomic;
 use crate::db_interface::{DbOperations, DbOperationsError};
+use crate::entities::datetime::Moment;
 use crate::entities::person::Person;
 use crate::entities::Entities;
-use crate::{CliError, DateParseSnafu, EntitySnafu, NotFoundSnafu, RecordParseSnafu};
+use crate::{CliError, DatabaseSnafu, NotFoundSnafu, RecordParseSnafu};
 use rusqlite::Connection;
+use snafu::ResultExt;
 
 use super::Entity;
 
@@ -17,14 +25,18 @@ Activity Type: {activity_type}
 Content: {content}
 People: {people}
 ";
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Activity {
     pub id: u64,
     pub name: String,
     pub activity_type: ActivityType,
     pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub timezone: Option<Tz>,
     pub content: String,
     pub people: Vec<Person>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
 }
 pub struct ParseActivityFromEditorData {
     pub name: String,
@@ -54,82 +66,28 @@ impl Activity {
             name,
             activity_type,
             date,
+            time: None,
+            timezone: None,
             content,
             people,
+            created_at: None,
+            updated_at: None,
         }
     }
 
-    pub fn build_from_sql(
-        conn: &Connection,
-        id: Result<u64, rusqlite::Error>,
-        name: Result<String, rusqlite::Error>,
-        activity_type: Result<u64, rusqlite::Error>,
-        date: Result<String, rusqlite::Error>,
-        content: Result<String, rusqlite::Error>,
-    ) -> Result<Activity, DbOperationsError> {
-        let id = match id {
-            Ok(activity_id) => activity_id,
-            Err(e) => {
-                return Err(DbOperationsError::RecordError {
-                    sqlite_error: Some(e),
-                    strum_error: None,
-                })
-            }
-        };
-        let name: String = match name {
-            Ok(name) => name,
-            Err(e) => {
-                return Err(DbOperationsError::RecordError {
-                    sqlite_error: Some(e),
-                    strum_error: None,
-                })
-            }
-        };
-        let activity_type_id: u64 = match activity_type {
-            Ok(activity_type_id) => activity_type_id,
-            Err(e) => {
-                return Err(DbOperationsError::RecordError {
-                    sqlite_error: Some(e),
-                    strum_error: None,
-                })
-            }
-        };
-        let content: String = match content {
-            Ok(content) => content,
-            Err(e) => {
-                return Err(DbOperationsError::RecordError {
-                    sqlite_error: Some(e),
-                    strum_error: None,
-                })
-            }
-        };
-        let activity_type = match ActivityType::get_by_id(conn, activity_type_id) {
-            Ok(activity_type) => match activity_type {
-                Some(activity_type) => activity_type,
-                None => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: None,
-                        strum_error: None,
-                    })
-                }
-            },
-            Err(e) => return Err(e),
-        };
-        let date = crate::helpers::parse_from_str_ymd(date.unwrap_or_default().as_str())
-            .unwrap_or_default();
-        let people = crate::db::db_helpers::people::get_by_activity(conn, id, true)?;
-        Ok(Activity {
-            id,
-            name,
-            activity_type,
-            date,
-            content,
-            people,
-        })
+    pub fn moment(&self) -> Moment {
+        Moment::new(self.date, self.time, self.timezone)
+    }
+
+    pub fn set_moment(&mut self, moment: Moment) {
+        self.date = moment.date;
+        self.time = moment.time;
+        self.timezone = moment.timezone;
     }
 
     pub fn get(
         conn: &Connection,
+        load: &mut LoadContext,
         name: Option<String>,
         person: Option<String>,
         content: Option<String>,
@@ -138,21 +96,21 @@ impl Activity {
         let mut activities: Vec<Activity> = vec![];
         if let Some(name) = name {
             activities =
-                crate::db::db_helpers::activities::get_by_name(conn, name, person.clone())?;
+                crate::db::db_helpers::activities::get_by_name(conn, load, name, person.clone())?;
             return Ok(activities);
         }
         if let Some(person) = person {
             activities =
-                crate::db::db_helpers::activities::get_by_person_name(conn, person.clone())?;
+                crate::db::db_helpers::activities::get_by_person_name(conn, load, person.clone())?;
             return Ok(activities);
         }
         if let Some(content) = content {
-            activities = crate::db::db_helpers::activities::get_by_content(conn, content)?;
+            activities = crate::db::db_helpers::activities::get_by_content(conn, load, content)?;
             return Ok(activities);
         }
         if let Some(activity_type) = activity_type {
             activities =
-                crate::db::db_helpers::activities::get_by_activity_type(conn, activity_type)?;
+                crate::db::db_helpers::activities::get_by_activity_type(conn, load, activity_type)?;
             return Ok(activities);
         }
         Ok(activities)
@@ -163,7 +121,7 @@ impl Activity {
         conn: &Connection,
         name: Option<String>,
         activity_type: Option<String>,
-        date: Option<String>,
+        date: Option<Moment>,
         content: Option<String>,
         people: Vec<String>,
     ) -> Result<&Self, CliError> {
@@ -176,40 +134,16 @@ impl Activity {
             self.activity_type = activity_type;
         }
 
-        if let Some(date) = date {
-            let date_obj: Option<NaiveDate>;
-            match crate::helpers::parse_from_str_ymd(&date) {
-                Ok(date) => date_obj = Some(date),
-                Err(_) => match crate::helpers::parse_from_str_md(&date) {
-                    Ok(date) => date_obj = Some(date),
-                    Err(_) => {
-                        return {
-                            DateParseSnafu {
-                                date: date.to_string(),
-                            }
-                            .fail()
-                        }
-                    }
-                },
-            }
-            self.date = match date_obj {
-                Some(date) => date,
-                None => {
-                    return {
-                        DateParseSnafu {
-                            date: date.to_string(),
-                        }
-                        .fail()
-                    }
-                }
-            };
+        if let Some(moment) = date {
+            self.set_moment(moment);
         }
 
         if let Some(content) = content {
             self.content = content;
         }
 
-        let people = crate::db::db_helpers::people::get_by_names(conn, people);
+        let people =
+            crate::db::db_helpers::people::get_by_names(conn, &mut LoadContext::new(true), people);
         self.people = match people {
             Ok(people) => people,
             Err(_) => {
@@ -272,27 +206,28 @@ impl Activity {
         })
     }
 
-    pub fn get_by_ids(conn: &Connection, ids: Vec<u64>) -> Result<Vec<Activity>, CliError> {
+    pub fn get_by_ids(
+        conn: &Connection,
+        load: &mut LoadContext,
+        ids: Vec<u64>,
+    ) -> Result<Vec<Activity>, CliError> {
         let mut activities: Vec<Activity> = vec![];
         for id in ids {
-            match Activity::get_by_id(conn, id) {
-                Ok(entity) => match entity {
-                    Some(Entities::Activity(activity)) => activities.push(activity),
-                    _ => {
-                        return EntitySnafu {
-                            entity: "Activity",
-                            message: format!("Error fecthing activity: {:#?}", entity),
-                        }
-                        .fail()
-                    }
-                },
-                Err(_) => {
+            match Activity::get_by_id(conn, load, id) {
+                Ok(Some(Entities::Activity(activity))) => activities.push(activity),
+                Ok(_) => {
                     return NotFoundSnafu {
                         entity: "Activity",
                         id,
                     }
                     .fail()
                 }
+                Err(e) => {
+                    return Err(e).context(DatabaseSnafu {
+                        entity: "Activity",
+                        action: "fetching",
+                    })
+                }
             }
         }
 
@@ -302,7 +237,7 @@ impl Activity {
 
 impl fmt::Display for Activity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let date = &self.date.to_string();
+        let date = &self.moment().to_string();
         let mut people_str = String::new();
         let people = self
             .people
@@ -321,211 +256,160 @@ impl fmt::Display for Activity {
 
 impl DbOperations for Activity {
     fn add(&self, conn: &Connection) -> Result<&Activity, DbOperationsError> {
-        let activity_type_str = self.activity_type.as_ref();
-        let date_str = self.date.to_string();
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Add, EntityKind::Activity, self.id)?;
+            let activity_type_str = self.activity_type.as_ref();
+            let date_str = self.moment().to_rfc3339();
 
-        let mut stmt = match conn.prepare(
-            "
-            SELECT
-                id
-            FROM
-                activity_types
-            WHERE
-                type = ?",
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => return Err(DbOperationsError::GenericError),
-        };
-        let mut rows = match stmt.query(params![activity_type_str]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        let mut types: Vec<u32> = Vec::new();
-        loop {
-            match rows.next() {
-                Ok(row) => match row {
-                    Some(row) => match row.get(0) {
-                        Ok(row) => types.push(row),
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
+            let mut stmt = match conn.prepare(
+                "
+                SELECT
+                    id
+                FROM
+                    activity_types
+                WHERE
+                    type = ?",
+            ) {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+            };
+            let mut rows = match stmt.query(params![activity_type_str]) {
+                Ok(rows) => rows,
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+            };
+            let mut types: Vec<u32> = Vec::new();
+            loop {
+                match rows.next() {
+                    Ok(row) => match row {
+                        Some(row) => match row.get(0) {
+                            Ok(row) => types.push(row),
+                            Err(e) => {
+                                return Err(DbOperationsError::RecordError {
+                                    sqlite_error: Some(e),
+                                    strum_error: None,
+                                })
+                            }
+                        },
+                        None => break,
                     },
-                    None => break,
-                },
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
+                    Err(e) => {
+                        return Err(DbOperationsError::RecordError {
+                            sqlite_error: Some(e),
+                            strum_error: None,
+                        })
+                    }
                 }
             }
-        }
 
-        let mut stmt = match conn.prepare(
-            "INSERT INTO
-                activities (name, type, date, content, deleted)
-                VALUES (?1, ?2, ?3, ?4, FALSE)
-            ",
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => return Err(DbOperationsError::GenericError),
-        };
-
-        match stmt.execute(params![self.name, types[0], date_str, self.content]) {
-            Ok(updated) => {
-                println!("[DEBUG][activities][insert] {} rows were updated", updated);
-            }
-            Err(_) => return Err(DbOperationsError::GenericError),
-        }
-
-        let id = conn.last_insert_rowid();
-
-        for person in &self.people {
             let mut stmt = match conn.prepare(
-                "INSERT INTO people_activities (
-                    person_id,
-                    activity_id,
-                    deleted
-                )
-                    VALUES (?1, ?2, FALSE)",
+                "INSERT INTO
+                    activities (
+                        name, type, date, timezone, content, deleted, created_at, updated_at
+                    )
+                    VALUES (?1, ?2, ?3, ?4, ?5, FALSE, datetime('now'), datetime('now'))
+                ",
             ) {
                 Ok(stmt) => stmt,
-                Err(_) => return Err(DbOperationsError::GenericError),
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
-            match stmt.execute(params![person.id, id]) {
+
+            match stmt.execute(params![
+                self.name,
+                types[0],
+                date_str,
+                self.timezone.map(|timezone| timezone.name()),
+                self.content
+            ]) {
                 Ok(updated) => {
-                    println!(
-                        "[DEBUG][people_activities][insert] {} rows were updated",
-                        updated
-                    );
+                    println!("[DEBUG][activities][insert] {} rows were updated", updated);
                 }
-                Err(_) => return Err(DbOperationsError::GenericError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
-        }
 
-        Ok(self)
-    }
+            let id = conn.last_insert_rowid();
 
-    fn remove(&self, conn: &Connection) -> Result<&Self, DbOperationsError> {
-        let mut stmt = match conn.prepare(
-            "UPDATE
-                    activities
-                SET
-                    deleted = TRUE
-                WHERE
-                    id = ?1",
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => return Err(DbOperationsError::GenericError),
-        };
-        match stmt.execute([self.id]) {
-            Ok(updated) => {
-                println!("[DEBUG][activities][update] {} rows were updated", updated);
+            for person in &self.people {
+                let mut stmt = match conn.prepare(
+                    "INSERT INTO people_activities (
+                        person_id,
+                        activity_id,
+                        deleted
+                    )
+                        VALUES (?1, ?2, FALSE)
+                        ON CONFLICT (person_id, activity_id) DO UPDATE SET deleted = FALSE",
+                ) {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
+                match stmt.execute(params![person.id, id]) {
+                    Ok(updated) => {
+                        println!(
+                            "[DEBUG][people_activities][insert] {} rows were updated",
+                            updated
+                        );
+                    }
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+                }
             }
-            Err(_) => return Err(DbOperationsError::GenericError),
-        }
 
-        Ok(self)
+            journal.finish()?;
+            Ok(self)
+        })
     }
 
-    fn save(&self, conn: &Connection) -> Result<&Activity, DbOperationsError> {
-        let activity_type_str = self.activity_type.as_ref();
-
-        let mut stmt = match conn.prepare(
-            "
-            SELECT
-                id
-            FROM
-                activity_types
-            WHERE
-                type = ?",
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => return Err(DbOperationsError::GenericError),
-        };
-        let mut rows = match stmt.query(params![activity_type_str]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        let mut types: Vec<u32> = Vec::new();
-        loop {
-            match rows.next() {
-                Ok(row) => match row {
-                    Some(row) => match row.get(0) {
-                        Ok(row) => types.push(row),
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    },
-                    None => break,
-                },
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
+    fn remove(&self, conn: &Connection) -> Result<&Self, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Remove, EntityKind::Activity, self.id)?;
+            let mut stmt = match conn.prepare(
+                "UPDATE
+                        activities
+                    SET
+                        deleted = TRUE
+                    WHERE
+                        id = ?1",
+            ) {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+            };
+            match stmt.execute([self.id]) {
+                Ok(updated) => {
+                    println!("[DEBUG][activities][update] {} rows were updated", updated);
                 }
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
-        }
 
-        let mut stmt = match conn.prepare(
-            "UPDATE
-                activities
-            SET
-                name = ?1,
-                type = ?2,
-                date = ?3,
-                content = ?4
-            WHERE
-                id = ?5",
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => return Err(DbOperationsError::GenericError),
-        };
-        match stmt.execute(params![
-            self.name,
-            types[0],
-            self.date.to_string(),
-            self.content,
-            self.id,
-        ]) {
-            Ok(updated) => {
-                println!("[DEBUG][activities][update] {} rows were updated", updated);
-            }
-            Err(_) => return Err(DbOperationsError::GenericError),
-        }
+            journal.finish()?;
+            Ok(self)
+        })
+    }
+
+    fn save(&self, conn: &Connection) -> Result<&Activity, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Save, EntityKind::Activity, self.id)?;
+            let activity_type_str = self.activity_type.as_ref();
 
-        for person in self.people.iter() {
             let mut stmt = match conn.prepare(
-                "SELECT
-                        id
-                    FROM
-                        people_activities
-                    WHERE
-                        activity_id = ?1
-                        AND person_id = ?2
-                    AND
-                        deleted = 0",
+                "
+                SELECT
+                    id
+                FROM
+                    activity_types
+                WHERE
+                    type = ?",
             ) {
                 Ok(stmt) => stmt,
-                Err(_) => return Err(DbOperationsError::GenericError),
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
-            let mut rows = match stmt.query(params![self.id, person.id]) {
+            let mut rows = match stmt.query(params![activity_type_str]) {
                 Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             };
-            let mut results: Vec<u32> = Vec::new();
+            let mut types: Vec<u32> = Vec::new();
             loop {
                 match rows.next() {
                     Ok(row) => match row {
                         Some(row) => match row.get(0) {
-                            Ok(row) => results.push(row),
+                            Ok(row) => types.push(row),
                             Err(e) => {
                                 return Err(DbOperationsError::RecordError {
                                     sqlite_error: Some(e),
@@ -544,208 +428,170 @@ impl DbOperations for Activity {
                 }
             }
 
-            if !results.is_empty() {
-                for id in results {
-                    let mut stmt = match conn.prepare(
-                        "
-                            UPDATE
-                                people_activities
-                            SET
-                                deleted = TRUE
-                            WHERE
-                                id = ?1",
-                    ) {
-                        Ok(stmt) => stmt,
-                        Err(_) => return Err(DbOperationsError::GenericError),
-                    };
-                    match stmt.execute(params![id]) {
-                        Ok(updated) => {
-                            println!(
-                                "[DEBUG][people_activities][update] {} rows were updated",
-                                updated
-                            );
-                        }
-                        Err(_) => return Err(DbOperationsError::GenericError),
-                    }
-                }
-            }
-
             let mut stmt = match conn.prepare(
-                "INSERT INTO people_activities (
-                        person_id,
-                        activity_id,
-                        deleted
-                    ) VALUES (?1, ?2, FALSE)",
+                "UPDATE
+                    activities
+                SET
+                    name = ?1,
+                    type = ?2,
+                    date = ?3,
+                    timezone = ?4,
+                    content = ?5,
+                    updated_at = datetime('now')
+                WHERE
+                    id = ?6",
             ) {
                 Ok(stmt) => stmt,
-                Err(_) => return Err(DbOperationsError::GenericError),
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
-            match stmt.execute(params![person.id, self.id]) {
+            match stmt.execute(params![
+                self.name,
+                types[0],
+                self.moment().to_rfc3339(),
+                self.timezone.map(|timezone| timezone.name()),
+                self.content,
+                self.id,
+            ]) {
                 Ok(updated) => {
-                    println!(
-                        "[DEBUG][people_activities][insert] {} rows were updated",
-                        updated
-                    );
+                    println!("[DEBUG][activities][update] {} rows were updated", updated);
                 }
-                Err(_) => return Err(DbOperationsError::GenericError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
-        }
 
-        Ok(self)
-    }
-
-    fn get_by_id(conn: &Connection, id: u64) -> Result<Option<Entities>, DbOperationsError> {
-        let mut stmt = match conn.prepare(
-            "
-            SELECT
-                *
-            FROM
-                activities
-            WHERE
-                id = ?1 AND
-            deleted = 0",
-        ) {
-            Ok(stmt) => stmt,
-            Err(_) => return Err(DbOperationsError::GenericError),
-        };
-        let mut rows = match stmt.query(params![id]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        match rows.next() {
-            Ok(row) => match row {
-                Some(row) => {
-                    let activity_id = match row.get(0) {
-                        Ok(row) => row,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let name: String = match row.get(1) {
-                        Ok(row) => row,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let activity_type_id = match row.get(2) {
-                        Ok(row) => row,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let content: String = match row.get(4) {
-                        Ok(row) => row,
+            for person in self.people.iter() {
+                let mut stmt = match conn.prepare(
+                    "SELECT
+                            id
+                        FROM
+                            people_activities
+                        WHERE
+                            activity_id = ?1
+                            AND person_id = ?2
+                        AND
+                            deleted = 0",
+                ) {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
+                let mut rows = match stmt.query(params![self.id, person.id]) {
+                    Ok(rows) => rows,
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+                };
+                let mut results: Vec<u32> = Vec::new();
+                loop {
+                    match rows.next() {
+                        Ok(row) => match row {
+                            Some(row) => match row.get(0) {
+                                Ok(row) => results.push(row),
+                                Err(e) => {
+                                    return Err(DbOperationsError::RecordError {
+                                        sqlite_error: Some(e),
+                                        strum_error: None,
+                                    })
+                                }
+                            },
+                            None => break,
+                        },
                         Err(e) => {
                             return Err(DbOperationsError::RecordError {
                                 sqlite_error: Some(e),
                                 strum_error: None,
                             })
                         }
-                    };
-                    let people =
-                        crate::db::db_helpers::people::get_by_activity(conn, activity_id, true)?;
-                    let activity_type = match ActivityType::get_by_id(conn, activity_type_id) {
-                        Ok(activity_type) => match activity_type {
-                            Some(activity_type) => activity_type,
-                            None => panic!("Activity type cannot be None"),
-                        },
-                        Err(e) => return Err(e),
-                    };
-                    Ok(Some(Entities::Activity(Activity {
-                        id: activity_id,
-                        name,
-                        activity_type,
-                        date: crate::helpers::parse_from_str_ymd(
-                            row.get::<usize, String>(3).unwrap_or_default().as_str(),
-                        )
-                        .unwrap_or_default(),
-                        content,
-                        people,
-                    })))
+                    }
                 }
-                None => Ok(None),
-            },
-            Err(_) => Err(DbOperationsError::GenericError),
-        }
-    }
-
-    fn get_all(conn: &Connection) -> Result<Vec<Box<Self>>, DbOperationsError> {
-        let mut stmt = match conn.prepare("SELECT * FROM activities WHERE deleted = 0") {
-            Ok(stmt) => stmt,
-            Err(_) => return Err(DbOperationsError::GenericError),
-        };
 
-        let rows = match stmt.query_map([], |row| {
-            let activity_id = row.get(0)?;
-            let people =
-                match crate::db::db_helpers::people::get_by_activity(conn, activity_id, true) {
-                    Ok(people) => people,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
+                if !results.is_empty() {
+                    for id in results {
+                        let mut stmt = match conn.prepare(
+                            "
+                                UPDATE
+                                    people_activities
+                                SET
+                                    deleted = TRUE
+                                WHERE
+                                    id = ?1",
+                        ) {
+                            Ok(stmt) => stmt,
+                            Err(e) => {
+                                return Err(DbOperationsError::InvalidStatement { sqlite_error: e })
+                            }
                         };
-                        return Err(sqlite_error);
+                        match stmt.execute(params![id]) {
+                            Ok(updated) => {
+                                println!(
+                                    "[DEBUG][people_activities][update] {} rows were updated",
+                                    updated
+                                );
+                            }
+                            Err(e) => {
+                                return Err(DbOperationsError::QueryError { sqlite_error: e })
+                            }
+                        }
                     }
+                }
+
+                let mut stmt = match conn.prepare(
+                    "INSERT INTO people_activities (
+                            person_id,
+                            activity_id,
+                            deleted
+                        ) VALUES (?1, ?2, FALSE)
+                            ON CONFLICT (person_id, activity_id) DO UPDATE SET deleted = FALSE",
+                ) {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
                 };
-            let activity_type = match ActivityType::get_by_id(conn, row.get(2)?) {
-                Ok(activity_type) => match activity_type {
-                    Some(activity_type) => activity_type,
-                    None => panic!("Activity type cannot be None"),
-                },
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
+                match stmt.execute(params![person.id, self.id]) {
+                    Ok(updated) => {
+                        println!(
+                            "[DEBUG][people_activities][insert] {} rows were updated",
+                            updated
+                        );
+                    }
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 }
-            };
-            Ok(Activity {
-                id: activity_id,
-                name: row.get(1)?,
-                activity_type,
-                date: crate::helpers::parse_from_str_ymd(
-                    row.get::<usize, String>(3).unwrap_or_default().as_str(),
-                )
-                .unwrap_or_default(),
-                content: row.get(4)?,
-                people,
-            })
-        }) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::GenericError),
-        };
+            }
 
-        let mut activities = Vec::new();
+            journal.finish()?;
+            Ok(self)
+        })
+    }
 
-        for activity in rows.into_iter() {
-            let activity = match activity {
-                Ok(activity) => activity,
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
-                }
-            };
-            activities.push(Box::new(activity));
-        }
+    fn get_by_id(
+        conn: &Connection,
+        load: &mut LoadContext,
+        id: u64,
+    ) -> Result<Option<Entities>, DbOperationsError> {
+        let mut activities = loader::activities(
+            conn,
+            load,
+            "
+            SELECT
+                *
+            FROM
+                activities
+            WHERE
+                id = ?1 AND
+            deleted = 0",
+            params![id],
+            Relations::ALL,
+        )?;
+        Ok(activities.pop().map(Entities::Activity))
+    }
 
-        Ok(activities)
+    fn get_all(conn: &Connection, load: &mut LoadContext) -> Result<Vec<Self>, DbOperationsError> {
+        loader::activities(
+            conn,
+            load,
+            "SELECT * FROM activities WHERE deleted = 0",
+            [],
+            Relations::ALL,
+        )
     }
 }
 
-#[derive(Debug, AsRefStr, EnumString, Clone, PartialEq)]
+#[derive(Debug, AsRefStr, EnumString, Clone, PartialEq, Serialize)]
 pub enum ActivityType {
     Phone,
     InPerson,
@@ -772,7 +618,7 @@ impl ActivityType {
 
         let mut rows = match stmt.query(params![id]) {
             Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
+            Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
         };
 
         match rows.next() {
@@ -834,8 +680,12 @@ mod tests {
                 name,
                 activity_type,
                 date,
+                time: None,
+                timezone: None,
                 content,
-                people
+                people,
+                created_at: None,
+                updated_at: None,
             },
             activity
         );
@@ -0,0 +1,260 @@
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::prelude::*;
+use chrono::LocalResult;
+use chrono_tz::Tz;
+use snafu::prelude::*;
+
+// The system's timezone, or else UTC
+pub fn system_timezone() -> Tz {
+    env::var("TZ")
+        .ok()
+        .or_else(|| iana_time_zone::get_timezone().ok())
+        .and_then(|name| Tz::from_str(name.trim_start_matches(':')).ok())
+        .unwrap_or(Tz::UTC)
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum DateTimeError {
+    #[snafu(display(
+        "Expected a date like 2024-05-01, optionally followed by a time like 18:30 and a timezone like Europe/Lisbon"
+    ))]
+    InvalidFormat,
+    #[snafu(display("Invalid date {}", date))]
+    InvalidDate { date: String },
+    #[snafu(display("Invalid time {}", time))]
+    InvalidTime { time: String },
+    #[snafu(display("Unknown timezone {}", timezone))]
+    UnknownTimezone { timezone: String },
+    #[snafu(display("A timezone needs a time"))]
+    TimezoneWithoutTime,
+    #[snafu(display("Year {} is out of range", year))]
+    OutOfRange { year: i32 },
+}
+
+// The time `datetime` stands for in `timezone`. Times skipped by a daylight
+// saving change are moved forward by it, repeated ones are taken the first
+// time round.
+pub fn localize(timezone: Tz, datetime: NaiveDateTime) -> DateTime<Tz> {
+    match timezone.from_local_datetime(&datetime) {
+        LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => at,
+        LocalResult::None => {
+            let before = timezone
+                .offset_from_utc_datetime(&(datetime - chrono::Duration::days(1)))
+                .fix();
+            timezone.from_utc_datetime(
+                &(datetime - chrono::Duration::seconds(before.local_minus_utc() as i64)),
+            )
+        }
+    }
+}
+
+// A date, optionally at a time of day in an IANA timezone. Without a time it
+// is the whole day, wherever it is observed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Moment {
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub timezone: Option<Tz>,
+}
+
+impl Moment {
+    pub fn new(date: NaiveDate, time: Option<NaiveTime>, timezone: Option<Tz>) -> Moment {
+        Moment {
+            date,
+            time,
+            timezone,
+        }
+    }
+
+    // Times without a timezone are in UTC, as they are stored
+    pub fn timezone(&self) -> Tz {
+        self.timezone.unwrap_or(Tz::UTC)
+    }
+
+    // The instant the moment stands for, when it has a time
+    pub fn at(&self) -> Option<DateTime<Tz>> {
+        self.time
+            .map(|time| localize(self.timezone(), self.date.and_time(time)))
+    }
+
+    // The stored form: the date, or with a time an RFC 3339 timestamp at the
+    // timezone's offset
+    pub fn to_rfc3339(&self) -> String {
+        match self.at() {
+            Some(at) => at.to_rfc3339(),
+            None => self.date.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    // Reads the stored form. The time is kept as it was written, which is
+    // the time in `timezone`; without a timezone the time is taken in UTC.
+    pub fn from_rfc3339(value: &str, timezone: Option<Tz>) -> Result<Moment, chrono::ParseError> {
+        if value.len() <= 10 {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")?;
+            return Ok(Moment::new(date, None, timezone));
+        }
+        let at = DateTime::parse_from_rfc3339(value)?;
+        Ok(match timezone {
+            Some(timezone) => {
+                let local = at.naive_local();
+                Moment::new(local.date(), Some(local.time()), Some(timezone))
+            }
+            None => {
+                let utc = at.naive_utc();
+                Moment::new(utc.date(), Some(utc.time()), Some(Tz::UTC))
+            }
+        })
+    }
+
+    // The time of day with its timezone, like 18:30 Europe/Lisbon
+    pub fn time_of_day(&self) -> Option<String> {
+        let time = self.time?;
+        let format = if time.second() == 0 {
+            "%H:%M"
+        } else {
+            "%H:%M:%S"
+        };
+        Some(match self.timezone {
+            Some(timezone) => format!("{} {}", time.format(format), timezone.name()),
+            None => time.format(format).to_string(),
+        })
+    }
+
+    // Parses a date as given on the command line or in the editor,
+    // 2024-05-01, optionally followed by a time, 18:30 or 18:30:15, and a
+    // timezone. Times without a timezone are in `default`.
+    pub fn parse(value: &str, default: Tz) -> Result<Moment, DateTimeError> {
+        let mut parts: Vec<&str> = value.split_whitespace().collect();
+        // 2024-05-01T18:30 is accepted too
+        if let Some((date, time)) = parts.first().copied().and_then(|date| date.split_once('T')) {
+            parts.splice(0..1, [date, time]);
+        }
+        let (date, time, timezone) = match parts[..] {
+            [date] => (date, None, None),
+            [date, time] => (date, Some(time), None),
+            [date, time, timezone] => (date, Some(time), Some(timezone)),
+            _ => return InvalidFormatSnafu.fail(),
+        };
+        let date = match crate::helpers::parse_from_str_ymd(date) {
+            Ok(date) => date,
+            Err(_) => match crate::helpers::parse_from_str_md(date) {
+                Ok(date) => date,
+                Err(_) => return InvalidDateSnafu { date }.fail(),
+            },
+        };
+        let time = match time {
+            Some(time) => match NaiveTime::parse_from_str(time, "%H:%M:%S")
+                .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            {
+                Ok(parsed) => Some(parsed),
+                // A date followed by a timezone
+                Err(_) if Tz::from_str(time).is_ok() => return TimezoneWithoutTimeSnafu.fail(),
+                Err(_) => return InvalidTimeSnafu { time }.fail(),
+            },
+            None => None,
+        };
+        let timezone = match timezone {
+            Some(timezone) => match Tz::from_str(timezone) {
+                Ok(timezone) => Some(timezone),
+                Err(_) => return UnknownTimezoneSnafu { timezone }.fail(),
+            },
+            None => time.map(|_| default),
+        };
+        Ok(Moment::new(date, time, timezone))
+    }
+}
+
+// Parses like `Moment::parse`, with times without a timezone in the system's
+impl FromStr for Moment {
+    type Err = DateTimeError;
+
+    fn from_str(value: &str) -> Result<Moment, DateTimeError> {
+        Moment::parse(value, system_timezone())
+    }
+}
+
+// The form `parse` reads, with seconds only when there are any
+impl fmt::Display for Moment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date.format("%Y-%m-%d"))?;
+        if let Some(time_of_day) = self.time_of_day() {
+            write!(f, " {}", time_of_day)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moments() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let time = NaiveTime::from_hms_opt(18, 30, 0).unwrap();
+        let lisbon = Moment::new(date, Some(time), Some(Tz::Europe__Lisbon));
+
+        assert_eq!(
+            lisbon,
+            Moment::from_str("2024-05-01 18:30 Europe/Lisbon").unwrap()
+        );
+        assert_eq!(
+            lisbon,
+            Moment::from_str("2024-05-01T18:30:00 Europe/Lisbon").unwrap()
+        );
+        assert_eq!("2024-05-01 18:30 Europe/Lisbon", lisbon.to_string());
+        assert_eq!(
+            Moment::new(date, None, None),
+            Moment::from_str("2024-05-01").unwrap()
+        );
+        assert_eq!(
+            lisbon,
+            Moment::parse("2024-05-01 18:30", Tz::Europe__Lisbon).unwrap()
+        );
+        assert!(matches!(
+            Moment::from_str("2024-05-01 Europe/Lisbon"),
+            Err(DateTimeError::TimezoneWithoutTime)
+        ));
+        assert!(matches!(
+            Moment::from_str("2024-05-01 18:30 Europe/Porto"),
+            Err(DateTimeError::UnknownTimezone { .. })
+        ));
+        assert!(matches!(
+            Moment::from_str("2024-05-01 25:00"),
+            Err(DateTimeError::InvalidTime { .. })
+        ));
+
+        // Stored at Lisbon's summer offset, and read back as written
+        assert_eq!("2024-05-01T18:30:00+01:00", lisbon.to_rfc3339());
+        assert_eq!(
+            lisbon,
+            Moment::from_rfc3339(&lisbon.to_rfc3339(), Some(Tz::Europe__Lisbon)).unwrap()
+        );
+        assert_eq!(
+            Moment::new(
+                date,
+                Some(NaiveTime::from_hms_opt(17, 30, 0).unwrap()),
+                Some(Tz::UTC)
+            ),
+            Moment::from_rfc3339(&lisbon.to_rfc3339(), None).unwrap()
+        );
+        assert_eq!(
+            Moment::new(date, None, None),
+            Moment::from_rfc3339("2024-05-01", None).unwrap()
+        );
+
+        // 01:30 does not exist in Lisbon on the last Sunday of March
+        let skipped = NaiveDate::from_ymd_opt(2024, 3, 31)
+            .unwrap()
+            .and_hms_opt(1, 30, 0)
+            .unwrap();
+        assert_eq!(
+            "2024-03-31T02:30:00+01:00",
+            localize(Tz::Europe__Lisbon, skipped).to_rfc3339()
+        );
+    }
+}
@@ -1,10 +1,12 @@
 use chrono::prelude::*;
 use rusqlite::params;
+use serde::Serialize;
 use std::{convert::AsRef, fmt};
 
 use crate::db::db_interface::DbOperationsError;
+use crate::db::loader::{self, LoadContext, Relations};
 use crate::entities::person::Person;
-use crate::entities::reminder::{RecurringType, Reminder};
+use crate::entities::reminder::Reminder;
 use rusqlite::Connection;
 
 #[derive(Debug)]
@@ -14,11 +16,34 @@ pub enum EventError {
     DateError,
 }
 
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventError::DbError(e) => write!(f, "{}", e),
+            EventError::EntityError(message) => write!(f, "{}", message),
+            EventError::DateError => write!(f, "The date range is out of bounds"),
+        }
+    }
+}
+
+impl std::error::Error for EventError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EventError::DbError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// `Event::kind` already tells the two apart
+#[derive(Serialize)]
+#[serde(untagged)]
 pub enum EventType {
     Person(Person),
     Reminder(Reminder),
 }
 
+#[derive(Serialize)]
 pub struct Event {
     pub date: NaiveDate,
     kind: String,
@@ -26,20 +51,25 @@ pub struct Event {
 }
 
 impl Event {
-    pub fn get_all(conn: &Connection, mut days: u64) -> Result<Vec<Event>, EventError> {
+    pub fn get_all(
+        conn: &Connection,
+        load: &mut LoadContext,
+        mut days: u64,
+    ) -> Result<Vec<Event>, EventError> {
         if days == 0 {
             days = 10 * 365; // 10 years
         }
         let mut events: Vec<Event> = vec![];
         let today = Local::now().naive_local();
-        let today_str = format!("{}", today.format("%Y-%m-%d"));
         let date_limit = match today.checked_add_days(chrono::Days::new(days)) {
             Some(date) => date,
             None => return Err(EventError::DateError),
         };
         let date_limit_str = format!("{}", date_limit.format("%Y-%m-%d"));
 
-        let mut stmt = match conn.prepare(
+        let people = match loader::people(
+            conn,
+            load,
             "SELECT
                     *,
                     strftime('%j', birthday) - strftime('%j', 'now') AS days_remaining
@@ -52,83 +82,13 @@ impl Event {
                 AND
                     deleted = 0
                 ",
+            params![days],
+            Relations::ALL,
         ) {
-            Ok(stmt) => stmt,
-            Err(e) => {
-                return Err(EventError::DbError(DbOperationsError::InvalidStatement {
-                    sqlite_error: e,
-                }));
-            }
-        };
-
-        let rows = match stmt.query_map(params![days], |row| {
-            let person_id = row.get(0)?;
-            let notes = match crate::db::db_helpers::notes::get_by_person(conn, person_id) {
-                Ok(notes) => notes,
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
-                }
-            };
-            let reminders =
-                match crate::db::db_helpers::reminders::get_by_person_reminders(conn, person_id) {
-                    Ok(reminders) => reminders,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-            let contact_info =
-                match crate::db::db_helpers::contact_info::get_by_person(conn, person_id) {
-                    Ok(contact_info) => contact_info,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-            let activities = match crate::db::db_helpers::activities::get_by_person(conn, person_id)
-            {
-                Ok(activities) => activities,
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
-                }
-            };
-            Ok(Person {
-                id: person_id,
-                name: row.get(1)?,
-                birthday: Some(
-                    crate::helpers::parse_from_str_ymd(
-                        row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                    )
-                    .unwrap_or_default(),
-                ),
-                contact_info,
-                activities,
-                reminders,
-                notes,
-            })
-        }) {
-            Ok(rows) => rows,
-            Err(_) => return Err(EventError::DbError(DbOperationsError::QueryError)),
+            Ok(people) => people,
+            Err(e) => return Err(EventError::DbError(e)),
         };
-        for person in rows.into_iter() {
-            let person = match person {
-                Ok(person) => person,
-                Err(_) => return Err(EventError::EntityError("Person".to_string())),
-            };
+        for person in people.into_iter() {
             if let Some(birthday) = person.birthday {
                 events.push(Event {
                     date: birthday,
@@ -138,86 +98,47 @@ impl Event {
             }
         }
 
-        let mut stmt = match conn.prepare(
+        // Recurring reminders can start before today, each shows up when it
+        // is due. Overdue reminders stay until they are done.
+        let reminders = match loader::reminders(
+            conn,
+            load,
             "
                 SELECT
                     *
                 FROM
                     reminders
                 WHERE
-                    (
-                        date BETWEEN ?1 AND ?2
-                        OR
-                        recurring < 8
-                    )
+                    substr(date, 1, 10) <= ?1
                 AND deleted = 0
                 ",
+            params![date_limit_str],
+            Relations::ALL,
         ) {
-            Ok(stmt) => stmt,
-            Err(e) => {
-                return Err(EventError::DbError(DbOperationsError::InvalidStatement {
-                    sqlite_error: e,
-                }))
-            }
-        };
-        let rows = match stmt.query_map(params![today_str, date_limit_str], |row| {
-            let reminder_id = row.get(0)?;
-            let people = match crate::db_helpers::people::get_by_reminder(conn, reminder_id) {
-                Ok(people) => people,
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
-                }
-            };
-            let recurring_type = match RecurringType::get_by_id(conn, row.get(4)?) {
-                Ok(recurring_type) => match recurring_type {
-                    Some(recurring_type) => recurring_type,
-                    None => panic!("Recurring Type cannot be None"),
-                },
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
-                }
-            };
-            Ok(Reminder {
-                id: reminder_id,
-                name: row.get(1)?,
-                date: crate::helpers::parse_from_str_ymd(
-                    row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                )
-                .unwrap_or_default(),
-                description: row.get(3)?,
-                recurring: recurring_type,
-                people,
-            })
-        }) {
-            Ok(rows) => rows,
-            Err(_) => return Err(EventError::DbError(DbOperationsError::QueryError)),
+            Ok(reminders) => reminders,
+            Err(e) => return Err(EventError::DbError(e)),
         };
-
-        for reminder in rows.into_iter() {
-            let reminder = match reminder {
-                Ok(reminder) => reminder,
-                Err(_) => return Err(EventError::EntityError("Reminder".to_string())),
-            };
-            events.push(Event {
-                date: reminder.date,
-                kind: "Reminder".to_string(),
-                details: EventType::Reminder(reminder),
-            });
+        for reminder in reminders.into_iter() {
+            match reminder.due(today.date()) {
+                Some(due) if due.date <= date_limit.date() => events.push(Event {
+                    date: due.date,
+                    kind: "Reminder".to_string(),
+                    details: EventType::Reminder(reminder),
+                }),
+                _ => {}
+            }
         }
         Ok(events)
     }
 }
 
-impl fmt::Display for Event {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Event {
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn display_with_date_format(&self, date_format: &str) -> String {
+        let date = self.date.format(date_format).to_string();
         match &self.details {
             EventType::Person(person) => {
                 let mut contact_info_str = String::new();
@@ -227,21 +148,16 @@ impl fmt::Display for Event {
                     contact_info_str.push_str(": ");
                     contact_info_str.push_str(ci.details.as_ref());
                 }
-                write!(
-                    f,
+                format!(
                     "name: {}\ndate: {}\nkind: {}\ncontact info: {}\n",
-                    person.name,
-                    &self.date.to_string(),
-                    &self.kind,
-                    contact_info_str,
+                    person.name, date, &self.kind, contact_info_str,
                 )
             }
             EventType::Reminder(reminder) => {
-                write!(
-                    f,
+                format!(
                     "name: {}\ndate: {}\nkind: {}\ndescription: {}\npeople: {}\nrecurring: {}\n",
                     reminder.name,
-                    &self.date.to_string(),
+                    date,
                     &self.kind,
                     reminder
                         .description
@@ -253,13 +169,23 @@ impl fmt::Display for Event {
                         .map(|p| p.name.as_str())
                         .collect::<Vec<&str>>()
                         .join(", "),
-                    reminder.recurring.as_ref(),
+                    reminder.recurrence(),
                 )
             }
         }
     }
 }
 
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.display_with_date_format(crate::config::DEFAULT_DATE_FORMAT)
+        )
+    }
+}
+
 pub trait EventTrait: fmt::Display {}
 impl EventTrait for Person {}
 impl EventTrait for Reminder {}
@@ -1,12 +1,19 @@
 use std::fmt;
 
 use chrono::prelude::*;
+use chrono_tz::Tz;
 use rusqlite::params;
+use serde::Serialize;
 
+use crate::db::entity_kind::EntityKind;
+use crate::db::journal::{self, Operation};
+use crate::db::loader::{self, LoadContext, Relations};
+use crate::db::transaction::atomic;
 use crate::db_interface::{DbOperations, DbOperationsError};
+use crate::entities::datetime::Moment;
 use crate::entities::person::Person;
 use crate::entities::Entities;
-use crate::{CliError, DateParseSnafu, RecordParseSnafu};
+use crate::{CliError, RecordParseSnafu};
 use rusqlite::Connection;
 
 pub static NOTE_TEMPLATE: &str = "Date: {date}
@@ -16,12 +23,16 @@ People: {people}
 
 use super::Entity;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Note {
     pub id: u64,
     pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub timezone: Option<Tz>,
     pub content: String,
     pub people: Vec<Person>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
 }
 
 impl Entity for Note {
@@ -35,36 +46,61 @@ impl Note {
         Note {
             id,
             date,
+            time: None,
+            timezone: None,
             content,
             people,
+            created_at: None,
+            updated_at: None,
         }
     }
 
+    pub fn moment(&self) -> Moment {
+        Moment::new(self.date, self.time, self.timezone)
+    }
+
+    pub fn set_moment(&mut self, moment: Moment) {
+        self.date = moment.date;
+        self.time = moment.time;
+        self.timezone = moment.timezone;
+    }
+
     pub fn get(
         conn: &Connection,
+        load: &mut LoadContext,
         person: Option<String>,
         content: Option<String>,
     ) -> Result<Vec<Note>, DbOperationsError> {
         let mut notes: Vec<Note> = vec![];
         if let Some(person) = person {
-            notes = Self::get_by_person(conn, person)?;
+            notes = Self::get_by_person(conn, load, person)?;
             return Ok(notes);
         }
         if let Some(content) = content {
-            notes = crate::db::db_helpers::notes::get_by_content(conn, content)?;
+            notes = crate::db::db_helpers::notes::get_by_content(conn, load, content)?;
         }
         Ok(notes)
     }
 
-    fn get_by_person(conn: &Connection, person: String) -> Result<Vec<Note>, DbOperationsError> {
-        let person = crate::db::db_helpers::people::get_by_name(conn, person);
-        match person {
-            Ok(person) => {
-                if person.len() > 1 {
-                    return Err(DbOperationsError::UnexpectedMultipleEntries);
+    fn get_by_person(
+        conn: &Connection,
+        load: &mut LoadContext,
+        person: String,
+    ) -> Result<Vec<Note>, DbOperationsError> {
+        let people = crate::db::db_helpers::people::get_by_name(conn, load, person.clone());
+        match people {
+            Ok(people) => {
+                if people.len() > 1 {
+                    return Err(DbOperationsError::UnexpectedMultipleEntries {
+                        entity: EntityKind::Person,
+                        name: person,
+                    });
                 }
-                let notes = person[0].notes.clone();
-                Ok(notes)
+                Ok(people
+                    .into_iter()
+                    .next()
+                    .map(|person| person.notes)
+                    .unwrap_or_default())
             }
             Err(e) => Err(e),
         }
@@ -73,40 +109,23 @@ impl Note {
     pub fn update(
         &mut self,
         conn: &Connection,
-        date: Option<String>,
+        date: Option<Moment>,
         content: Option<String>,
         people: Vec<String>,
     ) -> Result<&Self, CliError> {
-        if let Some(date) = date {
-            let date_obj: Option<NaiveDate>;
-            match crate::helpers::parse_from_str_ymd(&date) {
-                Ok(date) => date_obj = Some(date),
-                Err(_) => match crate::helpers::parse_from_str_md(&date) {
-                    Ok(date) => date_obj = Some(date),
-                    Err(_) => {
-                        return DateParseSnafu {
-                            date: date.to_string(),
-                        }
-                        .fail()
-                    }
-                },
-            }
-            self.date = match date_obj {
-                Some(date) => date,
-                None => {
-                    return DateParseSnafu {
-                        date: date.to_string(),
-                    }
-                    .fail()
-                }
-            };
+        if let Some(moment) = date {
+            self.set_moment(moment);
         }
 
         if let Some(content) = content {
             self.content = content;
         }
 
-        self.people = match crate::db::db_helpers::people::get_by_names(conn, people) {
+        self.people = match crate::db::db_helpers::people::get_by_names(
+            conn,
+            &mut LoadContext::new(true),
+            people,
+        ) {
             Ok(people) => people,
             Err(_) => {
                 return RecordParseSnafu {
@@ -153,284 +172,237 @@ impl Note {
 
 impl DbOperations for Note {
     fn add(&self, conn: &Connection) -> Result<&Note, DbOperationsError> {
-        let date_str = self.date.to_string();
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Add, EntityKind::Note, self.id)?;
+            let date_str = self.moment().to_rfc3339();
 
-        let mut stmt = match conn.prepare(
-            "INSERT INTO
-                notes (date, content, deleted)
-                VALUES (?1, ?2, FALSE)
-            ",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-
-        match stmt.execute(params![date_str, self.content]) {
-            Ok(updated) => {
-                println!("[DEBUG][notes][insert] {} rows were updated", updated);
-            }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
-
-        let id = &conn.last_insert_rowid();
-
-        for person in &self.people {
             let mut stmt = match conn.prepare(
-                "INSERT INTO people_notes (
-                    person_id,
-                    note_id,
-                    deleted
-                )
-                    VALUES (?1, ?2, FALSE)",
+                "INSERT INTO
+                    notes (date, timezone, content, deleted, created_at, updated_at)
+                    VALUES (?1, ?2, ?3, FALSE, datetime('now'), datetime('now'))
+                ",
             ) {
                 Ok(stmt) => stmt,
                 Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
 
-            match stmt.execute(params![person.id, id]) {
+            match stmt.execute(params![
+                date_str,
+                self.timezone.map(|timezone| timezone.name()),
+                self.content
+            ]) {
                 Ok(updated) => {
-                    println!(
-                        "[DEBUG][people_notes][insert] {} rows were updated",
-                        updated
-                    );
+                    println!("[DEBUG][notes][insert] {} rows were updated", updated);
                 }
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
-        }
 
-        Ok(self)
-    }
-
-    fn remove(&self, conn: &Connection) -> Result<&Self, DbOperationsError> {
-        let mut stmt = match conn.prepare(
-            "UPDATE
-                    notes
-                SET
-                    deleted = TRUE
-                WHERE
-                    id = ?1",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
+            let id = &conn.last_insert_rowid();
 
-        match stmt.execute([self.id]) {
-            Ok(updated) => {
-                println!("[DEBUG][notes][update] {} rows were updated", updated);
+            for person in &self.people {
+                let mut stmt = match conn.prepare(
+                    "INSERT INTO people_notes (
+                        person_id,
+                        note_id,
+                        deleted
+                    )
+                        VALUES (?1, ?2, FALSE)
+                        ON CONFLICT (person_id, note_id) DO UPDATE SET deleted = FALSE",
+                ) {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
+
+                match stmt.execute(params![person.id, id]) {
+                    Ok(updated) => {
+                        println!(
+                            "[DEBUG][people_notes][insert] {} rows were updated",
+                            updated
+                        );
+                    }
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+                }
             }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
 
-        Ok(self)
+            journal.finish()?;
+            Ok(self)
+        })
     }
 
-    fn save(&self, conn: &Connection) -> Result<&Note, DbOperationsError> {
-        let mut stmt = match conn.prepare(
-            "UPDATE
-                notes
-            SET
-                date = ?1,
-                content = ?2
-            WHERE
-                id = ?3",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-
-        match stmt.execute(params![self.date.to_string(), self.content, self.id]) {
-            Ok(updated) => {
-                println!("[DEBUG][notes][update] {} rows were updated", updated);
-            }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
-
-        for person in self.people.iter() {
+    fn remove(&self, conn: &Connection) -> Result<&Self, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Remove, EntityKind::Note, self.id)?;
             let mut stmt = match conn.prepare(
-                "SELECT
-                        id
-                    FROM
-                        people_notes
+                "UPDATE
+                        notes
+                    SET
+                        deleted = TRUE
                     WHERE
-                        note_id = ?1
-                        AND person_id = ?2
-                        AND deleted = 0",
+                        id = ?1",
             ) {
                 Ok(stmt) => stmt,
                 Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
 
-            let mut rows = match stmt.query(params![self.id, person.id]) {
-                Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
-            };
-            let mut results: Vec<u32> = Vec::new();
-            loop {
-                match rows.next() {
-                    Ok(row) => match row {
-                        Some(row) => match row.get(0) {
-                            Ok(row) => results.push(row),
-                            Err(e) => {
-                                return Err(DbOperationsError::RecordError {
-                                    sqlite_error: Some(e),
-                                    strum_error: None,
-                                })
-                            }
-                        },
-                        None => break,
-                    },
-                    Err(e) => {
-                        return Err(DbOperationsError::RecordError {
-                            sqlite_error: Some(e),
-                            strum_error: None,
-                        })
-                    }
+            match stmt.execute([self.id]) {
+                Ok(updated) => {
+                    println!("[DEBUG][notes][update] {} rows were updated", updated);
                 }
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
 
-            if !results.is_empty() {
-                for id in results {
-                    let mut stmt =
-                        match conn.prepare("UPDATE people_notes SET deleted = 1 WHERE id = ?1") {
-                            Ok(stmt) => stmt,
-                            Err(e) => {
-                                return Err(DbOperationsError::InvalidStatement { sqlite_error: e })
-                            }
-                        };
-
-                    match stmt.execute(params![id]) {
-                        Ok(updated) => {
-                            println!(
-                                "[DEBUG][people_notes][update] {} rows were updated",
-                                updated
-                            );
-                        }
-                        Err(_) => return Err(DbOperationsError::QueryError),
-                    }
-                }
-            }
+            journal.finish()?;
+            Ok(self)
+        })
+    }
 
+    fn save(&self, conn: &Connection) -> Result<&Note, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Save, EntityKind::Note, self.id)?;
             let mut stmt = match conn.prepare(
-                "INSERT INTO people_notes (
-                        person_id,
-                        note_id,
-                        deleted
-                    ) VALUES (?1, ?2, FALSE)",
+                "UPDATE
+                    notes
+                SET
+                    date = ?1,
+                    timezone = ?2,
+                    content = ?3,
+                    updated_at = datetime('now')
+                WHERE
+                    id = ?4",
             ) {
                 Ok(stmt) => stmt,
                 Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
-            match stmt.execute(params![person.id, self.id]) {
+
+            match stmt.execute(params![
+                self.moment().to_rfc3339(),
+                self.timezone.map(|timezone| timezone.name()),
+                self.content,
+                self.id
+            ]) {
                 Ok(updated) => {
-                    println!(
-                        "[DEBUG][people_notes][insert] {} rows were updated",
-                        updated
-                    );
+                    println!("[DEBUG][notes][update] {} rows were updated", updated);
                 }
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
-        }
 
-        Ok(self)
-    }
-
-    fn get_by_id(conn: &Connection, id: u64) -> Result<Option<Entities>, DbOperationsError> {
-        let mut stmt = match conn.prepare("SELECT * FROM notes WHERE id = ?1 AND deleted = 0") {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        let mut rows = match stmt.query(params![id]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        match rows.next() {
-            Ok(row) => match row {
-                Some(row) => {
-                    let note_id = match row.get(0) {
-                        Ok(note_id) => note_id,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let people = crate::db::db_helpers::people::get_by_note(conn, note_id)?;
-                    let content = match row.get(2) {
-                        Ok(content) => content,
+            for person in self.people.iter() {
+                let mut stmt = match conn.prepare(
+                    "SELECT
+                            id
+                        FROM
+                            people_notes
+                        WHERE
+                            note_id = ?1
+                            AND person_id = ?2
+                            AND deleted = 0",
+                ) {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
+
+                let mut rows = match stmt.query(params![self.id, person.id]) {
+                    Ok(rows) => rows,
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+                };
+                let mut results: Vec<u32> = Vec::new();
+                loop {
+                    match rows.next() {
+                        Ok(row) => match row {
+                            Some(row) => match row.get(0) {
+                                Ok(row) => results.push(row),
+                                Err(e) => {
+                                    return Err(DbOperationsError::RecordError {
+                                        sqlite_error: Some(e),
+                                        strum_error: None,
+                                    })
+                                }
+                            },
+                            None => break,
+                        },
                         Err(e) => {
                             return Err(DbOperationsError::RecordError {
                                 sqlite_error: Some(e),
                                 strum_error: None,
                             })
                         }
-                    };
-                    Ok(Some(Entities::Note(Note {
-                        id: note_id,
-                        date: crate::helpers::parse_from_str_ymd(
-                            row.get::<usize, String>(1).unwrap_or_default().as_str(),
-                        )
-                        .unwrap_or_default(),
-                        content,
-                        people,
-                    })))
+                    }
                 }
-                None => Ok(None),
-            },
-            Err(e) => Err(DbOperationsError::RecordError {
-                sqlite_error: Some(e),
-                strum_error: None,
-            }),
-        }
-    }
-    fn get_all(conn: &Connection) -> Result<Vec<Box<Self>>, DbOperationsError> {
-        let mut stmt = match conn.prepare("SELECT * FROM notes WHERE deleted = 0") {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
 
-        let rows = match stmt.query_map([], |row| {
-            let note_id = row.get(0)?;
-            let people = match crate::db::db_helpers::people::get_by_note(conn, note_id) {
-                Ok(people) => people,
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
-                }
-            };
-            Ok(Note {
-                id: note_id,
-                date: crate::helpers::parse_from_str_ymd(
-                    row.get::<usize, String>(1).unwrap_or_default().as_str(),
-                )
-                .unwrap_or_default(),
-                content: row.get(2)?,
-                people,
-            })
-        }) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
+                if !results.is_empty() {
+                    for id in results {
+                        let mut stmt = match conn
+                            .prepare("UPDATE people_notes SET deleted = 1 WHERE id = ?1")
+                        {
+                            Ok(stmt) => stmt,
+                            Err(e) => {
+                                return Err(DbOperationsError::InvalidStatement { sqlite_error: e })
+                            }
+                        };
 
-        let mut notes = Vec::new();
+                        match stmt.execute(params![id]) {
+                            Ok(updated) => {
+                                println!(
+                                    "[DEBUG][people_notes][update] {} rows were updated",
+                                    updated
+                                );
+                            }
+                            Err(e) => {
+                                return Err(DbOperationsError::QueryError { sqlite_error: e })
+                            }
+                        }
+                    }
+                }
 
-        for note in rows.into_iter() {
-            let note = match note {
-                Ok(note) => note,
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
+                let mut stmt = match conn.prepare(
+                    "INSERT INTO people_notes (
+                            person_id,
+                            note_id,
+                            deleted
+                        ) VALUES (?1, ?2, FALSE)
+                            ON CONFLICT (person_id, note_id) DO UPDATE SET deleted = FALSE",
+                ) {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
+                match stmt.execute(params![person.id, self.id]) {
+                    Ok(updated) => {
+                        println!(
+                            "[DEBUG][people_notes][insert] {} rows were updated",
+                            updated
+                        );
+                    }
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 }
-            };
-            notes.push(Box::new(note));
-        }
+            }
 
-        Ok(notes)
+            journal.finish()?;
+            Ok(self)
+        })
+    }
+
+    fn get_by_id(
+        conn: &Connection,
+        load: &mut LoadContext,
+        id: u64,
+    ) -> Result<Option<Entities>, DbOperationsError> {
+        let mut notes = loader::notes(
+            conn,
+            load,
+            "SELECT * FROM notes WHERE id = ?1 AND deleted = 0",
+            params![id],
+            Relations::ALL,
+        )?;
+        Ok(notes.pop().map(Entities::Note))
+    }
+    fn get_all(conn: &Connection, load: &mut LoadContext) -> Result<Vec<Self>, DbOperationsError> {
+        loader::notes(
+            conn,
+            load,
+            "SELECT * FROM notes WHERE deleted = 0",
+            [],
+            Relations::ALL,
+        )
     }
 }
 
@@ -446,7 +418,7 @@ impl fmt::Display for Note {
             "note id: {}\ncontent: {}\ndate: {}\npeople:{}\n",
             &self.id,
             &self.content,
-            &self.date.to_string(),
+            &self.moment().to_string(),
             people_str
         )
     }
@@ -469,8 +441,12 @@ mod tests {
             Note {
                 id,
                 date,
+                time: None,
+                timezone: None,
                 content,
                 people,
+                created_at: None,
+                updated_at: None,
             },
             note
         );
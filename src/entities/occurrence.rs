@@ -0,0 +1,174 @@
+use chrono::prelude::*;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use strum_macros::{AsRefStr, EnumString};
+
+use crate::db::db_interface::DbOperationsError;
+use crate::db::entity_kind::EntityKind;
+use crate::db::journal::{self, Operation};
+use crate::db::transaction::atomic;
+
+// What was done about an occurrence of a reminder
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, EnumString, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum OccurrenceStatus {
+    Done,
+    Skipped,
+    Snoozed,
+}
+
+// An occurrence of a reminder that was done, skipped or snoozed. Snoozed
+// occurrences are due again on `snoozed_until`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Occurrence {
+    pub date: NaiveDate,
+    pub status: OccurrenceStatus,
+    pub snoozed_until: Option<NaiveDate>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+impl Occurrence {
+    // Whether the occurrence no longer needs doing
+    pub fn is_handled(&self) -> bool {
+        self.status != OccurrenceStatus::Snoozed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderStatus {
+    Overdue,
+    Pending,
+    Snoozed,
+    Done,
+}
+
+// The occurrence a reminder is waiting on and the date it is due, which is
+// later than the occurrence when it was snoozed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Due {
+    pub occurrence: NaiveDate,
+    pub date: NaiveDate,
+    pub status: ReminderStatus,
+}
+
+impl Due {
+    pub fn new(occurrence: NaiveDate, snoozed_until: Option<NaiveDate>, today: NaiveDate) -> Due {
+        let date = snoozed_until.map_or(occurrence, |until| until.max(occurrence));
+        let status = if date < today {
+            ReminderStatus::Overdue
+        } else if date > today && date > occurrence {
+            ReminderStatus::Snoozed
+        } else {
+            ReminderStatus::Pending
+        };
+        Due {
+            occurrence,
+            date,
+            status,
+        }
+    }
+}
+
+// Records `status` for the occurrence of reminder `reminder_id` on `date`,
+// replacing whatever was recorded for it before
+pub fn record(
+    conn: &Connection,
+    operation: Operation,
+    reminder_id: u64,
+    date: NaiveDate,
+    status: OccurrenceStatus,
+    snoozed_until: Option<NaiveDate>,
+) -> Result<(), DbOperationsError> {
+    atomic(conn, || {
+        let journal = journal::begin(conn, operation, EntityKind::Reminder, reminder_id)?;
+        conn.execute(
+            "INSERT INTO reminder_occurrences (reminder_id, date, status, snoozed_until, created_at)
+                VALUES (?1, ?2, ?3, ?4, datetime('now'))
+                ON CONFLICT (reminder_id, date) DO UPDATE SET
+                    status = excluded.status,
+                    snoozed_until = excluded.snoozed_until,
+                    created_at = excluded.created_at",
+            params![
+                reminder_id,
+                date.to_string(),
+                status.as_ref(),
+                snoozed_until.map(|until| until.to_string())
+            ],
+        )
+        .map_err(|e| DbOperationsError::QueryError { sqlite_error: e })?;
+        journal.finish()?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::loader::LoadContext;
+    use crate::db_interface::DbOperations;
+    use crate::entities::reminder::Reminder;
+    use crate::entities::Entities;
+
+    fn load(conn: &Connection, id: u64) -> Reminder {
+        match Reminder::get_by_id(conn, &mut LoadContext::default(), id).unwrap() {
+            Some(Entities::Reminder(reminder)) => reminder,
+            _ => panic!("reminder {} not found", id),
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        Reminder::new(0, String::from("Call"), date(1), None, None, vec![])
+            .add(&conn)
+            .unwrap();
+        let reminder = load(&conn, 1);
+        assert_eq!(ReminderStatus::Overdue, reminder.status(date(10)));
+
+        reminder.snooze(&conn, date(1), date(12)).unwrap();
+        let reminder = load(&conn, 1);
+        assert_eq!(ReminderStatus::Snoozed, reminder.status(date(10)));
+
+        // Completing a snoozed occurrence replaces the snooze
+        reminder.complete(&conn, date(1)).unwrap();
+        let reminder = load(&conn, 1);
+        assert_eq!(1, reminder.history.len());
+        assert_eq!(OccurrenceStatus::Done, reminder.history[0].status);
+        assert_eq!(ReminderStatus::Done, reminder.status(date(10)));
+
+        let entry = journal::undo(&conn).unwrap().unwrap();
+        assert_eq!("complete", entry.operation);
+        assert_eq!(ReminderStatus::Snoozed, load(&conn, 1).status(date(10)));
+    }
+
+    #[test]
+    fn test_due() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        let today = date(10);
+
+        assert_eq!(
+            ReminderStatus::Overdue,
+            Due::new(date(9), None, today).status
+        );
+        assert_eq!(
+            ReminderStatus::Pending,
+            Due::new(date(10), None, today).status
+        );
+        assert_eq!(
+            ReminderStatus::Pending,
+            Due::new(date(11), None, today).status
+        );
+
+        let due = Due::new(date(9), Some(date(12)), today);
+        assert_eq!((date(12), ReminderStatus::Snoozed), (due.date, due.status));
+        let due = Due::new(date(5), Some(date(8)), today);
+        assert_eq!((date(8), ReminderStatus::Overdue), (due.date, due.status));
+        let due = Due::new(date(5), Some(date(10)), today);
+        assert_eq!((date(10), ReminderStatus::Pending), (due.date, due.status));
+    }
+}
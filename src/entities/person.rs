@@ -1,9 +1,14 @@
 use chrono::prelude::*;
 use rusqlite::params;
+use serde::{Serialize, Serializer};
 use std::{convert::AsRef, fmt, str::FromStr};
 use strum_macros::{AsRefStr, EnumString};
 
 use crate::db::db_interface::DbOperationsError;
+use crate::db::entity_kind::EntityKind;
+use crate::db::journal::{self, Operation};
+use crate::db::loader::{self, LoadContext, Relations};
+use crate::db::transaction::atomic;
 use crate::db_interface::DbOperations;
 use crate::entities::activity::Activity;
 use crate::entities::note::Note;
@@ -21,7 +26,7 @@ Contact Info: {contact_info}
 Activities: {activities}
 Reminders: {reminders}
 ";
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Person {
     pub id: u64,
     pub name: String,
@@ -30,6 +35,8 @@ pub struct Person {
     pub activities: Vec<Activity>,
     pub reminders: Vec<Reminder>,
     pub notes: Vec<Note>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
 }
 
 pub struct EditorData {
@@ -64,6 +71,8 @@ impl Person {
             activities,
             reminders,
             notes: vec![],
+            created_at: None,
+            updated_at: None,
         }
     }
 
@@ -167,6 +176,96 @@ impl Person {
         })
     }
 
+    // Copies the person together with their contact info, activities,
+    // reminders and notes into another database (e.g. another profile).
+    // Other people linked to those records are not copied. The copy runs in
+    // a single transaction on the target database.
+    pub fn copy_to(&self, target: &Connection) -> Result<Person, DbOperationsError> {
+        let tx = match target.unchecked_transaction() {
+            Ok(tx) => tx,
+            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+        };
+
+        let contact_info = self
+            .contact_info
+            .iter()
+            .map(|ci| ContactInfo::new(0, 0, ci.contact_info_type.clone()))
+            .collect();
+        let person = Person::new(
+            0,
+            self.name.clone(),
+            self.birthday,
+            contact_info,
+            vec![],
+            vec![],
+        );
+        let id = person.insert(&tx)?;
+        let mut copied =
+            match loader::people_by_id(&tx, &mut LoadContext::new(true), &[id], Relations::NONE)?
+                .pop()
+            {
+                Some(copied) => copied,
+                None => {
+                    return Err(DbOperationsError::MissingRow {
+                        table: String::from("people"),
+                    })
+                }
+            };
+
+        for activity in self.activities.iter() {
+            Activity {
+                time: activity.time,
+                timezone: activity.timezone,
+                ..Activity::new(
+                    0,
+                    activity.name.clone(),
+                    activity.activity_type.clone(),
+                    activity.date,
+                    activity.content.clone(),
+                    vec![copied.clone()],
+                )
+            }
+            .add(&tx)?;
+        }
+        for reminder in self.reminders.iter() {
+            Reminder {
+                time: reminder.time,
+                timezone: reminder.timezone,
+                exdates: reminder.exdates.clone(),
+                history: reminder.history.clone(),
+                ..Reminder::new(
+                    0,
+                    reminder.name.clone(),
+                    reminder.date,
+                    reminder.description.clone(),
+                    reminder.recurring.clone(),
+                    vec![copied.clone()],
+                )
+            }
+            .add(&tx)?;
+        }
+        for note in self.notes.iter() {
+            Note {
+                time: note.time,
+                timezone: note.timezone,
+                ..Note::new(0, note.date, note.content.clone(), vec![copied.clone()])
+            }
+            .add(&tx)?;
+        }
+
+        if let Err(e) = tx.commit() {
+            return Err(DbOperationsError::InvalidStatement { sqlite_error: e });
+        }
+
+        loader::load_people(
+            target,
+            &mut LoadContext::new(true),
+            std::slice::from_mut(&mut copied),
+            Relations::ALL,
+        )?;
+        Ok(copied)
+    }
+
     fn update_contact_info(conn: &Connection, person: &Person) -> Result<(), DbOperationsError> {
         if !person.contact_info.is_empty() {
             for ci in person.contact_info.iter() {
@@ -193,7 +292,7 @@ impl Person {
 
                 let mut rows = match stmt.query(params![ci_type]) {
                     Ok(rows) => rows,
-                    Err(_) => return Err(DbOperationsError::QueryError),
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 };
                 let mut types: Vec<u32> = Vec::new();
                 loop {
@@ -236,7 +335,7 @@ impl Person {
                 };
                 let mut rows = match stmt.query(params![person.id, types[0], ci_value]) {
                     Ok(rows) => rows,
-                    Err(_) => return Err(DbOperationsError::QueryError),
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 };
 
                 match rows.next() {
@@ -252,10 +351,12 @@ impl Person {
                                                    person_id,
                                                    contact_info_type_id,
                                                    contact_info_details,
-                                                   deleted
+                                                   deleted,
+                                                   created_at,
+                                                   updated_at
                                                )
                                             VALUES
-                                               (?1, ?2, ?3, 0)",
+                                               (?1, ?2, ?3, 0, datetime('now'), datetime('now'))",
                                     ) {
                                         Ok(stmt) => stmt,
                                         Err(e) => {
@@ -271,7 +372,11 @@ impl Person {
                                                 updated
                                             )
                                         }
-                                        Err(_) => return Err(DbOperationsError::QueryError),
+                                        Err(e) => {
+                                            return Err(DbOperationsError::QueryError {
+                                                sqlite_error: e,
+                                            })
+                                        }
                                     }
                                 }
                             }
@@ -282,9 +387,13 @@ impl Person {
                                 })
                             }
                         },
-                        None => return Err(DbOperationsError::QueryError),
+                        None => {
+                            return Err(DbOperationsError::MissingRow {
+                                table: String::from("contact_info"),
+                            })
+                        }
                     },
-                    Err(_) => return Err(DbOperationsError::QueryError),
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 }
             }
 
@@ -303,7 +412,7 @@ impl Person {
 
             let mut rows = match stmt.query(params![person.id]) {
                 Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             };
 
             let mut contact_infos: Vec<ContactInfo> = Vec::new();
@@ -404,7 +513,7 @@ impl Person {
                             updated
                         )
                     }
-                    Err(_) => return Err(DbOperationsError::QueryError),
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 }
             }
         }
@@ -430,7 +539,7 @@ impl Person {
             };
             let mut rows = match stmt.query(params![activity.id, person.id]) {
                 Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             };
 
             match rows.next() {
@@ -451,9 +560,13 @@ impl Person {
                             }
                         };
                     }
-                    None => return Err(DbOperationsError::QueryError),
+                    None => {
+                        return Err(DbOperationsError::MissingRow {
+                            table: String::from("people_activities"),
+                        })
+                    }
                 },
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
         }
 
@@ -472,7 +585,7 @@ impl Person {
         };
         let mut rows = match stmt.query(params![person.id]) {
             Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
+            Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
         };
         let mut ids: Vec<u64> = Vec::new();
         loop {
@@ -524,7 +637,7 @@ impl Person {
                             updated
                         );
                     }
-                    Err(_) => return Err(DbOperationsError::GenericError),
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 }
             }
         }
@@ -549,7 +662,7 @@ impl Person {
             };
             let mut rows = match stmt.query(params![reminder.id, person.id]) {
                 Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             };
 
             match rows.next() {
@@ -570,9 +683,13 @@ impl Person {
                             }
                         };
                     }
-                    None => return Err(DbOperationsError::QueryError),
+                    None => {
+                        return Err(DbOperationsError::MissingRow {
+                            table: String::from("people_reminders"),
+                        })
+                    }
                 },
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
         }
 
@@ -591,7 +708,7 @@ impl Person {
         };
         let mut rows = match stmt.query(params![person.id]) {
             Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
+            Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
         };
         let mut ids: Vec<u64> = Vec::new();
         loop {
@@ -643,7 +760,7 @@ impl Person {
                             updated
                         );
                     }
-                    Err(_) => return Err(DbOperationsError::GenericError),
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 }
             }
         }
@@ -651,98 +768,28 @@ impl Person {
     }
 }
 
-impl crate::db::db_interface::DbOperations for Person {
-    fn add(&self, conn: &Connection) -> Result<&Person, DbOperationsError> {
-        let mut stmt = match conn
-            .prepare("SELECT id FROM people WHERE name = ? AND deleted = 0 COLLATE NOCASE")
-        {
-            Ok(stmt) => stmt,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-
-        let mut rows = match stmt.query(params![self.name]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        let mut ids: Vec<u32> = Vec::new();
-        loop {
-            match rows.next() {
-                Ok(row) => match row {
-                    Some(row) => match row.get(0) {
-                        Ok(row) => ids.push(row),
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    },
-                    None => break,
-                },
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
-                }
-            }
-        }
-
-        if !ids.is_empty() {
-            return Err(DbOperationsError::DuplicateEntry);
-        }
-
-        // TODO make all db operations atomic
-        let birthday_str = match self.birthday {
-            Some(birthday) => birthday.to_string(),
-            None => "".to_string(),
-        };
-
-        let mut stmt = match conn
-            .prepare("INSERT INTO people (name, birthday, deleted) VALUES (?1, ?2, FALSE)")
-        {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-
-        match stmt.execute(params![self.name, birthday_str]) {
-            Ok(updated) => {
-                println!("[DEBUG][people][insert] {} rows were updated", updated);
-            }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
-        let id = conn.last_insert_rowid();
-
-        let res = self.contact_info.iter().try_for_each(|contact_info| {
-            let (ci_type, ci_value): (String, &str) = match &contact_info.contact_info_type {
-                ContactInfoType::Phone(value) => (
-                    ContactInfoType::Phone(value.clone()).as_ref().to_owned(),
-                    value.as_ref(),
-                ),
-                ContactInfoType::WhatsApp(value) => (
-                    ContactInfoType::WhatsApp(value.clone()).as_ref().to_owned(),
-                    value.as_ref(),
-                ),
-                ContactInfoType::Email(value) => (
-                    ContactInfoType::Email(value.clone()).as_ref().to_owned(),
-                    value.as_ref(),
-                ),
-            };
-
-            let mut stmt = match conn.prepare("SELECT id FROM contact_info_types WHERE type = ?") {
+impl Person {
+    // Adds the person with their contact info and returns the new id
+    pub(crate) fn insert(&self, conn: &Connection) -> Result<u64, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Add, EntityKind::Person, self.id)?;
+            let mut stmt = match conn
+                .prepare("SELECT id FROM people WHERE name = ? AND deleted = 0 COLLATE NOCASE")
+            {
                 Ok(stmt) => stmt,
-                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             };
-            let mut rows = match stmt.query(params![ci_type]) {
+
+            let mut rows = match stmt.query(params![self.name]) {
                 Ok(rows) => rows,
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             };
-            let mut types: Vec<u32> = Vec::new();
+            let mut ids: Vec<u32> = Vec::new();
             loop {
                 match rows.next() {
                     Ok(row) => match row {
                         Some(row) => match row.get(0) {
-                            Ok(row) => types.push(row),
+                            Ok(row) => ids.push(row),
                             Err(e) => {
                                 return Err(DbOperationsError::RecordError {
                                     sqlite_error: Some(e),
@@ -761,235 +808,205 @@ impl crate::db::db_interface::DbOperations for Person {
                 }
             }
 
+            if !ids.is_empty() {
+                return Err(DbOperationsError::DuplicateEntry {
+                    entity: EntityKind::Person,
+                    name: self.name.clone(),
+                });
+            }
+
+            // A missing birthday is stored as NULL
+            let birthday_str = self.birthday.map(|birthday| birthday.to_string());
+
             let mut stmt = match conn.prepare(
-                "INSERT INTO contact_info (
-                    person_id,
-                    contact_info_type_id,
-                    contact_info_details,
-                    deleted
-                )
-                    VALUES (?1, ?2, ?3, FALSE)",
+                "INSERT INTO people (name, birthday, deleted, created_at, updated_at)
+                    VALUES (?1, ?2, FALSE, datetime('now'), datetime('now'))",
             ) {
                 Ok(stmt) => stmt,
                 Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
 
-            match stmt.execute(params![id, types[0], ci_value]) {
-                Ok(updated) => println!(
-                    "[DEBUG][contact_info][insert] {} rows were updated",
-                    updated
-                ),
-                Err(_) => return Err(DbOperationsError::QueryError),
+            match stmt.execute(params![self.name, birthday_str]) {
+                Ok(updated) => {
+                    println!("[DEBUG][people][insert] {} rows were updated", updated);
+                }
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
-            Ok(())
-        });
-
-        res?;
+            let id = conn.last_insert_rowid();
 
-        Ok(self)
-    }
+            let res = self.contact_info.iter().try_for_each(|contact_info| {
+                let (ci_type, ci_value): (String, &str) = match &contact_info.contact_info_type {
+                    ContactInfoType::Phone(value) => (
+                        ContactInfoType::Phone(value.clone()).as_ref().to_owned(),
+                        value.as_ref(),
+                    ),
+                    ContactInfoType::WhatsApp(value) => (
+                        ContactInfoType::WhatsApp(value.clone()).as_ref().to_owned(),
+                        value.as_ref(),
+                    ),
+                    ContactInfoType::Email(value) => (
+                        ContactInfoType::Email(value.clone()).as_ref().to_owned(),
+                        value.as_ref(),
+                    ),
+                };
 
-    fn remove(&self, conn: &Connection) -> Result<&Person, DbOperationsError> {
-        let mut stmt = match conn.prepare(
-            "UPDATE
-                    people
-                SET
-                    deleted = TRUE
-                WHERE
-                    id = ?1",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        match stmt.execute([self.id]) {
-            Ok(updated) => {
-                println!("[DEBUG][people][update] {} rows were updated", updated);
-            }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
+                let mut stmt = match conn
+                    .prepare("SELECT id FROM contact_info_types WHERE type = ?")
+                {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
+                let mut rows = match stmt.query(params![ci_type]) {
+                    Ok(rows) => rows,
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+                };
+                let mut types: Vec<u32> = Vec::new();
+                loop {
+                    match rows.next() {
+                        Ok(row) => match row {
+                            Some(row) => match row.get(0) {
+                                Ok(row) => types.push(row),
+                                Err(e) => {
+                                    return Err(DbOperationsError::RecordError {
+                                        sqlite_error: Some(e),
+                                        strum_error: None,
+                                    })
+                                }
+                            },
+                            None => break,
+                        },
+                        Err(e) => {
+                            return Err(DbOperationsError::RecordError {
+                                sqlite_error: Some(e),
+                                strum_error: None,
+                            })
+                        }
+                    }
+                }
 
-        Ok(self)
-    }
+                let mut stmt = match conn.prepare(
+                    "INSERT INTO contact_info (
+                        person_id,
+                        contact_info_type_id,
+                        contact_info_details,
+                        deleted,
+                        created_at,
+                        updated_at
+                    )
+                        VALUES (?1, ?2, ?3, FALSE, datetime('now'), datetime('now'))",
+                ) {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
 
-    fn save(&self, conn: &Connection) -> Result<&Person, DbOperationsError> {
-        let birthday_str = match self.birthday {
-            Some(birthday) => birthday.to_string(),
-            None => "".to_string(),
-        };
+                match stmt.execute(params![id, types[0], ci_value]) {
+                    Ok(updated) => println!(
+                        "[DEBUG][contact_info][insert] {} rows were updated",
+                        updated
+                    ),
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+                }
+                Ok(())
+            });
 
-        let mut stmt = match conn.prepare(
-            "UPDATE
-                people
-            SET
-                name = ?1,
-                birthday = ?2
-            WHERE
-                id = ?3",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        match stmt.execute(params![self.name, birthday_str, self.id]) {
-            Ok(updated) => {
-                println!("[DEBUG][people][update] {} rows were updated", updated);
-            }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
+            res?;
 
-        Person::update_contact_info(conn, self)?;
-        Person::update_activities(conn, self)?;
-        Person::update_reminders(conn, self)?;
+            journal.finish()?;
+            Ok(id as u64)
+        })
+    }
+}
 
+impl crate::db::db_interface::DbOperations for Person {
+    fn add(&self, conn: &Connection) -> Result<&Person, DbOperationsError> {
+        self.insert(conn)?;
         Ok(self)
     }
 
-    fn get_by_id(conn: &Connection, id: u64) -> Result<Option<Entities>, DbOperationsError> {
-        let mut stmt = match conn.prepare("SELECT * FROM people WHERE id = ?1 AND deleted = 0") {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        let mut rows = match stmt.query(params![id]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        match rows.next() {
-            Ok(row) => match row {
-                Some(row) => {
-                    let person_id = match row.get(0) {
-                        Ok(person_id) => person_id,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let name = match row.get(1) {
-                        Ok(name) => name,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let notes = crate::db::db_helpers::notes::get_by_person(conn, person_id)?;
-                    let reminders =
-                        crate::db::db_helpers::reminders::get_by_person_reminders(conn, person_id)?;
-                    let contact_info =
-                        crate::db::db_helpers::contact_info::get_by_person(conn, person_id)?;
-                    let activities =
-                        crate::db::db_helpers::activities::get_by_person(conn, person_id)?;
-                    Ok(Some(Entities::Person(Person {
-                        id: person_id,
-                        name,
-                        birthday: Some(
-                            crate::helpers::parse_from_str_ymd(
-                                row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                            )
-                            .unwrap_or_default(),
-                        ),
-                        contact_info,
-                        activities,
-                        reminders,
-                        notes,
-                    })))
+    fn remove(&self, conn: &Connection) -> Result<&Person, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Remove, EntityKind::Person, self.id)?;
+            let mut stmt = match conn.prepare(
+                "UPDATE
+                        people
+                    SET
+                        deleted = TRUE
+                    WHERE
+                        id = ?1",
+            ) {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+            };
+            match stmt.execute([self.id]) {
+                Ok(updated) => {
+                    println!("[DEBUG][people][update] {} rows were updated", updated);
                 }
-                None => Ok(None),
-            },
-            Err(e) => Err(DbOperationsError::RecordError {
-                sqlite_error: Some(e),
-                strum_error: None,
-            }),
-        }
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+            }
+
+            journal.finish()?;
+            Ok(self)
+        })
     }
 
-    fn get_all(conn: &Connection) -> Result<Vec<Box<Self>>, DbOperationsError> {
-        let mut stmt = match conn.prepare("SELECT * FROM people WHERE deleted = 0 COLLATE NOCASE") {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
+    fn save(&self, conn: &Connection) -> Result<&Person, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Save, EntityKind::Person, self.id)?;
+            // A missing birthday is stored as NULL
+            let birthday_str = self.birthday.map(|birthday| birthday.to_string());
 
-        let rows = match stmt.query_map([], |row| {
-            let person_id = row.get(0)?;
-            let notes = match crate::db::db_helpers::notes::get_by_person(conn, person_id) {
-                Ok(notes) => notes,
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
-                }
+            let mut stmt = match conn.prepare(
+                "UPDATE
+                    people
+                SET
+                    name = ?1,
+                    birthday = ?2,
+                    updated_at = datetime('now')
+                WHERE
+                    id = ?3",
+            ) {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
-            let reminders =
-                match crate::db::db_helpers::reminders::get_by_person_reminders(conn, person_id) {
-                    Ok(reminders) => reminders,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-            let contact_info =
-                match crate::db::db_helpers::contact_info::get_by_person(conn, person_id) {
-                    Ok(contact_info) => contact_info,
-                    Err(e) => {
-                        let sqlite_error = match e {
-                            DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                            other => panic!("Unexpected error type: {:#?}", other),
-                        };
-                        return Err(sqlite_error);
-                    }
-                };
-            let activities = match crate::db::db_helpers::activities::get_by_person(conn, person_id)
-            {
-                Ok(activities) => activities,
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
+            match stmt.execute(params![self.name, birthday_str, self.id]) {
+                Ok(updated) => {
+                    println!("[DEBUG][people][update] {} rows were updated", updated);
                 }
-            };
-            Ok(Person {
-                id: person_id,
-                name: row.get(1)?,
-                birthday: Some(
-                    crate::helpers::parse_from_str_ymd(
-                        row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                    )
-                    .unwrap_or_default(),
-                ),
-                contact_info,
-                activities,
-                reminders,
-                notes,
-            })
-        }) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+            }
 
-        let mut people = Vec::new();
+            Person::update_contact_info(conn, self)?;
+            Person::update_activities(conn, self)?;
+            Person::update_reminders(conn, self)?;
 
-        for person in rows.into_iter() {
-            let person = match person {
-                Ok(person) => person,
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
-                }
-            };
-            people.push(Box::new(person));
-        }
+            journal.finish()?;
+            Ok(self)
+        })
+    }
+
+    fn get_by_id(
+        conn: &Connection,
+        load: &mut LoadContext,
+        id: u64,
+    ) -> Result<Option<Entities>, DbOperationsError> {
+        let mut people = loader::people(
+            conn,
+            load,
+            "SELECT * FROM people WHERE id = ?1 AND deleted = 0",
+            params![id],
+            Relations::ALL,
+        )?;
+        Ok(people.pop().map(Entities::Person))
+    }
 
-        Ok(people)
+    fn get_all(conn: &Connection, load: &mut LoadContext) -> Result<Vec<Self>, DbOperationsError> {
+        loader::people(
+            conn,
+            load,
+            "SELECT * FROM people WHERE deleted = 0 COLLATE NOCASE",
+            [],
+            Relations::ALL,
+        )
     }
 }
 
@@ -1046,10 +1063,11 @@ impl fmt::Display for Person {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ContactInfo {
     id: u64,
     person_id: u64,
+    #[serde(rename = "type", serialize_with = "serialize_contact_info_type")]
     pub contact_info_type: ContactInfoType,
     pub details: String,
 }
@@ -1076,6 +1094,14 @@ impl ContactInfo {
     }
 }
 
+// Only the type name is serialised: the value is already in `details`
+fn serialize_contact_info_type<S: Serializer>(
+    contact_info_type: &ContactInfoType,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(contact_info_type.as_ref())
+}
+
 #[derive(Debug, AsRefStr, EnumString, Clone, PartialEq)]
 pub enum ContactInfoType {
     Phone(String),
@@ -1095,7 +1121,7 @@ impl ContactInfoType {
 
         let mut rows = match stmt.query(params![id]) {
             Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
+            Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
         };
 
         match rows.next() {
@@ -1163,6 +1189,8 @@ mod tests {
                 activities,
                 reminders,
                 notes,
+                created_at: None,
+                updated_at: None,
             },
             person
         );
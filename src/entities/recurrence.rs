@@ -0,0 +1,710 @@
+use chrono::prelude::*;
+use chrono::{Days, Months};
+use serde::{Serialize, Serializer};
+use snafu::prelude::*;
+use std::{collections::VecDeque, fmt, str::FromStr};
+use strum_macros::{AsRefStr, EnumString};
+
+use crate::entities::reminder::RecurringType;
+
+#[derive(Debug, Snafu)]
+pub enum RecurrenceError {
+    #[snafu(display("The rule has no FREQ"))]
+    MissingFrequency,
+    #[snafu(display("Invalid rule part {}", part))]
+    InvalidPart { part: String },
+    #[snafu(display("A rule cannot have both COUNT and UNTIL"))]
+    CountAndUntil,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, EnumString)]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// A BYDAY entry: a weekday, or with `nth` only the nth one of the month
+// (or year), counting from the end when negative. "1MO", "-1FR" or "WE".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeekdayNum {
+    pub nth: Option<i32>,
+    pub weekday: Weekday,
+}
+
+impl fmt::Display for WeekdayNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(nth) = self.nth {
+            write!(f, "{}", nth)?;
+        }
+        let weekday = self.weekday.to_string();
+        write!(f, "{}", weekday[..2].to_uppercase())
+    }
+}
+
+impl FromStr for WeekdayNum {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<WeekdayNum, ()> {
+        if s.len() < 2 || !s.is_char_boundary(s.len() - 2) {
+            return Err(());
+        }
+        let (nth, weekday) = s.split_at(s.len() - 2);
+        let weekday = match weekday.to_uppercase().as_str() {
+            "MO" => Weekday::Mon,
+            "TU" => Weekday::Tue,
+            "WE" => Weekday::Wed,
+            "TH" => Weekday::Thu,
+            "FR" => Weekday::Fri,
+            "SA" => Weekday::Sat,
+            "SU" => Weekday::Sun,
+            _ => return Err(()),
+        };
+        let nth = match nth.trim_start_matches('+') {
+            "" => None,
+            nth => match nth.parse::<i32>() {
+                Ok(nth) if nth != 0 && nth.abs() <= 53 => Some(nth),
+                _ => return Err(()),
+            },
+        };
+        Ok(WeekdayNum { nth, weekday })
+    }
+}
+
+// The subset of an iCalendar RRULE (RFC 5545) prm understands. Weeks start
+// on Monday.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_month: Vec<u32>,
+    pub by_month_day: Vec<i32>,
+    pub by_day: Vec<WeekdayNum>,
+    pub by_set_pos: Vec<i32>,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+}
+
+impl RRule {
+    pub fn new(frequency: Frequency) -> RRule {
+        RRule {
+            frequency,
+            interval: 1,
+            by_month: vec![],
+            by_month_day: vec![],
+            by_day: vec![],
+            by_set_pos: vec![],
+            count: None,
+            until: None,
+        }
+    }
+
+    // The rule with its COUNT, counted from `start`, turned into an UNTIL,
+    // so it can be used from a later occurrence on
+    pub fn without_count(&self, start: NaiveDate) -> RRule {
+        match self.count {
+            Some(_) => RRule {
+                count: None,
+                until: Occurrences::new(start, Some(self.clone()), vec![], start)
+                    .last()
+                    .or(Some(start)),
+                ..self.clone()
+            },
+            None => self.clone(),
+        }
+    }
+
+    // The dates the rule gives in the period starting on `period`, a day, a
+    // Monday, or the first of a month or year depending on the frequency, in
+    // order. Dates before `start` are left in.
+    fn expand(&self, period: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = match self.frequency {
+            Frequency::Daily => {
+                let weekday = self.by_day.is_empty()
+                    || self.by_day.iter().any(|b| b.weekday == period.weekday());
+                let month_day = self.by_month_day.is_empty()
+                    || self.by_month_day.iter().any(|m| is_month_day(period, *m));
+                match weekday && month_day {
+                    true => vec![period],
+                    false => vec![],
+                }
+            }
+            Frequency::Weekly => days(period, 7)
+                .into_iter()
+                .filter(|d| match self.by_day.is_empty() {
+                    true => d.weekday() == start.weekday(),
+                    false => self.by_day.iter().any(|b| b.weekday == d.weekday()),
+                })
+                .collect(),
+            Frequency::Monthly => self.month(period, start),
+            Frequency::Yearly => {
+                if !self.by_month.is_empty() || !self.by_month_day.is_empty() {
+                    (1..=12)
+                        .filter_map(|month| period.with_month(month))
+                        .flat_map(|month| self.month(month, start))
+                        .collect()
+                } else if !self.by_day.is_empty() {
+                    self.select(days(period, days_in_year(period.year())), start)
+                } else {
+                    NaiveDate::from_ymd_opt(period.year(), start.month(), start.day())
+                        .into_iter()
+                        .collect()
+                }
+            }
+        };
+        if !self.by_month.is_empty() {
+            dates.retain(|d| self.by_month.contains(&d.month()));
+        }
+        if !self.by_set_pos.is_empty() {
+            dates = self
+                .by_set_pos
+                .iter()
+                .filter_map(|pos| nth(&dates, *pos))
+                .collect();
+            dates.sort();
+            dates.dedup();
+        }
+        dates
+    }
+
+    fn month(&self, first: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        if !self.by_month.is_empty() && !self.by_month.contains(&first.month()) {
+            return vec![];
+        }
+        self.select(
+            days(first, days_in_month(first.year(), first.month())),
+            start,
+        )
+    }
+
+    // The days of a month or year the BYMONTHDAY and BYDAY parts pick, or
+    // the day of the month of `start` without them
+    fn select(&self, scope: Vec<NaiveDate>, start: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = scope.clone();
+        if !self.by_month_day.is_empty() {
+            dates.retain(|d| self.by_month_day.iter().any(|m| is_month_day(*d, *m)));
+        }
+        if !self.by_day.is_empty() {
+            let picked: Vec<NaiveDate> = self
+                .by_day
+                .iter()
+                .flat_map(|b| {
+                    let matching: Vec<NaiveDate> = scope
+                        .iter()
+                        .copied()
+                        .filter(|d| d.weekday() == b.weekday)
+                        .collect();
+                    match b.nth {
+                        Some(n) => nth(&matching, n).into_iter().collect(),
+                        None => matching,
+                    }
+                })
+                .collect();
+            dates.retain(|d| picked.contains(d));
+        }
+        if self.by_month_day.is_empty() && self.by_day.is_empty() {
+            dates.retain(|d| d.day() == start.day());
+        }
+        dates
+    }
+
+    // The first day of the `index`th period from the one `start` is in
+    fn period(&self, start: NaiveDate, index: u32) -> Option<NaiveDate> {
+        let steps = self.interval.checked_mul(index)?;
+        match self.frequency {
+            Frequency::Daily => start.checked_add_days(Days::new(steps as u64)),
+            Frequency::Weekly => {
+                let monday = start - Days::new(start.weekday().num_days_from_monday() as u64);
+                monday.checked_add_days(Days::new(steps as u64 * 7))
+            }
+            Frequency::Monthly => start.with_day(1)?.checked_add_months(Months::new(steps)),
+            Frequency::Yearly => start
+                .with_ordinal(1)?
+                .checked_add_months(Months::new(steps.checked_mul(12)?)),
+        }
+    }
+
+    // How many whole periods lie between `start` and `date`
+    fn periods_between(&self, start: NaiveDate, date: NaiveDate) -> u32 {
+        if date <= start {
+            return 0;
+        }
+        let elapsed = match self.frequency {
+            Frequency::Daily => (date - start).num_days(),
+            Frequency::Weekly => (date - start).num_days() / 7,
+            Frequency::Monthly => {
+                (date.year() - start.year()) as i64 * 12 + date.month() as i64
+                    - start.month() as i64
+            }
+            Frequency::Yearly => (date.year() - start.year()) as i64,
+        };
+        (elapsed / self.interval as i64).clamp(0, u32::MAX as i64) as u32
+    }
+}
+
+fn days(first: NaiveDate, length: u32) -> Vec<NaiveDate> {
+    first.iter_days().take(length as usize).collect()
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    (28..=31)
+        .rev()
+        .find(|day| NaiveDate::from_ymd_opt(year, month, *day).is_some())
+        .unwrap_or(28)
+}
+
+fn days_in_year(year: i32) -> u32 {
+    match NaiveDate::from_ymd_opt(year, 12, 31) {
+        Some(last) => last.ordinal(),
+        None => 365,
+    }
+}
+
+// `day` is the `month_day`th day of its month, counting from the end when
+// negative
+fn is_month_day(day: NaiveDate, month_day: i32) -> bool {
+    match month_day {
+        m if m > 0 => day.day() as i32 == m,
+        m => days_in_month(day.year(), day.month()) as i32 + 1 + m == day.day() as i32,
+    }
+}
+
+// The `n`th of `dates`, counting from the end when negative
+fn nth(dates: &[NaiveDate], n: i32) -> Option<NaiveDate> {
+    match n {
+        n if n > 0 => dates.get(n as usize - 1).copied(),
+        n if n < 0 => dates
+            .len()
+            .checked_sub(n.unsigned_abs() as usize)
+            .map(|i| dates[i]),
+        _ => None,
+    }
+}
+
+fn parse_list<T: FromStr>(part: &str, value: &str) -> Result<Vec<T>, RecurrenceError> {
+    value
+        .split(',')
+        .map(|v| v.trim().parse::<T>())
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|_| RecurrenceError::InvalidPart {
+            part: part.to_string(),
+        })
+}
+
+// UNTIL is a date or a date-time, of which only the date is kept
+fn parse_until(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+fn join<T: fmt::Display>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// "FREQ=MONTHLY;BYDAY=1MO", with or without the "RRULE:" prefix
+impl FromStr for RRule {
+    type Err = RecurrenceError;
+
+    fn from_str(s: &str) -> Result<RRule, RecurrenceError> {
+        let s = s.trim();
+        let s = match s.get(..6) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("RRULE:") => &s[6..],
+            _ => s,
+        };
+        let mut frequency = None;
+        let mut rule = RRule::new(Frequency::Daily);
+        for part in s.split(';').filter(|p| !p.trim().is_empty()) {
+            let invalid = || RecurrenceError::InvalidPart {
+                part: part.to_string(),
+            };
+            let (name, value) = part.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+            match name.trim().to_uppercase().as_str() {
+                "FREQ" => {
+                    frequency =
+                        Some(Frequency::from_str(&value.to_uppercase()).map_err(|_| invalid())?)
+                }
+                "INTERVAL" => {
+                    rule.interval = match value.parse::<u32>() {
+                        Ok(interval) if interval > 0 => interval,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "BYMONTH" => {
+                    rule.by_month = parse_list(part, value)?;
+                    if rule.by_month.iter().any(|m| !(1..=12).contains(m)) {
+                        return Err(invalid());
+                    }
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = parse_list(part, value)?;
+                    if rule.by_month_day.iter().any(|d| *d == 0 || d.abs() > 31) {
+                        return Err(invalid());
+                    }
+                }
+                "BYDAY" => rule.by_day = parse_list(part, value)?,
+                "BYSETPOS" => {
+                    rule.by_set_pos = parse_list(part, value)?;
+                    if rule.by_set_pos.iter().any(|p| *p == 0 || p.abs() > 366) {
+                        return Err(invalid());
+                    }
+                }
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid())?),
+                "UNTIL" => rule.until = Some(parse_until(value).ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            }
+        }
+        rule.frequency = frequency.context(MissingFrequencySnafu)?;
+        ensure!(
+            rule.count.is_none() || rule.until.is_none(),
+            CountAndUntilSnafu
+        );
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.frequency.as_ref())?;
+        if self.interval > 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_month.is_empty() {
+            write!(f, ";BYMONTH={}", join(&self.by_month))?;
+        }
+        if !self.by_month_day.is_empty() {
+            write!(f, ";BYMONTHDAY={}", join(&self.by_month_day))?;
+        }
+        if !self.by_day.is_empty() {
+            write!(f, ";BYDAY={}", join(&self.by_day))?;
+        }
+        if !self.by_set_pos.is_empty() {
+            write!(f, ";BYSETPOS={}", join(&self.by_set_pos))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for RRule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl RecurringType {
+    // The rule a recurring type stands for, None for one time reminders. A
+    // month based rule starting after the 28th picks the last day it can,
+    // so a reminder on the 31st is due on the last day of shorter months.
+    pub fn rule(&self, start: NaiveDate) -> Option<RRule> {
+        let (frequency, interval) = match self {
+            RecurringType::OneTime => return None,
+            RecurringType::Daily => (Frequency::Daily, 1),
+            RecurringType::Weekly => (Frequency::Weekly, 1),
+            RecurringType::Fortnightly => (Frequency::Weekly, 2),
+            RecurringType::Monthly => (Frequency::Monthly, 1),
+            RecurringType::Quarterly => (Frequency::Monthly, 3),
+            RecurringType::Biannual => (Frequency::Monthly, 6),
+            RecurringType::Yearly => (Frequency::Yearly, 1),
+        };
+        let mut rule = RRule {
+            interval,
+            ..RRule::new(frequency)
+        };
+        if matches!(frequency, Frequency::Monthly | Frequency::Yearly) && start.day() > 28 {
+            if frequency == Frequency::Yearly {
+                rule.by_month = vec![start.month()];
+            }
+            rule.by_month_day = (28..=start.day() as i32).collect();
+            rule.by_set_pos = vec![-1];
+        }
+        Some(rule)
+    }
+
+    // The recurring type `rule` stands for on a reminder due on `start`
+    pub fn matching(rule: Option<&RRule>, start: NaiveDate) -> Option<RecurringType> {
+        [
+            RecurringType::OneTime,
+            RecurringType::Daily,
+            RecurringType::Weekly,
+            RecurringType::Fortnightly,
+            RecurringType::Monthly,
+            RecurringType::Quarterly,
+            RecurringType::Biannual,
+            RecurringType::Yearly,
+        ]
+        .into_iter()
+        .find(|recurring| recurring.rule(start).as_ref() == rule)
+    }
+}
+
+// A `--recurring` value: a recurring type like "monthly", or an RRULE
+pub fn parse(value: &str, start: NaiveDate) -> Result<Option<RRule>, RecurrenceError> {
+    match RecurringType::from_str(value.trim()) {
+        Ok(recurring) => Ok(recurring.rule(start)),
+        Err(_) => RRule::from_str(value).map(Some),
+    }
+}
+
+// A reminder's rule for people: its recurring type if it has one, the RRULE
+// otherwise
+pub fn describe(rule: Option<&RRule>, start: NaiveDate) -> String {
+    match RecurringType::matching(rule, start) {
+        Some(recurring) => recurring.as_ref().to_string(),
+        None => rule.map(|rule| rule.to_string()).unwrap_or_default(),
+    }
+}
+
+// Exception dates as stored: comma separated, NULL when there are none
+pub fn format_dates(dates: &[NaiveDate]) -> Option<String> {
+    match dates.is_empty() {
+        true => None,
+        false => Some(join(dates)),
+    }
+}
+
+// A rule that can't match, like the 30th of February, would otherwise be
+// expanded until the end of time
+const MAX_EMPTY_PERIODS: u32 = 10_000;
+
+// The dates a reminder first due on `start` falls on, from a given date
+// onwards, without its exception dates. COUNT includes the exceptions, as in
+// iCalendar.
+pub struct Occurrences {
+    start: NaiveDate,
+    rule: Option<RRule>,
+    exdates: Vec<NaiveDate>,
+    from: NaiveDate,
+    period: u32,
+    pending: VecDeque<NaiveDate>,
+    produced: u32,
+    done: bool,
+}
+
+impl Occurrences {
+    pub fn new(
+        start: NaiveDate,
+        rule: Option<RRule>,
+        exdates: Vec<NaiveDate>,
+        from: NaiveDate,
+    ) -> Occurrences {
+        // Without a COUNT the periods before `from` can be skipped
+        let period = match &rule {
+            Some(rule) if rule.count.is_none() => {
+                rule.periods_between(start, from).saturating_sub(1)
+            }
+            _ => 0,
+        };
+        Occurrences {
+            start,
+            rule,
+            exdates,
+            from,
+            period,
+            pending: VecDeque::new(),
+            produced: 0,
+            done: false,
+        }
+    }
+
+    // Queues the dates of the next period that has any
+    fn fill(&mut self) {
+        let rule = match &self.rule {
+            Some(rule) => rule,
+            None => {
+                self.pending.push_back(self.start);
+                self.done = true;
+                return;
+            }
+        };
+        for _ in 0..MAX_EMPTY_PERIODS {
+            let period = match rule.period(self.start, self.period) {
+                Some(period) => period,
+                None => break,
+            };
+            self.period += 1;
+            let dates: Vec<NaiveDate> = rule
+                .expand(period, self.start)
+                .into_iter()
+                .filter(|d| *d >= self.start)
+                .collect();
+            for date in dates.iter() {
+                if rule.until.is_some_and(|until| *date > until)
+                    || rule.count.is_some_and(|count| self.produced >= count)
+                {
+                    self.done = true;
+                    return;
+                }
+                self.produced += 1;
+                self.pending.push_back(*date);
+            }
+            if !dates.is_empty() {
+                return;
+            }
+        }
+        self.done = true;
+    }
+}
+
+impl Iterator for Occurrences {
+    type Item = NaiveDate;
+
+    fn next(&mut self) -> Option<NaiveDate> {
+        loop {
+            match self.pending.pop_front() {
+                Some(date) if date < self.from || self.exdates.contains(&date) => continue,
+                Some(date) => return Some(date),
+                None if self.done => return None,
+                None => self.fill(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        crate::helpers::parse_from_str_ymd(s).unwrap()
+    }
+
+    fn next(start: &str, recurring: &str, from: &str, count: usize) -> Vec<String> {
+        let rule = parse(recurring, date(start)).unwrap();
+        Occurrences::new(date(start), rule, vec![], date(from))
+            .take(count)
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_occurrences() {
+        assert_eq!(
+            vec!["2024-01-31", "2024-02-29", "2024-03-31", "2024-04-30"],
+            next("2024-01-31", "monthly", "2024-01-01", 4)
+        );
+        assert_eq!(
+            vec!["2025-02-28", "2025-05-30", "2025-08-30"],
+            next("2023-11-30", "quarterly", "2025-02-01", 3)
+        );
+        assert_eq!(
+            vec!["2025-02-28", "2025-08-31"],
+            next("2024-08-31", "biannual", "2024-09-01", 2)
+        );
+        assert_eq!(
+            vec!["2025-02-28", "2026-02-28", "2027-02-28", "2028-02-29"],
+            next("2024-02-29", "yearly", "2025-01-01", 4)
+        );
+        assert_eq!(
+            vec!["2026-10-19", "2026-10-26"],
+            next("2023-01-02", "weekly", "2026-10-17", 2)
+        );
+        assert_eq!(
+            vec!["2026-10-17"],
+            next("2026-10-17", "daily", "2026-10-17", 1)
+        );
+        assert_eq!(
+            vec!["2026-11-01"],
+            next("2026-11-01", "onetime", "2026-10-17", 3)
+        );
+        assert!(next("2023-01-02", "onetime", "2026-10-17", 1).is_empty());
+    }
+
+    #[test]
+    fn test_rules() {
+        // The first Monday of each month
+        assert_eq!(
+            vec!["2024-02-05", "2024-03-04", "2024-04-01"],
+            next("2024-01-10", "FREQ=MONTHLY;BYDAY=1MO", "2024-01-01", 3)
+        );
+        // Weekdays every 3 weeks, until a date
+        assert_eq!(
+            vec!["2024-01-04", "2024-01-05", "2024-01-22", "2024-01-23"],
+            next(
+                "2024-01-04",
+                "RRULE:FREQ=WEEKLY;INTERVAL=3;BYDAY=MO,TU,WE,TH,FR;UNTIL=20240123",
+                "2024-01-01",
+                10
+            )
+        );
+        // COUNT counts from the start, not from `from`
+        assert_eq!(
+            vec!["2025-12-26"],
+            next(
+                "2024-01-01",
+                "FREQ=YEARLY;BYDAY=-1FR;COUNT=2",
+                "2025-01-01",
+                3
+            )
+        );
+        assert_eq!(
+            vec!["2024-01-31", "2024-03-31"],
+            next(
+                "2024-01-31",
+                "FREQ=MONTHLY;BYMONTHDAY=-1;BYMONTH=1,3",
+                "2024-01-01",
+                2
+            )
+        );
+
+        let rule = RRule::from_str("freq=monthly;byday=-1fr;count=3").unwrap();
+        assert_eq!("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3", rule.to_string());
+        assert_eq!(
+            "FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20240329",
+            rule.without_count(date("2024-01-01")).to_string()
+        );
+        assert_eq!(
+            vec![date("2024-01-26"), date("2024-03-29")],
+            Occurrences::new(
+                date("2024-01-01"),
+                Some(rule),
+                vec![date("2024-02-23")],
+                date("2024-01-01")
+            )
+            .collect::<Vec<_>>()
+        );
+
+        assert!(RRule::from_str("INTERVAL=2").is_err());
+        assert!(RRule::from_str("FREQ=HOURLY").is_err());
+        assert!(RRule::from_str("FREQ=DAILY;COUNT=2;UNTIL=20240101").is_err());
+        assert!(next(
+            "2024-01-01",
+            "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30",
+            "2024-01-01",
+            1
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_recurring_types() {
+        let start = date("2024-01-30");
+        assert_eq!(
+            "FREQ=MONTHLY;BYMONTHDAY=28,29,30;BYSETPOS=-1",
+            RecurringType::Monthly.rule(start).unwrap().to_string()
+        );
+        assert_eq!(
+            "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=28,29;BYSETPOS=-1",
+            RecurringType::Yearly
+                .rule(date("2024-02-29"))
+                .unwrap()
+                .to_string()
+        );
+        for recurring in ["onetime", "daily", "fortnightly", "quarterly", "yearly"] {
+            let rule = parse(recurring, start).unwrap();
+            assert_eq!(recurring, describe(rule.as_ref(), start).to_lowercase());
+        }
+        let rule = parse("FREQ=WEEKLY;INTERVAL=3", start).unwrap();
+        assert_eq!("FREQ=WEEKLY;INTERVAL=3", describe(rule.as_ref(), start));
+    }
+}
@@ -1,16 +1,24 @@
 use chrono::prelude::*;
+use chrono_tz::Tz;
 use rusqlite::params;
+use serde::Serialize;
 use std::{convert::AsRef, fmt, str::FromStr};
-use strum_macros::{AsRefStr, EnumString};
+use strum_macros::{AsRefStr, EnumString, IntoStaticStr};
 
 use crate::db::db_interface::DbOperationsError;
+use crate::db::entity_kind::EntityKind;
+use crate::db::journal::{self, Operation};
+use crate::db::loader::{self, LoadContext, Relations};
+use crate::db::transaction::atomic;
 use crate::db_interface::DbOperations;
+use crate::entities::datetime::Moment;
+use crate::entities::occurrence::{self, Due, Occurrence, OccurrenceStatus, ReminderStatus};
 use crate::entities::person::Person;
+use crate::entities::recurrence::{self, Occurrences, RRule};
 use crate::entities::Entities;
-use crate::{
-    CliError, DateParseSnafu, EntitySnafu, NotFoundSnafu, RecordParseSnafu, RecurringTypeParseSnafu,
-};
+use crate::{CliError, DatabaseSnafu, NotFoundSnafu, RecordParseSnafu, RecurringTypeParseSnafu};
 use rusqlite::Connection;
+use snafu::ResultExt;
 
 use super::Entity;
 
@@ -28,14 +36,20 @@ Recurring: {recurring_type}
 Description: {description}
 People: {people}
 ";
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Reminder {
     pub id: u64,
     pub name: String,
     pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub timezone: Option<Tz>,
     pub description: Option<String>,
-    pub recurring: RecurringType,
+    pub recurring: Option<RRule>,
+    pub exdates: Vec<NaiveDate>,
+    pub history: Vec<Occurrence>,
     pub people: Vec<Person>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
 }
 impl Entity for Reminder {
     fn get_id(&self) -> u64 {
@@ -49,93 +63,38 @@ impl Reminder {
         name: String,
         date: NaiveDate,
         description: Option<String>,
-        recurring: RecurringType,
+        recurring: Option<RRule>,
         people: Vec<Person>,
     ) -> Reminder {
         Reminder {
             id,
             name,
             date,
+            time: None,
+            timezone: None,
             description,
             recurring,
+            exdates: vec![],
+            history: vec![],
             people,
+            created_at: None,
+            updated_at: None,
         }
     }
 
-    pub fn build_from_sql(
-        conn: &Connection,
-        id: Result<u64, rusqlite::Error>,
-        name: Result<String, rusqlite::Error>,
-        date: Result<String, rusqlite::Error>,
-        description: Result<Option<String>, rusqlite::Error>,
-        recurring_type_id: Result<u64, rusqlite::Error>,
-    ) -> Result<Reminder, DbOperationsError> {
-        let id = match id {
-            Ok(reminder_id) => reminder_id,
-            Err(e) => {
-                return Err(DbOperationsError::RecordError {
-                    sqlite_error: Some(e),
-                    strum_error: None,
-                })
-            }
-        };
-        let name: String = match name {
-            Ok(name) => name,
-            Err(e) => {
-                return Err(DbOperationsError::RecordError {
-                    sqlite_error: Some(e),
-                    strum_error: None,
-                })
-            }
-        };
-        let description: String = match description {
-            Ok(description) => match description {
-                Some(description) => description,
-                None => "".to_string(),
-            },
-            Err(e) => {
-                return Err(DbOperationsError::RecordError {
-                    sqlite_error: Some(e),
-                    strum_error: None,
-                })
-            }
-        };
-        let recurring_type_id = match recurring_type_id {
-            Ok(recurring_type_id) => recurring_type_id,
-            Err(e) => {
-                return Err(DbOperationsError::RecordError {
-                    sqlite_error: Some(e),
-                    strum_error: None,
-                })
-            }
-        };
-        let people = crate::db_helpers::people::get_by_reminder(conn, id)?;
-        let recurring_type = match RecurringType::get_by_id(conn, recurring_type_id) {
-            Ok(recurring_type) => match recurring_type {
-                Some(recurring_type) => recurring_type,
-                None => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: None,
-                        strum_error: None,
-                    })
-                }
-            },
-            Err(e) => return Err(e),
-        };
-        let date = crate::helpers::parse_from_str_ymd(date.unwrap_or_default().as_str())
-            .unwrap_or_default();
-        Ok(Reminder {
-            id,
-            name,
-            date,
-            description: Some(description),
-            recurring: recurring_type,
-            people,
-        })
+    pub fn moment(&self) -> Moment {
+        Moment::new(self.date, self.time, self.timezone)
+    }
+
+    pub fn set_moment(&mut self, moment: Moment) {
+        self.date = moment.date;
+        self.time = moment.time;
+        self.timezone = moment.timezone;
     }
 
     pub fn get(
         conn: &Connection,
+        load: &mut LoadContext,
         name: Option<String>,
         person: Option<String>,
         description: Option<String>,
@@ -143,29 +102,141 @@ impl Reminder {
         let mut reminders: Vec<Reminder> = vec![];
 
         if let Some(name) = name {
-            reminders = crate::db::db_helpers::reminders::get_by_name(conn, &name, person.clone())?;
+            reminders =
+                crate::db::db_helpers::reminders::get_by_name(conn, load, &name, person.clone())?;
             return Ok(reminders);
         }
         if let Some(person) = person {
-            reminders = crate::db::db_helpers::reminders::get_by_person(conn, person.clone())?;
+            reminders =
+                crate::db::db_helpers::reminders::get_by_person(conn, load, person.clone())?;
             return Ok(reminders);
         }
 
         if let Some(description) = description {
-            reminders = crate::db::db_helpers::reminders::get_by_description(conn, description)?;
+            reminders =
+                crate::db::db_helpers::reminders::get_by_description(conn, load, description)?;
         }
         Ok(reminders)
     }
 
+    // The dates the reminder is due on, from `from` onwards
+    pub fn occurrences(&self, from: NaiveDate) -> Occurrences {
+        Occurrences::new(
+            self.date,
+            self.recurring.clone(),
+            self.exdates.clone(),
+            from,
+        )
+    }
+
+    // The recurring type or RRULE of the reminder
+    pub fn recurrence(&self) -> String {
+        recurrence::describe(self.recurring.as_ref(), self.date)
+    }
+
+    // The `recurring_types` entry stored alongside the rule
+    fn recurring_type(&self) -> &'static str {
+        match RecurringType::matching(self.recurring.as_ref(), self.date) {
+            Some(recurring) => recurring.into(),
+            None => "Custom",
+        }
+    }
+
+    pub fn next_occurrences(&self, from: NaiveDate, count: usize) -> Vec<NaiveDate> {
+        self.occurrences(from).take(count).collect()
+    }
+
+    pub fn next_occurrence(&self, from: NaiveDate) -> Option<NaiveDate> {
+        self.occurrences(from).next()
+    }
+
+    pub fn is_occurrence(&self, date: NaiveDate) -> bool {
+        self.next_occurrence(date) == Some(date)
+    }
+
+    // The occurrence the reminder is waiting on: the latest one up to `today`
+    // after those that were done or skipped, or else the next one. Missed
+    // occurrences roll up into the latest. None once there are no more.
+    pub fn due(&self, today: NaiveDate) -> Option<Due> {
+        let from = match self
+            .history
+            .iter()
+            .filter(|o| o.is_handled())
+            .map(|o| o.date)
+            .max()
+        {
+            Some(handled) => handled.succ_opt()?,
+            None => self.date,
+        };
+        let mut occurrences = self.occurrences(from);
+        let mut date = occurrences.next()?;
+        if date <= today {
+            if let Some(latest) = occurrences.take_while(|d| *d <= today).last() {
+                date = latest;
+            }
+        }
+        let snoozed_until = self
+            .history
+            .iter()
+            .find(|o| o.date == date && o.status == OccurrenceStatus::Snoozed)
+            .and_then(|o| o.snoozed_until);
+        Some(Due::new(date, snoozed_until, today))
+    }
+
+    pub fn status(&self, today: NaiveDate) -> ReminderStatus {
+        self.due(today)
+            .map_or(ReminderStatus::Done, |due| due.status)
+    }
+
+    pub fn complete(&self, conn: &Connection, date: NaiveDate) -> Result<(), DbOperationsError> {
+        occurrence::record(
+            conn,
+            Operation::Complete,
+            self.id,
+            date,
+            OccurrenceStatus::Done,
+            None,
+        )
+    }
+
+    pub fn skip(&self, conn: &Connection, date: NaiveDate) -> Result<(), DbOperationsError> {
+        occurrence::record(
+            conn,
+            Operation::Skip,
+            self.id,
+            date,
+            OccurrenceStatus::Skipped,
+            None,
+        )
+    }
+
+    // Postpones the occurrence on `date` until `until`
+    pub fn snooze(
+        &self,
+        conn: &Connection,
+        date: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<(), DbOperationsError> {
+        occurrence::record(
+            conn,
+            Operation::Snooze,
+            self.id,
+            date,
+            OccurrenceStatus::Snoozed,
+            Some(until),
+        )
+    }
+
     pub fn get_all_filtered(
         conn: &Connection,
-        include_past: bool,
+        load: &mut LoadContext,
+        include_done: bool,
     ) -> Result<Vec<Reminder>, DbOperationsError> {
-        let reminders = Reminder::get_all(conn)?;
+        let reminders = Reminder::get_all(conn, load)?;
+        let today = chrono::Local::now().date_naive();
         let filtered_reminders: Vec<Reminder> = reminders
-            .iter()
-            .map(|r| *r.to_owned())
-            .filter(|r| include_past || r.date > chrono::Local::now().date_naive())
+            .into_iter()
+            .filter(|r| include_done || r.status(today) != ReminderStatus::Done)
             .collect::<Vec<_>>();
 
         Ok(filtered_reminders)
@@ -175,7 +246,7 @@ impl Reminder {
         &mut self,
         conn: &Connection,
         name: Option<String>,
-        date: Option<String>,
+        date: Option<Moment>,
         description: Option<String>,
         recurring: Option<String>,
         people: Vec<String>,
@@ -184,29 +255,8 @@ impl Reminder {
             self.name = name;
         }
 
-        if let Some(date) = date {
-            let date_obj: Option<NaiveDate>;
-            match crate::helpers::parse_from_str_ymd(&date) {
-                Ok(date) => date_obj = Some(date),
-                Err(_) => match crate::helpers::parse_from_str_md(&date) {
-                    Ok(date) => date_obj = Some(date),
-                    Err(_) => {
-                        return DateParseSnafu {
-                            date: date.to_string(),
-                        }
-                        .fail()
-                    }
-                },
-            }
-            self.date = match date_obj {
-                Some(date) => date,
-                None => {
-                    return DateParseSnafu {
-                        date: date.to_string(),
-                    }
-                    .fail()
-                }
-            }
+        if let Some(moment) = date {
+            self.set_moment(moment);
         }
 
         if let Some(description) = description {
@@ -215,31 +265,20 @@ impl Reminder {
             self.description = None;
         }
 
-        let recurring_type = match recurring {
-            Some(recurring_type_str) => match recurring_type_str.as_str() {
-                "daily" => Some(RecurringType::Daily),
-                "weekly" => Some(RecurringType::Weekly),
-                "fortnightly" => Some(RecurringType::Fortnightly),
-                "monthly" => Some(RecurringType::Monthly),
-                "quarterly" => Some(RecurringType::Quarterly),
-                "biannual" => Some(RecurringType::Biannual),
-                "yearly" => Some(RecurringType::Yearly),
-                "onetime" => Some(RecurringType::OneTime),
-                _ => {
-                    return RecurringTypeParseSnafu {
-                        recurring_type: recurring_type_str.to_string(),
-                    }
-                    .fail()
-                }
-            },
-            None => Some(RecurringType::OneTime),
+        self.recurring = match recurring {
+            Some(recurring) => {
+                recurrence::parse(&recurring, self.date).context(RecurringTypeParseSnafu {
+                    recurring_type: recurring.clone(),
+                })?
+            }
+            None => None,
         };
 
-        if let Some(recurring_type) = recurring_type {
-            self.recurring = recurring_type;
-        }
-
-        let people = match crate::db::db_helpers::people::get_by_names(conn, people) {
+        let people = match crate::db::db_helpers::people::get_by_names(
+            conn,
+            &mut LoadContext::new(true),
+            people,
+        ) {
             Ok(people) => people,
             Err(_) => {
                 return RecordParseSnafu {
@@ -300,27 +339,28 @@ impl Reminder {
         })
     }
 
-    pub fn get_by_ids(conn: &Connection, ids: Vec<u64>) -> Result<Vec<Reminder>, CliError> {
+    pub fn get_by_ids(
+        conn: &Connection,
+        load: &mut LoadContext,
+        ids: Vec<u64>,
+    ) -> Result<Vec<Reminder>, CliError> {
         let mut reminders: Vec<Reminder> = Vec::new();
         for id in ids {
-            match Reminder::get_by_id(conn, id) {
-                Ok(entity) => match entity {
-                    Some(Entities::Reminder(reminder)) => reminders.push(reminder),
-                    _ => {
-                        return EntitySnafu {
-                            entity: "Reminder",
-                            message: format!("Error fetching reminder: {:#?}", entity),
-                        }
-                        .fail()
-                    }
-                },
-                Err(_) => {
+            match Reminder::get_by_id(conn, load, id) {
+                Ok(Some(Entities::Reminder(reminder))) => reminders.push(reminder),
+                Ok(_) => {
                     return NotFoundSnafu {
                         entity: "Reminder",
                         id,
                     }
                     .fail()
                 }
+                Err(e) => {
+                    return Err(e).context(DatabaseSnafu {
+                        entity: "Reminder",
+                        action: "fetching",
+                    })
+                }
             }
         }
         Ok(reminders)
@@ -329,359 +369,289 @@ impl Reminder {
 
 impl DbOperations for Reminder {
     fn add(&self, conn: &Connection) -> Result<&Reminder, DbOperationsError> {
-        let mut stmt = match conn.prepare("SELECT id FROM reminders WHERE name = ? AND deleted = 0")
-        {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        let mut rows = match stmt.query(params![self.name]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        let mut ids: Vec<u32> = Vec::new();
-        loop {
-            match rows.next() {
-                Ok(row) => match row {
-                    Some(row) => match row.get(0) {
-                        Ok(row) => ids.push(row),
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Add, EntityKind::Reminder, self.id)?;
+            let mut stmt =
+                match conn.prepare("SELECT id FROM reminders WHERE name = ? AND deleted = 0") {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
+            let mut rows = match stmt.query(params![self.name]) {
+                Ok(rows) => rows,
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+            };
+            let mut ids: Vec<u32> = Vec::new();
+            loop {
+                match rows.next() {
+                    Ok(row) => match row {
+                        Some(row) => match row.get(0) {
+                            Ok(row) => ids.push(row),
+                            Err(e) => {
+                                return Err(DbOperationsError::RecordError {
+                                    sqlite_error: Some(e),
+                                    strum_error: None,
+                                })
+                            }
+                        },
+                        None => break,
                     },
-                    None => break,
-                },
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
+                    Err(e) => {
+                        return Err(DbOperationsError::RecordError {
+                            sqlite_error: Some(e),
+                            strum_error: None,
+                        })
+                    }
                 }
             }
-        }
 
-        if !ids.is_empty() {
-            return Err(DbOperationsError::DuplicateEntry);
-        }
+            if !ids.is_empty() {
+                return Err(DbOperationsError::DuplicateEntry {
+                    entity: EntityKind::Reminder,
+                    name: self.name.clone(),
+                });
+            }
 
-        let recurring_str = &self.recurring.as_ref();
+            let recurring_str = self.recurring_type();
 
-        let date_str = self.date.to_string();
+            let date_str = self.moment().to_rfc3339();
 
-        let mut stmt = match conn.prepare("SELECT id FROM recurring_types WHERE type = ?") {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        let mut rows = match stmt.query(params![recurring_str]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        let mut types: Vec<u32> = Vec::new();
-        loop {
-            match rows.next() {
-                Ok(row) => match row {
-                    Some(row) => match row.get(0) {
-                        Ok(row) => types.push(row),
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
+            let mut stmt = match conn.prepare("SELECT id FROM recurring_types WHERE type = ?") {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+            };
+            let mut rows = match stmt.query(params![recurring_str]) {
+                Ok(rows) => rows,
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+            };
+            let mut types: Vec<u32> = Vec::new();
+            loop {
+                match rows.next() {
+                    Ok(row) => match row {
+                        Some(row) => match row.get(0) {
+                            Ok(row) => types.push(row),
+                            Err(e) => {
+                                return Err(DbOperationsError::RecordError {
+                                    sqlite_error: Some(e),
+                                    strum_error: None,
+                                })
+                            }
+                        },
+                        None => break,
                     },
-                    None => break,
-                },
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
+                    Err(e) => {
+                        return Err(DbOperationsError::RecordError {
+                            sqlite_error: Some(e),
+                            strum_error: None,
+                        })
+                    }
                 }
             }
-        }
 
-        let mut stmt = match conn.prepare(
-            "INSERT INTO
-                reminders (name, date, recurring, description, deleted)
-                VALUES (?1, ?2, ?3, ?4, FALSE)
-            ",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        match stmt.execute(params![self.name, date_str, types[0], self.description]) {
-            Ok(updated) => {
-                println!("[DEBUG][reminders][insert] {} rows were updated", updated);
-            }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
-
-        let id = conn.last_insert_rowid();
-
-        for person in &self.people {
             let mut stmt = match conn.prepare(
-                "INSERT INTO people_reminders (
-                    person_id,
-                    reminder_id,
-                    deleted
-                )
-                    VALUES (?1, ?2, FALSE)",
+                "INSERT INTO
+                    reminders (
+                        name, date, timezone, recurring, rrule, exdates, description, deleted,
+                        created_at, updated_at
+                    )
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, FALSE, datetime('now'), datetime('now'))
+                ",
             ) {
                 Ok(stmt) => stmt,
                 Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
-            match stmt.execute(params![person.id, id]) {
+            match stmt.execute(params![
+                self.name,
+                date_str,
+                self.timezone.map(|timezone| timezone.name()),
+                types[0],
+                self.recurring.as_ref().map(|rule| rule.to_string()),
+                recurrence::format_dates(&self.exdates),
+                self.description
+            ]) {
                 Ok(updated) => {
-                    println!(
-                        "[DEBUG][people_reminders][insert] {} rows were updated",
-                        updated
-                    );
+                    println!("[DEBUG][reminders][insert] {} rows were updated", updated);
                 }
-                Err(_) => return Err(DbOperationsError::QueryError),
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
-        }
 
-        Ok(self)
-    }
+            let id = conn.last_insert_rowid();
 
-    fn remove(&self, conn: &Connection) -> Result<&Self, DbOperationsError> {
-        let mut stmt = match conn.prepare(
-            "UPDATE
-                    reminders
-                SET
-                    deleted = TRUE
-                WHERE
-                    id = ?1",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        match stmt.execute([self.id]) {
-            Ok(updated) => {
-                println!("[DEBUG][reminders][update] {} rows were updated", updated);
+            // Copied reminders keep what was done about them
+            for occurrence in &self.history {
+                match conn.execute(
+                    "INSERT INTO reminder_occurrences (
+                        reminder_id, date, status, snoozed_until, created_at
+                    )
+                        VALUES (?1, ?2, ?3, ?4, datetime('now'))",
+                    params![
+                        id,
+                        occurrence.date.to_string(),
+                        occurrence.status.as_ref(),
+                        occurrence.snoozed_until.map(|until| until.to_string())
+                    ],
+                ) {
+                    Ok(_) => (),
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+                }
             }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
-
-        Ok(self)
-    }
-
-    fn save(&self, conn: &Connection) -> Result<&Reminder, DbOperationsError> {
-        let recurring_str = &self.recurring.as_ref();
 
-        let date_str = self.date.to_string();
-
-        let mut stmt = match conn.prepare("SELECT id FROM recurring_types WHERE type = ?") {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        let mut rows = match stmt.query(params![recurring_str]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        let mut types: Vec<u32> = Vec::new();
-        loop {
-            match rows.next() {
-                Ok(row) => match row {
-                    Some(row) => match row.get(0) {
-                        Ok(row) => types.push(row),
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    },
-                    None => break,
-                },
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
+            for person in &self.people {
+                let mut stmt = match conn.prepare(
+                    "INSERT INTO people_reminders (
+                        person_id,
+                        reminder_id,
+                        deleted
+                    )
+                        VALUES (?1, ?2, FALSE)
+                        ON CONFLICT (person_id, reminder_id) DO UPDATE SET deleted = FALSE",
+                ) {
+                    Ok(stmt) => stmt,
+                    Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+                };
+                match stmt.execute(params![person.id, id]) {
+                    Ok(updated) => {
+                        println!(
+                            "[DEBUG][people_reminders][insert] {} rows were updated",
+                            updated
+                        );
+                    }
+                    Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
                 }
             }
-        }
 
-        let mut stmt = match conn.prepare(
-            "UPDATE
-                reminders
-            SET
-                name = ?1,
-                date = ?2,
-                recurring = ?3,
-                description = ?4
-            WHERE
-                id = ?5
-            ",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        match stmt.execute(params![
-            self.name,
-            date_str,
-            types[0],
-            self.description,
-            self.id
-        ]) {
-            Ok(updated) => {
-                println!("[DEBUG][reminders][update] {} rows were updated", updated);
+            journal.finish()?;
+            Ok(self)
+        })
+    }
+
+    fn remove(&self, conn: &Connection) -> Result<&Self, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Remove, EntityKind::Reminder, self.id)?;
+            let mut stmt = match conn.prepare(
+                "UPDATE
+                        reminders
+                    SET
+                        deleted = TRUE
+                    WHERE
+                        id = ?1",
+            ) {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+            };
+            match stmt.execute([self.id]) {
+                Ok(updated) => {
+                    println!("[DEBUG][reminders][update] {} rows were updated", updated);
+                }
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
             }
-            Err(_) => return Err(DbOperationsError::QueryError),
-        }
 
-        // TODO allow for changing people
-        Ok(self)
+            journal.finish()?;
+            Ok(self)
+        })
     }
 
-    fn get_by_id(conn: &Connection, id: u64) -> Result<Option<Entities>, DbOperationsError> {
-        let mut stmt = match conn.prepare("SELECT * FROM reminders WHERE id = ?1 AND deleted = 0") {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        let mut rows = match stmt.query(params![id]) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-        match rows.next() {
-            Ok(row) => match row {
-                Some(row) => {
-                    let reminder_id = match row.get(0) {
-                        Ok(reminder_id) => reminder_id,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let name: String = match row.get(1) {
-                        Ok(name) => name,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let people = crate::db_helpers::people::get_by_reminder(conn, reminder_id)?;
-                    let description: Option<String> = match row.get(3) {
-                        Ok(description) => description,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let recurring_type_id = match row.get(4) {
-                        Ok(recurring_type_id) => recurring_type_id,
-                        Err(e) => {
-                            return Err(DbOperationsError::RecordError {
-                                sqlite_error: Some(e),
-                                strum_error: None,
-                            })
-                        }
-                    };
-                    let recurring_type = match RecurringType::get_by_id(conn, recurring_type_id) {
-                        Ok(recurring_type) => match recurring_type {
-                            Some(recurring_type) => recurring_type,
-                            None => {
+    fn save(&self, conn: &Connection) -> Result<&Reminder, DbOperationsError> {
+        atomic(conn, || {
+            let journal = journal::begin(conn, Operation::Save, EntityKind::Reminder, self.id)?;
+            let recurring_str = self.recurring_type();
+
+            let date_str = self.moment().to_rfc3339();
+
+            let mut stmt = match conn.prepare("SELECT id FROM recurring_types WHERE type = ?") {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
+            };
+            let mut rows = match stmt.query(params![recurring_str]) {
+                Ok(rows) => rows,
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+            };
+            let mut types: Vec<u32> = Vec::new();
+            loop {
+                match rows.next() {
+                    Ok(row) => match row {
+                        Some(row) => match row.get(0) {
+                            Ok(row) => types.push(row),
+                            Err(e) => {
                                 return Err(DbOperationsError::RecordError {
-                                    sqlite_error: None,
+                                    sqlite_error: Some(e),
                                     strum_error: None,
                                 })
                             }
                         },
-                        Err(e) => return Err(e),
-                    };
-                    Ok(Some(Entities::Reminder(Reminder {
-                        id: reminder_id,
-                        name,
-                        date: crate::helpers::parse_from_str_ymd(
-                            row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                        )
-                        .unwrap_or_default(),
-                        description,
-                        recurring: recurring_type,
-                        people,
-                    })))
-                }
-                None => Ok(None),
-            },
-            Err(e) => Err(DbOperationsError::RecordError {
-                sqlite_error: Some(e),
-                strum_error: None,
-            }),
-        }
-    }
-    fn get_all(conn: &Connection) -> Result<Vec<Box<Self>>, DbOperationsError> {
-        let sql = "SELECT * FROM reminders WHERE deleted = 0";
-        let mut stmt = match conn.prepare(sql) {
-            Ok(stmt) => stmt,
-            Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
-        };
-        let rows = match stmt.query_map([], |row| {
-            let reminder_id = row.get(0)?;
-            let people = match crate::db_helpers::people::get_by_reminder(conn, reminder_id) {
-                Ok(people) => people,
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
+                        None => break,
+                    },
+                    Err(e) => {
+                        return Err(DbOperationsError::RecordError {
+                            sqlite_error: Some(e),
+                            strum_error: None,
+                        })
+                    }
                 }
+            }
+
+            let mut stmt = match conn.prepare(
+                "UPDATE
+                    reminders
+                SET
+                    name = ?1,
+                    date = ?2,
+                    timezone = ?3,
+                    recurring = ?4,
+                    rrule = ?5,
+                    exdates = ?6,
+                    description = ?7,
+                    updated_at = datetime('now')
+                WHERE
+                    id = ?8
+                ",
+            ) {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(DbOperationsError::InvalidStatement { sqlite_error: e }),
             };
-            let recurring_type_id: u64 = row.get(4)?;
-            let recurring_type = match RecurringType::get_by_id(conn, recurring_type_id) {
-                Ok(recurring_type) => match recurring_type {
-                    Some(recurring_type) => recurring_type,
-                    None => panic!("Recurring Type cannot be None"),
-                },
-                Err(e) => {
-                    let sqlite_error = match e {
-                        DbOperationsError::InvalidStatement { sqlite_error } => sqlite_error,
-                        other => panic!("Unexpected error type: {:#?}", other),
-                    };
-                    return Err(sqlite_error);
+            match stmt.execute(params![
+                self.name,
+                date_str,
+                self.timezone.map(|timezone| timezone.name()),
+                types[0],
+                self.recurring.as_ref().map(|rule| rule.to_string()),
+                recurrence::format_dates(&self.exdates),
+                self.description,
+                self.id
+            ]) {
+                Ok(updated) => {
+                    println!("[DEBUG][reminders][update] {} rows were updated", updated);
                 }
-            };
-            Ok(Reminder {
-                id: reminder_id,
-                name: row.get(1)?,
-                date: crate::helpers::parse_from_str_ymd(
-                    row.get::<usize, String>(2).unwrap_or_default().as_str(),
-                )
-                .unwrap_or_default(),
-                description: row.get(3)?,
-                recurring: recurring_type,
-                people,
-            })
-        }) {
-            Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
-        };
-
-        let mut reminders = Vec::new();
+                Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
+            }
 
-        for reminder in rows.into_iter() {
-            let reminder = match reminder {
-                Ok(reminder) => reminder,
-                Err(e) => {
-                    return Err(DbOperationsError::RecordError {
-                        sqlite_error: Some(e),
-                        strum_error: None,
-                    })
-                }
-            };
-            reminders.push(Box::new(reminder));
-        }
+            journal.finish()?;
+            // TODO allow for changing people
+            Ok(self)
+        })
+    }
 
-        Ok(reminders)
+    fn get_by_id(
+        conn: &Connection,
+        load: &mut LoadContext,
+        id: u64,
+    ) -> Result<Option<Entities>, DbOperationsError> {
+        let mut reminders = loader::reminders(
+            conn,
+            load,
+            "SELECT * FROM reminders WHERE id = ?1 AND deleted = 0",
+            params![id],
+            Relations::ALL,
+        )?;
+        Ok(reminders.pop().map(Entities::Reminder))
+    }
+    fn get_all(conn: &Connection, load: &mut LoadContext) -> Result<Vec<Self>, DbOperationsError> {
+        loader::reminders(
+            conn,
+            load,
+            "SELECT * FROM reminders WHERE deleted = 0",
+            [],
+            Relations::ALL,
+        )
     }
 }
 
@@ -691,7 +661,11 @@ impl fmt::Display for Reminder {
             Some(description) => description.as_ref(),
             None => "",
         };
-        let recurring_type_str = &self.recurring.as_ref();
+        let recurring_type_str = self.recurrence();
+        let due_str = match self.due(Local::now().date_naive()) {
+            Some(due) => format!("{} ({})", due.date, due.status.as_ref()),
+            None => String::from(ReminderStatus::Done.as_ref()),
+        };
         let mut people_str = String::new();
         for person in self.people.iter() {
             people_str.push_str("\n\t");
@@ -699,18 +673,20 @@ impl fmt::Display for Reminder {
         }
         write!(
             f,
-            "reminder id: {}\nname: {}\ndate: {}\ndescription: {}\nrecurring type: {}\npeople:{}\n",
+            "reminder id: {}\nname: {}\ndate: {}\ndescription: {}\nrecurring type: {}\ndue: {}\npeople:{}\n",
             &self.id,
             &self.name,
-            &self.date.to_string(),
+            &self.moment().to_string(),
             description_str,
             recurring_type_str,
+            due_str,
             people_str
         )
     }
 }
 
-#[derive(Debug, AsRefStr, EnumString, Clone, PartialEq)]
+#[derive(Debug, AsRefStr, IntoStaticStr, EnumString, Clone, PartialEq, Serialize)]
+#[strum(ascii_case_insensitive)]
 pub enum RecurringType {
     OneTime,
     Daily,
@@ -733,7 +709,7 @@ impl RecurringType {
         };
         let mut rows = match stmt.query(params![id]) {
             Ok(rows) => rows,
-            Err(_) => return Err(DbOperationsError::QueryError),
+            Err(e) => return Err(DbOperationsError::QueryError { sqlite_error: e }),
         };
 
         match rows.next() {
@@ -780,7 +756,7 @@ mod tests {
         let name = String::from("I forgot");
         let date = crate::helpers::parse_from_str_ymd("2022-01-01").unwrap();
         let description = String::from("I don't remember");
-        let recurring = RecurringType::Daily;
+        let recurring = RecurringType::Daily.rule(date);
         let people: Vec<Person> = vec![];
 
         let reminder = Reminder::new(
@@ -797,11 +773,66 @@ mod tests {
                 id,
                 name,
                 date,
+                time: None,
+                timezone: None,
                 description: Some(description),
                 recurring,
+                exdates: vec![],
+                history: vec![],
                 people,
+                created_at: None,
+                updated_at: None,
             },
             reminder
         );
     }
+
+    #[test]
+    fn test_due() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let occurrence = |d, status, snoozed_until| Occurrence {
+            date: d,
+            status,
+            snoozed_until,
+            created_at: None,
+        };
+        let mut reminder = Reminder::new(
+            1,
+            String::from("Rent"),
+            date(1, 1),
+            None,
+            RecurringType::Monthly.rule(date(1, 1)),
+            vec![],
+        );
+
+        // Missed occurrences roll up into the latest
+        let due = reminder.due(date(3, 15)).unwrap();
+        assert_eq!(
+            (date(3, 1), ReminderStatus::Overdue),
+            (due.date, due.status)
+        );
+
+        reminder.history = vec![occurrence(date(3, 1), OccurrenceStatus::Done, None)];
+        let due = reminder.due(date(3, 15)).unwrap();
+        assert_eq!(
+            (date(4, 1), ReminderStatus::Pending),
+            (due.date, due.status)
+        );
+
+        reminder.history.push(occurrence(
+            date(4, 1),
+            OccurrenceStatus::Snoozed,
+            Some(date(4, 5)),
+        ));
+        let due = reminder.due(date(4, 2)).unwrap();
+        assert_eq!(
+            (date(4, 1), date(4, 5), ReminderStatus::Snoozed),
+            (due.occurrence, due.date, due.status)
+        );
+
+        let mut once = Reminder::new(2, String::from("Call"), date(2, 1), None, None, vec![]);
+        assert_eq!(ReminderStatus::Overdue, once.status(date(3, 15)));
+        once.history = vec![occurrence(date(2, 1), OccurrenceStatus::Skipped, None)];
+        assert_eq!(ReminderStatus::Done, once.status(date(3, 15)));
+    }
 }
@@ -0,0 +1,301 @@
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::db::loader::{self, LoadContext, Relations};
+use crate::db_interface::DbOperationsError;
+use crate::entities::activity::{Activity, ActivityType};
+use crate::entities::note::Note;
+use crate::entities::occurrence::Occurrence;
+use crate::entities::person::{ContactInfo, Person};
+use crate::entities::recurrence::RRule;
+use crate::entities::reminder::Reminder;
+
+use super::Entity;
+
+// Shallow entities refer to their relations by id instead of holding them,
+// so loading one doesn't pull in the rest of the graph. Relations are loaded
+// on demand with the `load_*` methods, as deep as `Relations` asks for.
+
+// A person with its contact info, which it owns
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShallowPerson {
+    pub id: u64,
+    pub name: String,
+    pub birthday: Option<NaiveDate>,
+    pub contact_info: Vec<ContactInfo>,
+    pub activity_ids: Vec<u64>,
+    pub reminder_ids: Vec<u64>,
+    pub note_ids: Vec<u64>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShallowActivity {
+    pub id: u64,
+    pub name: String,
+    pub activity_type: ActivityType,
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub timezone: Option<Tz>,
+    pub content: String,
+    pub person_ids: Vec<u64>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShallowReminder {
+    pub id: u64,
+    pub name: String,
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub timezone: Option<Tz>,
+    pub description: Option<String>,
+    pub recurring: Option<RRule>,
+    pub exdates: Vec<NaiveDate>,
+    pub history: Vec<Occurrence>,
+    pub person_ids: Vec<u64>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShallowNote {
+    pub id: u64,
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub timezone: Option<Tz>,
+    pub content: String,
+    pub person_ids: Vec<u64>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl Entity for ShallowPerson {
+    fn get_id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Entity for ShallowActivity {
+    fn get_id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Entity for ShallowReminder {
+    fn get_id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Entity for ShallowNote {
+    fn get_id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl ShallowPerson {
+    pub fn get_by_id(
+        conn: &Connection,
+        load: &mut LoadContext,
+        id: u64,
+    ) -> Result<Option<ShallowPerson>, DbOperationsError> {
+        Ok(loader::shallow_people(
+            conn,
+            load,
+            "SELECT * FROM people WHERE id = ?1 AND deleted = 0",
+            params![id],
+        )?
+        .pop())
+    }
+
+    pub fn get_all(
+        conn: &Connection,
+        load: &mut LoadContext,
+    ) -> Result<Vec<ShallowPerson>, DbOperationsError> {
+        loader::shallow_people(conn, load, "SELECT * FROM people WHERE deleted = 0", [])
+    }
+
+    pub fn load_activities(
+        &self,
+        conn: &Connection,
+        load: &mut LoadContext,
+        relations: Relations,
+    ) -> Result<Vec<Activity>, DbOperationsError> {
+        loader::activities_by_id(conn, load, &self.activity_ids, relations)
+    }
+
+    pub fn load_reminders(
+        &self,
+        conn: &Connection,
+        load: &mut LoadContext,
+        relations: Relations,
+    ) -> Result<Vec<Reminder>, DbOperationsError> {
+        loader::reminders_by_id(conn, load, &self.reminder_ids, relations)
+    }
+
+    pub fn load_notes(
+        &self,
+        conn: &Connection,
+        load: &mut LoadContext,
+        relations: Relations,
+    ) -> Result<Vec<Note>, DbOperationsError> {
+        loader::notes_by_id(conn, load, &self.note_ids, relations)
+    }
+}
+
+impl ShallowActivity {
+    pub fn get_by_id(
+        conn: &Connection,
+        load: &mut LoadContext,
+        id: u64,
+    ) -> Result<Option<ShallowActivity>, DbOperationsError> {
+        Ok(loader::shallow_activities(
+            conn,
+            load,
+            "SELECT * FROM activities WHERE id = ?1 AND deleted = 0",
+            params![id],
+        )?
+        .pop())
+    }
+
+    pub fn get_all(
+        conn: &Connection,
+        load: &mut LoadContext,
+    ) -> Result<Vec<ShallowActivity>, DbOperationsError> {
+        loader::shallow_activities(conn, load, "SELECT * FROM activities WHERE deleted = 0", [])
+    }
+
+    pub fn load_people(
+        &self,
+        conn: &Connection,
+        load: &mut LoadContext,
+        relations: Relations,
+    ) -> Result<Vec<Person>, DbOperationsError> {
+        loader::people_by_id(conn, load, &self.person_ids, relations)
+    }
+}
+
+impl ShallowReminder {
+    pub fn get_by_id(
+        conn: &Connection,
+        load: &mut LoadContext,
+        id: u64,
+    ) -> Result<Option<ShallowReminder>, DbOperationsError> {
+        Ok(loader::shallow_reminders(
+            conn,
+            load,
+            "SELECT * FROM reminders WHERE id = ?1 AND deleted = 0",
+            params![id],
+        )?
+        .pop())
+    }
+
+    pub fn get_all(
+        conn: &Connection,
+        load: &mut LoadContext,
+    ) -> Result<Vec<ShallowReminder>, DbOperationsError> {
+        loader::shallow_reminders(conn, load, "SELECT * FROM reminders WHERE deleted = 0", [])
+    }
+
+    pub fn load_people(
+        &self,
+        conn: &Connection,
+        load: &mut LoadContext,
+        relations: Relations,
+    ) -> Result<Vec<Person>, DbOperationsError> {
+        loader::people_by_id(conn, load, &self.person_ids, relations)
+    }
+}
+
+impl ShallowNote {
+    pub fn get_by_id(
+        conn: &Connection,
+        load: &mut LoadContext,
+        id: u64,
+    ) -> Result<Option<ShallowNote>, DbOperationsError> {
+        Ok(loader::shallow_notes(
+            conn,
+            load,
+            "SELECT * FROM notes WHERE id = ?1 AND deleted = 0",
+            params![id],
+        )?
+        .pop())
+    }
+
+    pub fn get_all(
+        conn: &Connection,
+        load: &mut LoadContext,
+    ) -> Result<Vec<ShallowNote>, DbOperationsError> {
+        loader::shallow_notes(conn, load, "SELECT * FROM notes WHERE deleted = 0", [])
+    }
+
+    pub fn load_people(
+        &self,
+        conn: &Connection,
+        load: &mut LoadContext,
+        relations: Relations,
+    ) -> Result<Vec<Person>, DbOperationsError> {
+        loader::people_by_id(conn, load, &self.person_ids, relations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shallow_person() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO people (name, birthday, deleted) VALUES ('Zeh', NULL, FALSE);
+            INSERT INTO people (name, birthday, deleted) VALUES ('Ana', NULL, FALSE);
+            INSERT INTO activities (name, type, date, content, deleted)
+                VALUES ('Lunch', 1, '2024-01-02', 'Tacos', FALSE);
+            INSERT INTO activities (name, type, date, content, deleted)
+                VALUES ('Call', 2, '2024-01-03', '', TRUE);
+            INSERT INTO people_activities (person_id, activity_id, deleted) VALUES (1, 1, FALSE);
+            INSERT INTO people_activities (person_id, activity_id, deleted) VALUES (2, 1, FALSE);
+            INSERT INTO people_activities (person_id, activity_id, deleted) VALUES (1, 2, FALSE);
+            INSERT INTO notes (date, content, deleted) VALUES ('2024-01-02', 'Lisbon', FALSE);
+            INSERT INTO people_notes (person_id, note_id, deleted) VALUES (1, 1, FALSE);",
+        )
+        .unwrap();
+
+        let person = ShallowPerson::get_by_id(&conn, &mut LoadContext::default(), 1)
+            .unwrap()
+            .unwrap();
+        // Trashed activities are left out
+        assert_eq!(vec![1], person.activity_ids);
+        assert!(person.reminder_ids.is_empty());
+        assert_eq!(vec![1], person.note_ids);
+
+        let activities = person
+            .load_activities(&conn, &mut LoadContext::default(), Relations::NONE)
+            .unwrap();
+        assert_eq!("Lunch", activities[0].name);
+        assert!(activities[0].people.is_empty());
+        let activities = person
+            .load_activities(&conn, &mut LoadContext::default(), Relations::ALL)
+            .unwrap();
+        assert_eq!(
+            vec!["Zeh", "Ana"],
+            activities[0]
+                .people
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, activities[0].people[0].notes.len());
+
+        let activity = ShallowActivity::get_by_id(&conn, &mut LoadContext::default(), 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![1, 2], activity.person_ids);
+    }
+}
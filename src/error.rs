@@ -0,0 +1,194 @@
+use std::error::Error as StdError;
+
+use snafu::prelude::*;
+use strum_macros::AsRefStr;
+
+use crate::db::entity_kind::EntityKind;
+use crate::db_interface::DbOperationsError;
+use crate::entities::event::EventError;
+use crate::notify::NotifyError;
+use crate::CliError;
+
+// What prm was doing when the database failed. Operations without an entity
+// name what they work on.
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr)]
+pub enum Operation {
+    #[strum(serialize = "open the database")]
+    Open,
+    #[strum(serialize = "initialise the database")]
+    Initialise,
+    #[strum(serialize = "migrate the database")]
+    Migrate,
+    #[strum(serialize = "check the database")]
+    Check,
+    #[strum(serialize = "replay the journal")]
+    Replay,
+    #[strum(serialize = "load the history")]
+    History,
+    #[strum(serialize = "prune the journal")]
+    Prune,
+    #[strum(serialize = "list the trash")]
+    Trash,
+    #[strum(serialize = "purge the trash")]
+    Purge,
+    #[strum(serialize = "search")]
+    Search,
+    #[strum(serialize = "load")]
+    Load,
+    #[strum(serialize = "list")]
+    List,
+    #[strum(serialize = "add")]
+    Add,
+    #[strum(serialize = "save")]
+    Save,
+    #[strum(serialize = "remove")]
+    Remove,
+    #[strum(serialize = "restore")]
+    Restore,
+    #[strum(serialize = "complete")]
+    Complete,
+    #[strum(serialize = "skip")]
+    Skip,
+    #[strum(serialize = "snooze")]
+    Snooze,
+}
+
+// "load person 3", "list people" or "replay the journal"
+fn describe(operation: &Operation, entity: &Option<EntityKind>, id: &Option<u64>) -> String {
+    match (entity, id) {
+        (Some(entity), Some(id)) => format!("{} {} {}", operation.as_ref(), entity.as_ref(), id),
+        (Some(entity), None) => format!("{} {}", operation.as_ref(), entity.table()),
+        (None, _) => operation.as_ref().to_string(),
+    }
+}
+
+// The error of the prm library and binary: a database error with what prm
+// was doing when it happened, a CLI error, an error loading events or one
+// delivering notifications. Display gives a one line summary; `chain` and
+// `report` walk the underlying causes.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum Error {
+    #[snafu(display("Could not {}: {}", describe(operation, entity, id), source))]
+    Db {
+        operation: Operation,
+        entity: Option<EntityKind>,
+        id: Option<u64>,
+        source: DbOperationsError,
+    },
+    #[snafu(context(false), display("{}", source))]
+    Cli { source: CliError },
+    #[snafu(context(false), display("Could not load events: {}", source))]
+    Event { source: EventError },
+    #[snafu(context(false), display("{}", source))]
+    Notify { source: NotifyError },
+    #[cfg(feature = "async")]
+    #[snafu(display("Database task failed: {}", source))]
+    Task { source: tokio::task::JoinError },
+}
+
+impl Error {
+    // The error followed by its causes. Causes already included in the
+    // message before them are left out.
+    pub fn chain(&self) -> Vec<String> {
+        let mut messages = vec![self.to_string()];
+        let mut source = self.source();
+        while let Some(error) = source {
+            let message = error.to_string();
+            if !messages.iter().any(|m| m.ends_with(&message)) {
+                messages.push(message);
+            }
+            source = error.source();
+        }
+        messages
+    }
+
+    // The message for the user: the summary, or with `verbose` every cause
+    pub fn report(&self, verbose: bool) -> String {
+        let chain = self.chain();
+        if verbose {
+            chain.join("\n  caused by: ")
+        } else {
+            chain[0].clone()
+        }
+    }
+
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            Error::Db { source, .. } if is_unavailable(source) => exitcode::UNAVAILABLE,
+            Error::Db {
+                source:
+                    DbOperationsError::InvalidSearchQuery { .. }
+                    | DbOperationsError::AgeOutOfRange { .. },
+                ..
+            } => exitcode::USAGE,
+            Error::Db {
+                operation: Operation::Open | Operation::Initialise | Operation::Migrate,
+                ..
+            } => exitcode::UNAVAILABLE,
+            Error::Db { .. } | Error::Event { .. } => exitcode::DATAERR,
+            Error::Notify { source } => match source {
+                NotifyError::Events { .. } | NotifyError::Record { .. } => exitcode::DATAERR,
+                NotifyError::DuplicateBackend { .. } => exitcode::CONFIG,
+                _ => exitcode::UNAVAILABLE,
+            },
+            Error::Cli { source } => match source {
+                CliError::NotFound { .. } => exitcode::NOINPUT,
+                CliError::UnknownColumn { .. } => exitcode::USAGE,
+                CliError::Output { .. } => exitcode::SOFTWARE,
+                CliError::Config { .. } => exitcode::CONFIG,
+                _ => exitcode::DATAERR,
+            },
+            #[cfg(feature = "async")]
+            Error::Task { .. } => exitcode::SOFTWARE,
+        }
+    }
+}
+
+fn is_unavailable(error: &DbOperationsError) -> bool {
+    matches!(
+        error,
+        DbOperationsError::PoolError { .. }
+            | DbOperationsError::MigrationError { .. }
+            | DbOperationsError::InitialisationError { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let sqlite_error = rusqlite::Connection::open_in_memory()
+            .unwrap()
+            .execute("SELECT * FROM nowhere", [])
+            .unwrap_err();
+        let error = Error::Db {
+            operation: Operation::Load,
+            entity: Some(EntityKind::Person),
+            id: Some(3),
+            source: DbOperationsError::QueryError { sqlite_error },
+        };
+        assert_eq!("Could not load person 3: Query failed", error.report(false));
+        assert_eq!(
+            vec![
+                "Could not load person 3: Query failed",
+                "no such table: nowhere",
+                "Error code 1: SQL error or missing database",
+            ],
+            error.chain()
+        );
+        assert!(error
+            .report(true)
+            .contains("\n  caused by: no such table: nowhere"));
+        assert_eq!(exitcode::DATAERR, error.exit_code());
+
+        let error = Error::from(CliError::NotFound {
+            entity: String::from("Person"),
+            id: 3,
+        });
+        assert_eq!(vec!["Entity not found Person for id 3"], error.chain());
+        assert_eq!(exitcode::NOINPUT, error.exit_code());
+    }
+}
@@ -1,20 +1,57 @@
 use crate::entities::Entity;
-use std::any::type_name_of_val;
+use std::any::type_name;
 use std::{
     fmt::Display,
-    io::{self, Write},
+    io::{self, IsTerminal, Write},
+    str::FromStr,
 };
 
 use snafu::Snafu;
 
 use crate::entities::activity::ActivityType;
 use crate::entities::person::{ContactInfo, ContactInfoType};
-use crate::{ActivityTypeParseSnafu, CliError, ContactInfoParseSnafu};
+use crate::{ActivityTypeParseSnafu, CliError, ContactInfoParseSnafu, DateParseSnafu};
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub(crate)))]
-pub struct SelectionError {
-    pub message: String,
+pub enum SelectionError {
+    #[snafu(display("Aborted"))]
+    Aborted,
+    #[snafu(display("No {} found", entity))]
+    NotFound { entity: String },
+    #[snafu(display("No {} with id {} among the matches", entity, id))]
+    UnknownId { entity: String, id: u64 },
+    #[snafu(display("Cannot {} without a terminal, use {}", action, hint))]
+    NotATerminal { action: String, hint: String },
+    #[snafu(display("Invalid input: {}", input))]
+    InvalidInput { input: String },
+}
+
+// How to pick among several entities matching a filter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Selection {
+    // Ask on the terminal
+    #[default]
+    Prompt,
+    First,
+    All,
+    Id(u64),
+}
+
+impl FromStr for Selection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Selection, String> {
+        match s {
+            "prompt" => Ok(Selection::Prompt),
+            "first" => Ok(Selection::First),
+            "all" => Ok(Selection::All),
+            _ => match s.parse::<u64>() {
+                Ok(id) => Ok(Selection::Id(id)),
+                Err(_) => Err(format!("expected prompt, first, all or an id, got {}", s)),
+            },
+        }
+    }
 }
 
 pub struct ActivityVars {
@@ -43,58 +80,159 @@ pub fn parse_from_str_ymd(date: &str) -> Result<chrono::NaiveDate, chrono::Parse
     chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
 }
 
+// Timestamps are stored in SQLite's `datetime('now')` format. Rows written
+// before timestamps were tracked have none.
+pub fn get_timestamp(row: &rusqlite::Row, column: &str) -> Option<chrono::NaiveDateTime> {
+    row.get::<&str, Option<String>>(column)
+        .ok()
+        .flatten()
+        .and_then(|timestamp| {
+            chrono::NaiveDateTime::parse_from_str(&timestamp, "%Y-%m-%d %H:%M:%S").ok()
+        })
+}
+
+// Dates given on the command line, e.g. a reminder's exception dates
+pub fn parse_dates(dates: Vec<String>) -> Result<Vec<chrono::NaiveDate>, CliError> {
+    dates
+        .into_iter()
+        .map(|date| match parse_from_str_ymd(&date) {
+            Ok(date) => Ok(date),
+            Err(_) => DateParseSnafu { date }.fail(),
+        })
+        .collect()
+}
+
 pub fn parse_from_str_md(date: &str) -> Result<chrono::NaiveDate, chrono::ParseError> {
     parse_from_str_ymd(format!("1-{}", date).as_ref())
 }
 
+// Parses an age such as `90d`, `2w`, `6m` or `1y` into days. A bare number
+// is taken as days; months and years are approximated as 30 and 365 days.
+pub fn parse_days(age: &str) -> Result<u64, String> {
+    let (number, unit) = match age.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => age.split_at(i),
+        None => (age, "d"),
+    };
+    let multiplier = match unit {
+        "d" => 1,
+        "w" => 7,
+        "m" => 30,
+        "y" => 365,
+        _ => return Err(format!("invalid unit in {}, expected d, w, m or y", age)),
+    };
+    match number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+    {
+        Some(days) => Ok(days),
+        None => Err(format!("invalid age {}, expected e.g. 90d", age)),
+    }
+}
+
 pub fn unwrap_arg_or_empty_string(arg: Option<String>) -> String {
     arg.unwrap_or("".to_string())
 }
 
-pub fn handle_id_selection<T>(entity_vec: Vec<T>) -> Result<Vec<T>, SelectionError>
+// Lower-cased entity name, e.g. `person` for `prm::entities::person::Person`
+fn entity_name<T>() -> String {
+    type_name::<T>()
+        .split("::")
+        .last()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+fn read_answer() -> Result<String, SelectionError> {
+    io::stdout().flush().ok();
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(_) => Ok(answer.trim().to_string()),
+        Err(e) => InvalidInputSnafu {
+            input: e.to_string(),
+        }
+        .fail(),
+    }
+}
+
+// Resolves a list of matches down to the ones to act on. Only prompts when
+// `selection` is `Selection::Prompt` and there is more than one match.
+pub fn handle_id_selection<T>(
+    entity_vec: Vec<T>,
+    selection: &Selection,
+) -> Result<Vec<T>, SelectionError>
 where
     T: Clone + Display + Entity,
 {
-    let entity_name = match type_name_of_val(&entity_vec[0]).split("::").last() {
-        Some(entity_name) => entity_name.to_lowercase(),
-        None => {
-            return Err(SelectionError {
-                message: format!("Invalid entity name: {}", type_name_of_val(&entity_vec[0])),
-            })
+    let entity_name = entity_name::<T>();
+    if entity_vec.is_empty() {
+        return NotFoundSnafu {
+            entity: entity_name,
         }
-    };
-    println!("Multiple {}s found", entity_name);
-    for e in entity_vec.clone() {
-        println!("[{}]\n{}", e.get_id(), e);
+        .fail();
     }
-    print!(
-        "Which of the {} do you want to remove (0 to cancel)? ",
-        entity_name
-    );
-    io::stdout().flush().unwrap();
-    let mut n = String::new();
-    io::stdin().read_line(&mut n).unwrap();
-    let n = match n.trim().parse::<usize>() {
-        Ok(n) => n,
-        Err(_) => {
-            return Err(SelectionError {
-                message: String::from("Invalid input"),
-            })
+    let id = match selection {
+        Selection::First => return Ok(entity_vec[..1].to_vec()),
+        Selection::All => return Ok(entity_vec),
+        Selection::Id(id) => *id,
+        Selection::Prompt if entity_vec.len() == 1 => return Ok(entity_vec),
+        Selection::Prompt => {
+            if !io::stdin().is_terminal() {
+                return NotATerminalSnafu {
+                    action: format!("select one of multiple {}s", entity_name),
+                    hint: "--select first|all|<id>",
+                }
+                .fail();
+            }
+            println!("Multiple {}s found", entity_name);
+            for e in entity_vec.iter() {
+                println!("[{}]\n{}", e.get_id(), e);
+            }
+            print!(
+                "Which of the {} do you want to remove (0 to cancel)? ",
+                entity_name
+            );
+            let answer = read_answer()?;
+            match answer.parse::<u64>() {
+                Ok(0) => return AbortedSnafu.fail(),
+                Ok(id) => id,
+                Err(_) => return InvalidInputSnafu { input: answer }.fail(),
+            }
         }
     };
-    if n == 0 {
-        return Err(SelectionError {
-            message: String::from("Aborted"),
-        });
+    match entity_vec.into_iter().find(|e| e.get_id() == id) {
+        Some(e) => Ok(vec![e]),
+        None => UnknownIdSnafu {
+            entity: entity_name,
+            id,
+        }
+        .fail(),
     }
-    for e in entity_vec.clone() {
-        if e.get_id() == n as u64 {
-            return Ok(vec![e]);
+}
+
+// Asks a yes/no question, unless `yes` already answered it. Anything but `y`
+// aborts.
+pub fn confirm(question: &str, yes: bool) -> Result<(), SelectionError> {
+    confirm_on(question, yes, io::stdin().is_terminal())
+}
+
+// `confirm`, with `terminal` telling whether there is a terminal to ask on
+pub fn confirm_on(question: &str, yes: bool, terminal: bool) -> Result<(), SelectionError> {
+    if yes {
+        return Ok(());
+    }
+    if !terminal {
+        return NotATerminalSnafu {
+            action: "confirm",
+            hint: "--yes",
         }
+        .fail();
+    }
+    print!("{} [y/n] ", question);
+    if read_answer()? != "y" {
+        return AbortedSnafu.fail();
     }
-    Err(SelectionError {
-        message: String::from("Unknown error"),
-    })
+    Ok(())
 }
 
 pub fn get_activity_type(activity_type: String) -> Result<ActivityType, CliError> {
@@ -150,3 +288,55 @@ pub fn join_int_vector<T: ToString>(vec: &[T]) -> String {
         .trim_end_matches(',')
         .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::note::Note;
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(Ok(90), parse_days("90d"));
+        assert_eq!(Ok(14), parse_days("2w"));
+        assert_eq!(Ok(60), parse_days("2m"));
+        assert_eq!(Ok(365), parse_days("1y"));
+        assert_eq!(Ok(3), parse_days("3"));
+        assert!(parse_days("3x").is_err());
+        assert!(parse_days("abc").is_err());
+        assert!(parse_days("-3d").is_err());
+        assert!(parse_days("").is_err());
+        assert!(parse_days("99999999999999999999d").is_err());
+        assert!(parse_days("100000000000000000y").is_err());
+    }
+
+    #[test]
+    fn test_handle_id_selection() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let notes = vec![
+            Note::new(1, date, String::from("a"), vec![]),
+            Note::new(2, date, String::from("b"), vec![]),
+        ];
+
+        assert_eq!(Ok(Selection::Id(7)), "7".parse::<Selection>());
+        assert!("last".parse::<Selection>().is_err());
+
+        let selected = handle_id_selection(notes.clone(), &Selection::First).unwrap();
+        assert_eq!(vec![1], selected.iter().map(|n| n.id).collect::<Vec<u64>>());
+        let selected = handle_id_selection(notes.clone(), &Selection::Id(2)).unwrap();
+        assert_eq!(vec![2], selected.iter().map(|n| n.id).collect::<Vec<u64>>());
+        assert_eq!(
+            2,
+            handle_id_selection(notes.clone(), &Selection::All)
+                .unwrap()
+                .len()
+        );
+        assert!(matches!(
+            handle_id_selection(notes, &Selection::Id(3)),
+            Err(SelectionError::UnknownId { .. })
+        ));
+        assert!(matches!(
+            handle_id_selection(Vec::<Note>::new(), &Selection::All),
+            Err(SelectionError::NotFound { .. })
+        ));
+    }
+}
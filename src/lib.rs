@@ -1,86 +1,98 @@
-#![feature(type_name_of_val)]
-
+pub mod config;
 pub mod db;
 pub mod editor;
 pub mod entities;
+pub mod error;
 pub mod helpers;
+pub mod notify;
+pub mod prm;
 
 use std::collections::HashMap;
 
 use snafu::prelude::*;
 
+use crate::db_interface::DbOperationsError;
+
 pub use crate::db::{db_helpers, db_interface};
+pub use crate::error::Error;
+#[cfg(feature = "async")]
+pub use crate::prm::asynchronous::AsyncPrm;
+pub use crate::prm::Prm;
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub))]
 pub enum CliError {
     #[snafu(display("Invalid birthday: {}", birthday))]
-    BirthdayParse {
-        birthday: String,
-    },
+    BirthdayParse { birthday: String },
     #[snafu(display("Invalid contact info: {}", contact_info))]
-    ContactInfoParse {
-        contact_info: String,
-    },
+    ContactInfoParse { contact_info: String },
     #[snafu(display("Invalid activity type: {}", activity_type))]
-    ActivityTypeParse {
-        activity_type: String,
-    },
+    ActivityTypeParse { activity_type: String },
     #[snafu(display("Invalid date: {}", date))]
-    DateParse {
+    DateParse { date: String },
+    #[snafu(display("Invalid date {}: {}", date, source))]
+    DateTimeParse {
         date: String,
+        source: crate::entities::datetime::DateTimeError,
     },
-    #[snafu(display("Invalid recurring type: {}", recurring_type))]
+    #[snafu(display("Invalid recurring type {}: {}", recurring_type, source))]
     RecurringTypeParse {
         recurring_type: String,
+        source: crate::entities::recurrence::RecurrenceError,
+    },
+    #[snafu(display("Error running the editor for {}: {}", entity, source))]
+    EditorRun {
+        entity: String,
+        source: std::io::Error,
     },
-    #[snafu(display("Error parsing {} from editor", entity))]
+    #[snafu(display("Error parsing {} from editor: {}", entity, source))]
     EditorParse {
         entity: String,
-        message: String,
+        source: Box<CliError>,
     },
     #[snafu(display("Invalid record: {}", record))]
-    RecordParse {
-        record: String,
-    },
+    RecordParse { record: String },
     #[snafu(display("Error parsing field: {}", field))]
-    FieldError {
-        field: String,
-    },
+    FieldError { field: String },
+    #[snafu(display("Error running the editor"))]
     EditorError,
+    #[snafu(display("Invalid format"))]
     FormatError,
+    #[snafu(display("Invalid id format"))]
     InvalidIdFormat,
     #[snafu(display("Error while parsing activity: {}", activity))]
-    ActivityParseError {
-        activity: String,
-    },
-    #[snafu(display("Error adding {}", entity))]
+    ActivityParseError { activity: String },
+    #[snafu(display("Error adding {}: {}", entity, source))]
     Add {
         entity: String,
+        source: DbOperationsError,
     },
-    #[snafu(display("Entity error {}", entity))]
-    Entity {
+    #[snafu(display("Error {} {}: {}", action, entity, source))]
+    Database {
         entity: String,
-        message: String,
+        action: String,
+        source: DbOperationsError,
     },
-    #[snafu(display("Error editing {}", entity))]
+    #[snafu(display("{}", message))]
+    Entity { entity: String, message: String },
+    #[snafu(display("Error editing {}: {}", entity, source))]
     Edit {
         entity: String,
-        message: String,
+        source: DbOperationsError,
     },
     #[snafu(display("Entity not found {} for id {}", entity, id))]
-    NotFound {
-        entity: String,
-        id: u64,
-    },
+    NotFound { entity: String, id: u64 },
     #[snafu(display("Unexpected missing field {}: {}", entity, field))]
-    MissingField {
-        entity: String,
-        field: String,
-    },
+    MissingField { entity: String, field: String },
     #[snafu(display("Failed to apply string template {}: {:#?}", template, vars))]
     Template {
         template: String,
         vars: HashMap<String, String>,
     },
+    #[snafu(display("Unknown column {}, expected one of: {}", column, available))]
+    UnknownColumn { column: String, available: String },
+    #[snafu(display("Error formatting output: {}", message))]
+    Output { message: String },
+    #[snafu(display("{}", source))]
+    Config { source: crate::config::ConfigError },
 }
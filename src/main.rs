@@ -2,41 +2,139 @@ mod cli;
 
 use clap::builder::ArgAction;
 use clap::{Args, Parser, Subcommand};
-use ics::properties::{Comment, DtStart, Due, RRule, Status, Summary};
+use cli::ids::{ById, IdArgs};
+use cli::output::Format;
+use cli::table::{TableArgs, Tabular};
+use ics::properties::{Comment, DtStart, RRule, Status, Summary};
 use ics::{escape_text, Event as IcsEvent, ICalendar, ToDo};
-use prm::db_interface::DbOperations;
+use prm::config::Config;
+use prm::db::audit;
+use prm::db::doctor;
+use prm::db::entity_kind::EntityKind;
+use prm::db::journal::{self, JournalEntry};
+use prm::db::loader::LoadContext;
+use prm::db::trash;
+use prm::db_interface::{DbOperations, DbOperationsError};
 use prm::entities::activity::Activity;
 use prm::entities::event::{Event, EventType};
 use prm::entities::note::Note;
 use prm::entities::person::Person;
 use prm::entities::reminder::Reminder;
-use prm::helpers::handle_id_selection;
+use prm::error::{DbSnafu, Operation};
+use prm::helpers::{confirm, handle_id_selection, parse_days, Selection, SelectionError};
+use prm::prm::Stored;
 use rusqlite::Connection;
+use serde::Serialize;
+use snafu::IntoError;
 use std::fmt::Display;
+use std::fs;
 use std::io;
-use std::io::Write;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-use prm::db::db_helpers::{notes, people, reminders};
+use prm::db::db_helpers::{notes, people, reminders, search};
 
-use prm::entities::Entity;
 use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set from `--verbose` before any command runs
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+// Prints `error` and exits with its exit code
+fn fail(error: impl Into<prm::Error>) -> ! {
+    let error = error.into();
+    eprintln!("{}", error.report(VERBOSE.load(Ordering::Relaxed)));
+    exit(error.exit_code());
+}
 
 #[derive(Parser)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Path to the database, overriding the config file
+    #[arg(long, global = true, env = "PRM_DB")]
+    db: Option<PathBuf>,
+    /// Profile to use instead of the configured default
+    #[arg(long, global = true, env = "PRM_PROFILE")]
+    profile: Option<String>,
+    /// Output format for show and list commands
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    /// Print the full chain of causes of errors
+    #[arg(long, global = true)]
+    verbose: bool,
+    /// Fail on rows that cannot be read instead of skipping them
+    #[arg(long, global = true)]
+    strict: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     Init {},
+    Migrate {},
     Add(AddArgs),
     Show(ShowArgs),
     Edit(EditArgs),
     Remove(RemoveArgs),
     List(ListArgs),
     Ics(IcsArgs),
+    Reminder(ReminderArgs),
+    /// Send notifications for due and upcoming reminders and birthdays
+    /// through the configured backends, each one only once
+    Notify {
+        /// Keep running, checking again every --interval seconds
+        #[arg(long)]
+        daemon: bool,
+        /// Seconds between checks, overriding the config file
+        #[arg(long, requires = "daemon")]
+        interval: Option<u64>,
+        /// Days ahead to notify about, overriding the config file
+        #[arg(long)]
+        days: Option<u64>,
+    },
+    Profile(ProfileArgs),
+    Trash(TrashArgs),
+    /// Check the database for corruption and inconsistent rows
+    Doctor {
+        /// Repair problems, moving rows that cannot be repaired to the trash
+        #[arg(long)]
+        fix: bool,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+    /// Revert the last operations
+    Undo {
+        #[arg(default_value_t = 1)]
+        count: u64,
+    },
+    /// Re-apply operations reverted by undo
+    Redo {
+        #[arg(default_value_t = 1)]
+        count: u64,
+    },
+    /// Show the most recent operations, or the field changes of an entity
+    History {
+        /// Show the field changes of a single entity: person, activity,
+        /// reminder, note or contact_info
+        #[arg(requires = "id")]
+        entity: Option<EntityKind>,
+        id: Option<u64>,
+        #[arg(short, long, default_value_t = 20)]
+        limit: u64,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+    /// Full-text search across people, activities, reminders, notes and
+    /// contact info. Supports "phrases", prefix* and AND/OR/NOT queries
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        #[arg(short, long, default_value_t = 20)]
+        limit: u64,
+        #[command(flatten)]
+        table: TableArgs,
+    },
 }
 
 #[derive(Args)]
@@ -74,6 +172,27 @@ struct RemoveArgs {
     entity: RemoveEntity,
 }
 
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct ProfileArgs {
+    #[command(subcommand)]
+    command: ProfileCommand,
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct ReminderArgs {
+    #[command(subcommand)]
+    command: ReminderCommand,
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct TrashArgs {
+    #[command(subcommand)]
+    command: TrashCommand,
+}
+
 #[derive(Args)]
 #[command(args_conflicts_with_subcommands = true)]
 struct IcsArgs {
@@ -85,6 +204,82 @@ struct IcsArgs {
     all: bool,
 }
 
+#[derive(Subcommand)]
+enum TrashCommand {
+    List {
+        #[command(flatten)]
+        table: TableArgs,
+    },
+    /// Undelete an entity and the links that were removed with it
+    Restore {
+        /// person, activity, reminder, note or contact_info
+        entity: EntityKind,
+        id: u64,
+    },
+    /// Permanently delete trashed entities
+    Purge {
+        /// Only purge entities deleted at least this long ago, e.g. 90d
+        #[arg(long, value_parser = parse_days)]
+        older_than: Option<u64>,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReminderCommand {
+    /// Mark the occurrence the reminder is waiting on as done
+    Done {
+        id: u64,
+        /// Mark the occurrence on this date instead
+        #[arg(short, long)]
+        date: Option<String>,
+    },
+    /// Skip the occurrence the reminder is waiting on
+    Skip {
+        id: u64,
+        /// Skip the occurrence on this date instead
+        #[arg(short, long)]
+        date: Option<String>,
+    },
+    /// Postpone the occurrence the reminder is waiting on
+    Snooze {
+        id: u64,
+        #[arg(short, long)]
+        until: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    List {},
+    Create {
+        name: String,
+        #[arg(long)]
+        database: Option<PathBuf>,
+        #[arg(long)]
+        ics: Option<PathBuf>,
+    },
+    Switch {
+        name: String,
+    },
+    Delete {
+        name: String,
+        /// Also delete the profile's database and ICS files
+        #[arg(long)]
+        purge: bool,
+    },
+    CopyPerson {
+        id: u64,
+        /// Profile to copy from, defaults to the active profile
+        #[arg(long)]
+        from: Option<String>,
+        #[arg(long)]
+        to: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum AddEntity {
     Person {
@@ -109,8 +304,12 @@ enum AddEntity {
         name: Option<String>,
         #[arg(short, long)]
         date: Option<String>,
+        /// A recurring type like monthly, or an RRULE like FREQ=MONTHLY;BYDAY=1MO
         #[arg(short, long)]
         recurring: Option<String>,
+        /// Dates the reminder is skipped on
+        #[arg(long = "exdate", value_delimiter = ',')]
+        exdates: Vec<String>,
         #[arg(long)]
         description: Option<String>,
         #[arg(short, long)]
@@ -126,12 +325,16 @@ enum AddEntity {
 #[derive(Subcommand)]
 enum ShowEntity {
     Person {
+        #[command(flatten)]
+        ids: IdArgs,
         #[arg(short, long)]
         name: Option<String>,
         #[arg(short, long)]
         birthday: Option<String>,
     },
     Activity {
+        #[command(flatten)]
+        ids: IdArgs,
         #[arg(short, long)]
         name: Option<String>,
         #[arg(short, long)]
@@ -140,6 +343,8 @@ enum ShowEntity {
         content: Option<String>,
     },
     Reminder {
+        #[command(flatten)]
+        ids: IdArgs,
         #[arg(short, long)]
         name: Option<String>,
         #[arg(short, long)]
@@ -148,6 +353,8 @@ enum ShowEntity {
         description: Option<String>,
     },
     Notes {
+        #[command(flatten)]
+        ids: IdArgs,
         #[arg(short, long)]
         person: Option<String>,
         #[arg(short, long)]
@@ -193,8 +400,13 @@ enum EditEntity {
         date: Option<String>,
         #[arg(long)]
         description: Option<String>,
+        /// A recurring type like monthly, or an RRULE like FREQ=MONTHLY;BYDAY=1MO
         #[arg(short, long)]
         recurring: Option<String>,
+        /// Dates the reminder is skipped on, replacing the current ones. Clear
+        /// them with an empty --exdate
+        #[arg(long = "exdate", value_delimiter = ',', num_args = 0..)]
+        exdates: Option<Vec<String>>,
     },
     Note {
         #[arg(short, long)]
@@ -211,6 +423,8 @@ enum ListEntity {
     People {
         #[arg(short, long)]
         name: Option<String>,
+        #[command(flatten)]
+        table: TableArgs,
     },
     Activities {
         #[arg(short, long)]
@@ -221,76 +435,374 @@ enum ListEntity {
         content: Option<String>,
         #[arg(short, long)]
         activity_type: Option<String>,
+        #[command(flatten)]
+        table: TableArgs,
     },
     Reminders {
         #[arg(short, long)]
         name: Option<String>,
-        #[arg(short, long, action = ArgAction::SetTrue)]
-        include_past: bool,
+        /// Include reminders with nothing left to do
+        #[arg(short, long, alias = "include-past", action = ArgAction::SetTrue)]
+        include_done: bool,
+        #[command(flatten)]
+        table: TableArgs,
     },
     Notes {
         #[arg(short, long)]
         content: Option<String>,
+        #[command(flatten)]
+        table: TableArgs,
     },
     Events {
-        #[arg(short, long, default_value = "90")]
-        days: u64,
+        #[arg(short, long)]
+        days: Option<u64>,
+        #[command(flatten)]
+        table: TableArgs,
     },
 }
 
+#[derive(Args)]
+struct ConfirmArgs {
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+    /// How to resolve multiple matches: prompt, first, all or an id
+    #[arg(long, default_value = "prompt")]
+    select: Selection,
+}
+
 #[derive(Subcommand)]
 enum RemoveEntity {
     Person {
-        #[arg(short, long)]
-        name: String,
+        #[command(flatten)]
+        ids: IdArgs,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+        #[arg(short, long, required_unless_present_any = ["id", "ids"])]
+        name: Option<String>,
     },
     Activity {
-        #[arg(short, long)]
-        name: String,
+        #[command(flatten)]
+        ids: IdArgs,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+        #[arg(short, long, required_unless_present_any = ["id", "ids"])]
+        name: Option<String>,
         #[arg(short, long)]
         person: Option<String>,
         #[arg(short, long)]
         content: Option<String>,
     },
     Reminder {
-        #[arg(short, long)]
-        name: String,
+        #[command(flatten)]
+        ids: IdArgs,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+        #[arg(short, long, required_unless_present_any = ["id", "ids"])]
+        name: Option<String>,
     },
     Note {
-        #[arg(short, long)]
-        content: String,
+        #[command(flatten)]
+        ids: IdArgs,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+        #[arg(short, long, required_unless_present_any = ["id", "ids"])]
+        content: Option<String>,
     },
 }
 
-pub fn remove_entity<T: Entity + Display + DbOperations>(conn: &Connection, entity: &T) {
+pub fn remove_entity<T: Stored + Display>(conn: &Connection, entity: &T) {
     match entity.remove(conn) {
         Ok(_) => println!("{}\nremoved successfully", entity),
-        Err(_) => {
-            eprintln!("Error while removing {}", entity);
+        Err(e) => fail(
+            DbSnafu {
+                operation: Operation::Remove,
+                entity: T::KIND,
+                id: entity.get_id(),
+            }
+            .into_error(e),
+        ),
+    };
+}
+
+fn get_by_ids<T: ById>(conn: &Connection, load: &mut LoadContext, ids: &[u64]) -> Vec<T> {
+    match cli::ids::get_by_ids::<T>(conn, load, ids) {
+        Ok(entities) => entities,
+        Err(e) => fail(e),
+    }
+}
+
+// Removes every entity or none of them: a failure exits before the
+// transaction is committed
+fn remove_all<T: Stored + Display>(conn: &Connection, entities: &[T]) {
+    let tx = match conn.unchecked_transaction() {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Error while removing: {}", e);
             exit(exitcode::DATAERR);
         }
     };
+    for entity in entities {
+        remove_entity(&tx, entity);
+    }
+    if let Err(e) = tx.commit() {
+        eprintln!("Error while removing: {}", e);
+        exit(exitcode::DATAERR);
+    }
 }
 
-fn main() {
-    let args = Cli::parse();
+// Removing by id asks for confirmation like removing by filter does
+fn remove_by_ids<T: ById + Stored + Display>(
+    conn: &Connection,
+    load: &mut LoadContext,
+    ids: &[u64],
+    entity: &str,
+    yes: bool,
+) {
+    let entities = get_by_ids::<T>(conn, load, ids);
+    confirm_removal(&entities, entity, yes);
+    remove_all(conn, &entities);
+}
+
+// Exits unless the removal of `entities` is confirmed
+fn confirm_removal<T: Display>(entities: &[T], entity: &str, yes: bool) {
+    if let Err(e) = cli::ids::confirm_removal(entities, entity, yes, io::stdin().is_terminal()) {
+        match e {
+            SelectionError::Aborted => println!("Not removing"),
+            _ => eprintln!("{}", e),
+        }
+        exit(selection_exit_code(&e));
+    }
+}
+
+fn selection_exit_code(e: &SelectionError) -> exitcode::ExitCode {
+    match e {
+        SelectionError::Aborted => exitcode::TEMPFAIL,
+        SelectionError::NotFound { .. } | SelectionError::UnknownId { .. } => exitcode::NOINPUT,
+        SelectionError::NotATerminal { .. } | SelectionError::InvalidInput { .. } => {
+            exitcode::USAGE
+        }
+    }
+}
+
+// Resolves `matches` with `--select`, confirms unless `--yes` is given and
+// removes the selected entities
+fn remove_matches<T: Stored + Display + Clone>(
+    conn: &Connection,
+    matches: Vec<T>,
+    entity: &str,
+    confirm_args: &ConfirmArgs,
+) {
+    let selected = match handle_id_selection::<T>(matches, &confirm_args.select) {
+        Ok(selected) => selected,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(selection_exit_code(&e));
+        }
+    };
+
+    confirm_removal(&selected, entity, confirm_args.yes);
+    remove_all(conn, &selected);
+}
+
+fn open_database(db_path: &Path, migrate: bool) -> Connection {
+    if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            eprintln!("Error creating {}: {}", parent.display(), e);
+            exit(exitcode::CANTCREAT);
+        }
+    }
 
-    let conn = match Connection::open("data/prm.db") {
+    let conn = match Connection::open(db_path) {
         Ok(conn) => conn,
         Err(_) => {
-            eprintln!("Error opening database");
+            eprintln!("Error opening database {}", db_path.display());
             exit(exitcode::UNAVAILABLE);
         }
     };
+    // Foreign keys are off by default and can only be enabled per connection
+    if let Err(e) = conn.pragma_update(None, "foreign_keys", true) {
+        eprintln!("Error enabling foreign keys: {}", e);
+        exit(exitcode::UNAVAILABLE);
+    }
+
+    if migrate {
+        if let Err(e) = prm::db::migrations::migrate(&conn) {
+            fail(db_error(Operation::Migrate, None, e));
+        }
+    }
+    conn
+}
+
+fn print_output<T: Serialize>(
+    format: Format,
+    items: &[T],
+    empty: Option<&str>,
+    display: impl Fn(&T) -> String,
+) {
+    if let Err(e) = cli::output::print(format, items, empty, display) {
+        fail(e);
+    }
+}
+
+fn print_list<T: Serialize + Tabular>(
+    format: Format,
+    items: &mut [T],
+    table: &TableArgs,
+    date_format: &str,
+) {
+    if let Err(e) = cli::output::print_list(format, items, table, date_format) {
+        fail(e);
+    }
+}
+
+// Applies `step` (undo or redo) up to `count` times, stopping early when
+// there is nothing left
+fn replay_journal(
+    conn: &Connection,
+    count: u64,
+    action: &str,
+    done: &str,
+    step: fn(&Connection) -> Result<Option<JournalEntry>, DbOperationsError>,
+) {
+    for _ in 0..count {
+        match step(conn) {
+            Ok(Some(entry)) => println!(
+                "{} {} {} {} ({})",
+                done, entry.operation, entry.entity, entry.entity_id, entry.created_at
+            ),
+            Ok(None) => {
+                println!("Nothing to {}", action);
+                return;
+            }
+            Err(e) => fail(db_error(Operation::Replay, None, e)),
+        }
+    }
+}
+
+// A database error that isn't about a single entity
+fn db_error(
+    operation: Operation,
+    entity: Option<EntityKind>,
+    source: DbOperationsError,
+) -> prm::Error {
+    DbSnafu {
+        operation,
+        entity,
+        id: None,
+    }
+    .into_error(source)
+}
+
+// Whether two paths lead to the same file, through links and relative parts
+fn same_file(a: &Path, b: &Path) -> bool {
+    let resolve = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    resolve(a) == resolve(b)
+}
+
+fn database_path(config: &Config, profile: &str) -> PathBuf {
+    match config.database_path(profile) {
+        Ok(db_path) => db_path,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(exitcode::CONFIG);
+        }
+    }
+}
+
+fn main() {
+    let args = Cli::parse();
+    VERBOSE.store(args.verbose, Ordering::Relaxed);
+
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(exitcode::CONFIG);
+        }
+    };
+
+    // The `edit` crate picks the editor from the environment, where the
+    // config only fills in for VISUAL and EDITOR
+    if let Some(editor) = &config.editor {
+        if std::env::var_os("VISUAL").is_none() && std::env::var_os("EDITOR").is_none() {
+            std::env::set_var("VISUAL", editor);
+        }
+    }
+
+    let timezone = config.default_timezone();
+
+    let profile = config.active_profile(args.profile.as_deref());
+
+    // Profile management works on the config file and does not need the
+    // active profile's database
+    if let Commands::Profile(profile_args) = args.command {
+        let res = match profile_args.command {
+            ProfileCommand::List {} => cli::profile::list(&config, &profile),
+            ProfileCommand::Create {
+                name,
+                database,
+                ics,
+            } => cli::profile::create(&mut config, name, database, ics),
+            ProfileCommand::Switch { name } => cli::profile::switch(&mut config, name),
+            ProfileCommand::Delete { name, purge } => {
+                cli::profile::delete(&mut config, name, purge)
+            }
+            ProfileCommand::CopyPerson { id, from, to } => {
+                let from_path = match args.db {
+                    Some(db_path) => db_path,
+                    None => database_path(&config, &from.unwrap_or(profile)),
+                };
+                let to_path = database_path(&config, &to);
+                if same_file(&from_path, &to_path) {
+                    eprintln!("Source and target databases are the same");
+                    exit(exitcode::USAGE);
+                }
+                let from_conn = open_database(&from_path, true);
+                let to_conn = open_database(&to_path, true);
+                cli::profile::copy_person(&from_conn, &to_conn, id, config.journal_retention)
+                    .map(|_| ())
+            }
+        };
+        if let Err(e) = res {
+            fail(e);
+        }
+        return;
+    }
+
+    let db_path = match args.db {
+        Some(db_path) => db_path,
+        None => database_path(&config, &profile),
+    };
+
+    // `init` and `migrate` report on the migrations themselves
+    let conn = open_database(
+        &db_path,
+        !matches!(args.command, Commands::Init {} | Commands::Migrate {}),
+    );
+    let mut load = LoadContext::new(args.strict);
 
     match args.command {
         Commands::Init {} => {
             match prm::db::db_helpers::init_db(&conn) {
                 Ok(_) => println!("Database initialised"),
-                Err(_) => {
-                    eprintln!("Error initalising database");
-                    exit(exitcode::UNAVAILABLE);
+                Err(e) => fail(db_error(Operation::Initialise, None, e)),
+            };
+        }
+        Commands::Migrate {} => {
+            match prm::db::migrations::migrate(&conn) {
+                Ok(applied) => {
+                    if applied.is_empty() {
+                        println!(
+                            "Database is up to date (version {})",
+                            prm::db::migrations::latest_version()
+                        );
+                    }
+                    for version in applied {
+                        println!("Applied migration {}", version);
+                    }
                 }
+                Err(e) => fail(db_error(Operation::Migrate, None, e)),
             };
         }
         Commands::Add(add) => match add.entity {
@@ -312,129 +824,136 @@ fn main() {
                 people,
             } => {
                 if let Err(e) =
-                    cli::add::activity(&conn, name, activity_type, date, content, people)
+                    cli::add::activity(&conn, name, activity_type, date, content, people, timezone)
                 {
-                    eprintln!("{}", e);
-                    exit(exitcode::DATAERR);
+                    fail(e);
                 };
             }
             AddEntity::Reminder {
                 name,
                 date,
                 recurring,
+                exdates,
                 description,
                 people,
             } => {
-                if let Err(e) =
-                    cli::add::reminder(&conn, name, date, recurring, description, people)
-                {
-                    eprintln!("{}", e);
-                    exit(exitcode::DATAERR);
+                if let Err(e) = cli::add::reminder(
+                    &conn,
+                    name,
+                    date,
+                    recurring,
+                    exdates,
+                    description,
+                    people,
+                    timezone,
+                ) {
+                    fail(e);
                 };
             }
             AddEntity::Notes { content, people } => {
-                match cli::add::note(&conn, content, people) {
+                match cli::add::note(&conn, content, people, timezone) {
                     Ok(_) => (),
-                    Err(_) => {
-                        eprintln!("Error adding note");
-                        exit(exitcode::DATAERR);
-                    }
+                    Err(e) => fail(e),
                 };
             }
         },
         Commands::Show(show) => match show.entity {
-            ShowEntity::Person { name, birthday } => {
-                if [name.clone(), birthday.clone()].iter().all(Option::is_none) {
-                    eprintln!("No name or birthday provided");
-                    exit(exitcode::DATAERR);
-                }
-                let people = match people::get_by_name_and_birthday(&conn, name, birthday) {
-                    Ok(people) => people,
-                    Err(e) => {
-                        eprintln!("Error while fetching person: {:#?}", e);
+            ShowEntity::Person {
+                ids,
+                name,
+                birthday,
+            } => {
+                let ids = ids.ids();
+                let people = if !ids.is_empty() {
+                    get_by_ids::<Person>(&conn, &mut load, &ids)
+                } else {
+                    if [name.clone(), birthday.clone()].iter().all(Option::is_none) {
+                        eprintln!("No id, name or birthday provided");
                         exit(exitcode::DATAERR);
                     }
+                    match people::get_by_name_and_birthday(&conn, &mut load, name, birthday) {
+                        Ok(people) => people,
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Person), e)),
+                    }
                 };
-                for person in people {
-                    println!("{}", person);
-                }
+                print_output(args.format, &people, None, |p| p.to_string());
             }
             ShowEntity::Activity {
+                ids,
                 name,
                 person,
                 content,
             } => {
-                if [name.clone(), person.clone(), content.clone()]
-                    .iter()
-                    .all(Option::is_none)
-                {
-                    eprintln!("No name, person or content provided");
-                    exit(exitcode::DATAERR);
-                }
-                let activities = match Activity::get(&conn, name, person, content, None) {
-                    Ok(activities) => activities,
-                    Err(e) => {
-                        eprintln!("Error fetching activities: {:#?}", e);
+                let ids = ids.ids();
+                let activities = if !ids.is_empty() {
+                    get_by_ids::<Activity>(&conn, &mut load, &ids)
+                } else {
+                    if [name.clone(), person.clone(), content.clone()]
+                        .iter()
+                        .all(Option::is_none)
+                    {
+                        eprintln!("No id, name, person or content provided");
                         exit(exitcode::DATAERR);
                     }
-                };
-                if activities.is_empty() {
-                    println!("No activities found");
-                } else {
-                    for activity in activities {
-                        println!("{}", activity);
+                    match Activity::get(&conn, &mut load, name, person, content, None) {
+                        Ok(activities) => activities,
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Activity), e)),
                     }
-                }
+                };
+                print_output(args.format, &activities, Some("No activities found"), |a| {
+                    a.to_string()
+                });
             }
             ShowEntity::Reminder {
+                ids,
                 name,
                 person,
                 description,
             } => {
-                if [name.clone(), person.clone(), description.clone()]
-                    .iter()
-                    .all(Option::is_none)
-                {
-                    eprintln!("No name, person or description provided");
-                    exit(exitcode::DATAERR);
-                }
-                let reminders = match Reminder::get(&conn, name, person, description) {
-                    Ok(reminder) => reminder,
-                    Err(e) => {
-                        eprintln!("Error fetching reminders: {:#?}", e);
+                let ids = ids.ids();
+                let reminders = if !ids.is_empty() {
+                    get_by_ids::<Reminder>(&conn, &mut load, &ids)
+                } else {
+                    if [name.clone(), person.clone(), description.clone()]
+                        .iter()
+                        .all(Option::is_none)
+                    {
+                        eprintln!("No id, name, person or description provided");
                         exit(exitcode::DATAERR);
                     }
-                };
-                if reminders.is_empty() {
-                    println!("No reminders found");
-                } else {
-                    for reminder in reminders {
-                        println!("{}", reminder);
+                    match Reminder::get(&conn, &mut load, name, person, description) {
+                        Ok(reminder) => reminder,
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Reminder), e)),
                     }
-                }
+                };
+                print_output(args.format, &reminders, Some("No reminders found"), |r| {
+                    r.to_string()
+                });
             }
-            ShowEntity::Notes { person, content } => {
-                if [person.clone(), content.clone()]
-                    .iter()
-                    .all(Option::is_none)
-                {
-                    eprintln!("No person or content provided");
-                    exit(exitcode::DATAERR);
-                }
-                let notes = match Note::get(&conn, person, content) {
-                    Ok(note) => note,
-                    Err(e) => {
-                        eprintln!("Error fetching notes: {:#?}", e);
+            ShowEntity::Notes {
+                ids,
+                person,
+                content,
+            } => {
+                let ids = ids.ids();
+                let notes = if !ids.is_empty() {
+                    get_by_ids::<Note>(&conn, &mut load, &ids)
+                } else {
+                    if [person.clone(), content.clone()]
+                        .iter()
+                        .all(Option::is_none)
+                    {
+                        eprintln!("No id, person or content provided");
                         exit(exitcode::DATAERR);
                     }
-                };
-                if notes.is_empty() {
-                    println!("No notes found");
-                } else {
-                    for note in notes {
-                        println!("{}", note);
+                    match Note::get(&conn, &mut load, person, content) {
+                        Ok(note) => note,
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Note), e)),
                     }
-                }
+                };
+                print_output(args.format, &notes, Some("No notes found"), |n| {
+                    n.to_string()
+                });
             }
         },
         Commands::Edit(edit) => match edit.entity {
@@ -456,10 +975,7 @@ fn main() {
                     reminders,
                 ) {
                     Ok(_) => (),
-                    Err(e) => {
-                        eprintln!("Error editing person: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
+                    Err(e) => fail(e),
                 };
             }
             EditEntity::Activity {
@@ -469,12 +985,9 @@ fn main() {
                 date,
                 content,
             } => {
-                match cli::edit::activity(&conn, id, name, activity_type, date, content) {
+                match cli::edit::activity(&conn, id, name, activity_type, date, content, timezone) {
                     Ok(_) => (),
-                    Err(e) => {
-                        eprintln!("Error editing activity: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
+                    Err(e) => fail(e),
                 };
             }
             EditEntity::Reminder {
@@ -483,192 +996,121 @@ fn main() {
                 date,
                 description,
                 recurring,
+                exdates,
             } => {
-                match cli::edit::reminder(&conn, id, name, date, description, recurring) {
+                match cli::edit::reminder(
+                    &conn,
+                    id,
+                    name,
+                    date,
+                    description,
+                    recurring,
+                    exdates,
+                    timezone,
+                ) {
                     Ok(_) => (),
-                    Err(e) => {
-                        eprintln!("Error editing reminder: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
+                    Err(e) => fail(e),
                 };
             }
             EditEntity::Note { id, date, content } => {
-                match cli::edit::note(&conn, id, date, content) {
+                match cli::edit::note(&conn, id, date, content, timezone) {
                     Ok(_) => (),
-                    Err(e) => {
-                        eprintln!("Error editing note: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
+                    Err(e) => fail(e),
                 };
             }
         },
         Commands::Remove(remove) => match remove.entity {
-            RemoveEntity::Person { name } => {
-                let mut people = match people::get_by_name(&conn, name) {
-                    Ok(people) => people,
-                    Err(e) => {
-                        eprintln!("Error while fetching person: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
-                };
-                if people.len() > 1 {
-                    people = match handle_id_selection::<Person>(people) {
+            RemoveEntity::Person { ids, confirm, name } => {
+                let ids = ids.ids();
+                if !ids.is_empty() {
+                    remove_by_ids::<Person>(&conn, &mut load, &ids, "person", confirm.yes);
+                } else {
+                    let name = name.unwrap_or_default();
+                    let people = match people::get_by_name(&conn, &mut load, name) {
                         Ok(people) => people,
-                        Err(e) => {
-                            eprintln!("{}", e.message);
-                            exit(exitcode::DATAERR);
-                        }
-                    }
-                }
-
-                let person = &people[0];
-
-                println!("{}", person);
-                print!("Do you want to remove this person? [y/n] ");
-                io::stdout().flush().unwrap();
-                let mut answer = String::new();
-                io::stdin().read_line(&mut answer).unwrap();
-                if answer.trim() != "y" {
-                    println!("Not removing");
-                    exit(exitcode::OK);
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Person), e)),
+                    };
+                    remove_matches(&conn, people, "person", &confirm);
                 }
-
-                remove_entity(&conn, person);
             }
             RemoveEntity::Activity {
+                ids,
+                confirm,
                 name,
                 person,
                 content,
             } => {
-                let mut activities = match Activity::get(&conn, Some(name), person, content, None) {
-                    Ok(activities) => activities,
-                    Err(e) => {
-                        eprintln!("Error while fetching activity: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
-                };
-                if activities.len() > 1 {
-                    activities = match handle_id_selection::<Activity>(activities) {
-                        Ok(activities) => activities,
-                        Err(e) => {
-                            eprintln!("{}", e.message);
-                            exit(exitcode::DATAERR);
-                        }
-                    }
-                }
-
-                let activity = &activities[0];
-
-                println!("{}", activity);
-                print!("Do you want to remove this activity? [y/n] ");
-                io::stdout().flush().unwrap();
-                let mut answer = String::new();
-                io::stdin().read_line(&mut answer).unwrap();
-                if answer.trim() != "y" {
-                    println!("Not removing");
-                    exit(exitcode::OK);
+                let ids = ids.ids();
+                if !ids.is_empty() {
+                    remove_by_ids::<Activity>(&conn, &mut load, &ids, "activity", confirm.yes);
+                } else {
+                    let activities =
+                        match Activity::get(&conn, &mut load, name, person, content, None) {
+                            Ok(activities) => activities,
+                            Err(e) => {
+                                fail(db_error(Operation::List, Some(EntityKind::Activity), e))
+                            }
+                        };
+                    remove_matches(&conn, activities, "activity", &confirm);
                 }
-
-                remove_entity(&conn, activity);
             }
-            RemoveEntity::Reminder { name } => {
-                let mut reminders = match reminders::get_by_name(&conn, &name, None) {
-                    Ok(reminders) => reminders,
-                    Err(e) => {
-                        eprintln!("Error fetching reminder: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
-                };
-                if reminders.len() > 1 {
-                    reminders = match handle_id_selection::<Reminder>(reminders) {
+            RemoveEntity::Reminder { ids, confirm, name } => {
+                let ids = ids.ids();
+                if !ids.is_empty() {
+                    remove_by_ids::<Reminder>(&conn, &mut load, &ids, "reminder", confirm.yes);
+                } else {
+                    let name = name.unwrap_or_default();
+                    let reminders = match reminders::get_by_name(&conn, &mut load, &name, None) {
                         Ok(reminders) => reminders,
-                        Err(e) => {
-                            eprintln!("{}", e.message);
-                            exit(exitcode::DATAERR);
-                        }
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Reminder), e)),
                     };
+                    remove_matches(&conn, reminders, "reminder", &confirm);
                 }
-
-                let reminders = &reminders[0];
-
-                println!("{}", reminders);
-                print!("Do you want to remove this reminder? [y/n] ");
-                io::stdout().flush().unwrap();
-                let mut answer = String::new();
-                io::stdin().read_line(&mut answer).unwrap();
-                if answer.trim() != "y" {
-                    println!("Not removing");
-                    exit(0);
-                }
-
-                remove_entity(&conn, reminders);
             }
-            RemoveEntity::Note { content } => {
-                let mut notes = match notes::get_by_content(&conn, content) {
-                    Ok(notes) => notes,
-                    Err(e) => {
-                        eprintln!("Error while fetching notes: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
-                };
-
-                if notes.len() > 1 {
-                    notes = match handle_id_selection::<Note>(notes) {
+            RemoveEntity::Note {
+                ids,
+                confirm,
+                content,
+            } => {
+                let ids = ids.ids();
+                if !ids.is_empty() {
+                    remove_by_ids::<Note>(&conn, &mut load, &ids, "note", confirm.yes);
+                } else {
+                    let content = content.unwrap_or_default();
+                    let notes = match notes::get_by_content(&conn, &mut load, content) {
                         Ok(notes) => notes,
-                        Err(e) => {
-                            eprintln!("{}", e.message);
-                            exit(exitcode::DATAERR);
-                        }
-                    }
-                }
-
-                let note = &notes[0];
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Note), e)),
+                    };
 
-                println!("{}", note);
-                print!("Do you want to remove this note? [y/n] ");
-                io::stdout().flush().unwrap();
-                let mut answer = String::new();
-                io::stdin().read_line(&mut answer).unwrap();
-                if answer.trim() != "y" {
-                    println!("Not removing");
-                    exit(exitcode::OK);
+                    remove_matches(&conn, notes, "note", &confirm);
                 }
-
-                remove_entity(&conn, note);
             }
         },
         Commands::List(list) => match list.entity {
-            ListEntity::People { name } => {
-                let people: Vec<Person>;
+            ListEntity::People { name, table } => {
+                let mut people: Vec<Person>;
                 if let Some(name) = name {
-                    people = match people::get_by_name(&conn, name) {
+                    people = match people::get_by_name(&conn, &mut load, name) {
                         Ok(people) => people,
-                        Err(e) => {
-                            eprintln!("Error while fetching person: {:#?}", e);
-                            exit(exitcode::DATAERR);
-                        }
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Person), e)),
                     }
                 } else {
-                    people = match Person::get_all(&conn) {
-                        Ok(people) => people.iter().map(|p| *p.to_owned()).collect(),
-                        Err(e) => {
-                            eprintln!("Error while fetching person: {:#?}", e);
-                            exit(exitcode::DATAERR);
-                        }
+                    people = match Person::get_all(&conn, &mut load) {
+                        Ok(people) => people,
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Person), e)),
                     };
                 }
 
-                for person in people.iter() {
-                    println!("{}", person);
-                }
+                print_list(args.format, &mut people, &table, &config.date_format);
             }
             ListEntity::Activities {
                 name,
                 person,
                 content,
                 activity_type,
+                table,
             } => {
-                let activities = if [
+                let mut activities = if [
                     name.clone(),
                     person.clone(),
                     content.clone(),
@@ -677,82 +1119,58 @@ fn main() {
                 .iter()
                 .all(Option::is_none)
                 {
-                    match Activity::get_all(&conn) {
-                        Ok(activities) => {
-                            activities.iter().map(|a| *a.to_owned()).collect::<Vec<_>>()
-                        }
-                        Err(e) => {
-                            eprintln!("Error while fetching activities: {:#?}", e);
-                            exit(exitcode::DATAERR);
-                        }
+                    match Activity::get_all(&conn, &mut load) {
+                        Ok(activities) => activities,
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Activity), e)),
                     }
                 } else {
-                    match Activity::get(&conn, name, person, content, activity_type) {
+                    match Activity::get(&conn, &mut load, name, person, content, activity_type) {
                         Ok(activities) => activities,
-                        Err(e) => {
-                            eprintln!("Error while fetching activities: {:#?}", e);
-                            exit(exitcode::DATAERR);
-                        }
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Activity), e)),
                     }
                 };
-                for activity in activities.iter() {
-                    println!("{}", activity);
-                }
+                print_list(args.format, &mut activities, &table, &config.date_format);
             }
-            ListEntity::Reminders { name, include_past } => {
-                let reminders: Vec<Reminder>;
+            ListEntity::Reminders {
+                name,
+                include_done,
+                table,
+            } => {
+                let mut reminders: Vec<Reminder>;
                 if let Some(name) = name {
-                    reminders = match reminders::get_by_name(&conn, &name, None) {
+                    reminders = match reminders::get_by_name(&conn, &mut load, &name, None) {
                         Ok(reminders) => reminders,
-                        Err(e) => {
-                            eprintln!("Error while fetching reminders: {:#?}", e);
-                            exit(exitcode::DATAERR);
-                        }
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Reminder), e)),
                     }
                 } else {
-                    reminders = match Reminder::get_all_filtered(&conn, include_past) {
+                    reminders = match Reminder::get_all_filtered(&conn, &mut load, include_done) {
                         Ok(reminders) => reminders,
-                        Err(e) => {
-                            eprintln!("Error while fetching reminders: {:#?}", e);
-                            exit(exitcode::DATAERR);
-                        }
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Reminder), e)),
                     };
                 };
 
-                for reminder in reminders.iter() {
-                    println!("{}", reminder);
-                }
+                print_list(args.format, &mut reminders, &table, &config.date_format);
             }
-            ListEntity::Notes { content } => {
-                let notes: Vec<Note>;
+            ListEntity::Notes { content, table } => {
+                let mut notes: Vec<Note>;
                 if let Some(content) = content {
-                    notes = match notes::get_by_content(&conn, content) {
+                    notes = match notes::get_by_content(&conn, &mut load, content) {
                         Ok(notes) => notes,
-                        Err(e) => {
-                            eprintln!("Error while fetching notes: {:#?}", e);
-                            exit(exitcode::DATAERR);
-                        }
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Note), e)),
                     }
                 } else {
-                    notes = match Note::get_all(&conn) {
-                        Ok(notes) => notes.iter().map(|n| *n.to_owned()).collect::<Vec<_>>(),
-                        Err(e) => {
-                            eprintln!("Error while fetching notes: {:#?}", e);
-                            exit(exitcode::DATAERR);
-                        }
+                    notes = match Note::get_all(&conn, &mut load) {
+                        Ok(notes) => notes,
+                        Err(e) => fail(db_error(Operation::List, Some(EntityKind::Note), e)),
                     }
                 }
-                for note in notes.iter() {
-                    println!("{}", note);
-                }
+                print_list(args.format, &mut notes, &table, &config.date_format);
             }
-            ListEntity::Events { days } => {
-                let mut events = match Event::get_all(&conn, days) {
+            ListEntity::Events { days, table } => {
+                let days = days.unwrap_or(config.events_days);
+                let mut events = match Event::get_all(&conn, &mut load, days) {
                     Ok(events) => events,
-                    Err(e) => {
-                        eprintln!("Error while fetching events: {:#?}", e);
-                        exit(exitcode::DATAERR);
-                    }
+                    Err(e) => fail(e),
                 };
 
                 // Sort events by date (month and day)
@@ -761,24 +1179,20 @@ fn main() {
                     let b_md = b.date.format("%m-%d").to_string();
                     a_md.cmp(&b_md)
                 });
-                for event in events {
-                    println!("{}", event);
-                }
+                print_list(args.format, &mut events, &table, &config.date_format);
             }
         },
         Commands::Ics(ics) => {
-            let events = match Event::get_all(&conn, 0) {
+            let events = match Event::get_all(&conn, &mut load, 0) {
                 Ok(events) => events,
-                Err(e) => {
-                    eprintln!("Error while fetching events: {:#?}", e);
-                    exit(exitcode::DATAERR);
-                }
+                Err(e) => fail(e),
             };
             let mut calendar = ICalendar::new("2.0", "ics-rs");
+            let mut timezones = cli::calendar::Timezones::default();
 
             for event in events {
                 let uuid = Uuid::new_v4();
-                let dtstamp = chrono::Local::now().format("%Y%m%dT%H%M%SZ").to_string();
+                let dtstamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
                 match event.details {
                     EventType::Person(person) => {
                         if !ics.birthdays && !ics.all {
@@ -801,7 +1215,22 @@ fn main() {
                             continue;
                         }
                         let mut todo = ToDo::new(uuid.to_string(), dtstamp);
-                        let dtdue = format!("{}T090000", event.date.format("%Y%m%d"));
+                        let moment = reminder.moment();
+                        // Recurring reminders start at the occurrence they are
+                        // waiting on, which is due later when it was snoozed
+                        let occurrence = reminder
+                            .due(chrono::Local::now().date_naive())
+                            .map_or(event.date, |due| due.occurrence);
+                        timezones.add(&moment, occurrence);
+                        timezones.add(&moment, event.date);
+                        if let Some(rule) = &reminder.recurring {
+                            let rule = rule.without_count(reminder.date);
+                            todo.push(cli::calendar::dtstart(&moment, occurrence));
+                            todo.push(cli::calendar::rrule(&rule, &moment));
+                            for exdate in reminder.exdates.iter().filter(|d| **d > occurrence) {
+                                todo.push(cli::calendar::exdate(&moment, *exdate));
+                            }
+                        }
                         todo.push(Summary::new(reminder.name));
                         todo.push(Comment::new(
                             reminder
@@ -809,18 +1238,235 @@ fn main() {
                                 .unwrap_or_else(|| String::from("[Empty]")),
                         ));
                         todo.push(Status::needs_action());
-                        todo.push(Due::new(dtdue));
+                        todo.push(cli::calendar::due(&moment, event.date));
                         calendar.add_todo(todo);
                     }
                 }
             }
-            match calendar.save_file("data/calendar.ics") {
-                Ok(_) => println!("Saved to data/calendar.ics"),
+            // Reminders with nothing left to do are not events, but are kept
+            // as completed to-dos
+            if ics.reminders || ics.all {
+                let reminders = match Reminder::get_all_filtered(&conn, &mut load, true) {
+                    Ok(reminders) => reminders,
+                    Err(e) => fail(db_error(Operation::List, Some(EntityKind::Reminder), e)),
+                };
+                let today = chrono::Local::now().date_naive();
+                for reminder in reminders.into_iter().filter(|r| r.due(today).is_none()) {
+                    let last = reminder
+                        .history
+                        .iter()
+                        .map(|o| o.date)
+                        .max()
+                        .unwrap_or(reminder.date);
+                    let moment = reminder.moment();
+                    timezones.add(&moment, last);
+                    let mut todo = ToDo::new(
+                        Uuid::new_v4().to_string(),
+                        chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+                    );
+                    todo.push(Summary::new(reminder.name));
+                    todo.push(Comment::new(
+                        reminder
+                            .description
+                            .unwrap_or_else(|| String::from("[Empty]")),
+                    ));
+                    todo.push(Status::completed());
+                    todo.push(cli::calendar::due(&moment, last));
+                    calendar.add_todo(todo);
+                }
+            }
+            match timezones.components() {
+                Ok(components) => {
+                    for timezone in components {
+                        calendar.add_timezone(timezone);
+                    }
+                }
                 Err(e) => {
-                    eprintln!("Error while saving to data/calendar.ics: {:#?}", e);
+                    eprintln!("Error describing timezones: {}", e);
+                    exit(exitcode::DATAERR);
+                }
+            }
+            let ics_path = match config.ics_path(&profile) {
+                Ok(ics_path) => ics_path,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(exitcode::CONFIG);
+                }
+            };
+            if let Some(parent) = ics_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                if let Err(e) = std::fs::create_dir_all(parent) {
+                    eprintln!("Error creating {}: {}", parent.display(), e);
+                    exit(exitcode::CANTCREAT);
+                }
+            }
+            match calendar.save_file(&ics_path) {
+                Ok(_) => println!("Saved to {}", ics_path.display()),
+                Err(e) => {
+                    eprintln!("Error while saving to {}: {}", ics_path.display(), e);
                     exit(exitcode::SOFTWARE);
                 }
             };
         }
+        Commands::Reminder(reminder_args) => {
+            let res = match reminder_args.command {
+                ReminderCommand::Done { id, date } => {
+                    cli::reminder::done(&conn, &mut load, id, date)
+                }
+                ReminderCommand::Skip { id, date } => {
+                    cli::reminder::skip(&conn, &mut load, id, date)
+                }
+                ReminderCommand::Snooze { id, until } => {
+                    cli::reminder::snooze(&conn, &mut load, id, until)
+                }
+            };
+            if let Err(e) = res {
+                fail(e);
+            }
+        }
+        Commands::Notify {
+            daemon,
+            interval,
+            days,
+        } => {
+            if let Err(e) =
+                cli::notify::run(&conn, &mut load, &config.notify, daemon, interval, days)
+            {
+                fail(e);
+            }
+        }
+        Commands::Doctor { fix, table } => {
+            let mut problems = match doctor::diagnose(&conn, fix) {
+                Ok(problems) => problems,
+                Err(e) => fail(db_error(Operation::Check, None, e)),
+            };
+            if args.format == Format::Table && problems.is_empty() {
+                println!("No problems found");
+                return;
+            }
+            print_list(args.format, &mut problems, &table, &config.date_format);
+            let unfixed = problems.iter().filter(|p| !p.fixed).count();
+            if fix {
+                eprintln!(
+                    "Fixed {} of {} problems",
+                    problems.len() - unfixed,
+                    problems.len()
+                );
+            } else if unfixed > 0 {
+                eprintln!(
+                    "Found {} problems, run prm doctor --fix to repair them",
+                    unfixed
+                );
+            }
+            if unfixed > 0 {
+                exit(exitcode::DATAERR);
+            }
+        }
+        Commands::Undo { count } => replay_journal(&conn, count, "undo", "Undid", journal::undo),
+        Commands::Redo { count } => replay_journal(&conn, count, "redo", "Redid", journal::redo),
+        Commands::History {
+            entity: Some(entity),
+            id: Some(id),
+            limit,
+            table,
+        } => {
+            let mut entries = match audit::for_entity(&conn, entity, id, limit) {
+                Ok(entries) => entries,
+                Err(e) => fail(db_error(Operation::History, None, e)),
+            };
+            if args.format == Format::Table && entries.is_empty() {
+                println!("No history for {} {}", entity.as_ref(), id);
+            } else {
+                print_list(args.format, &mut entries, &table, &config.date_format);
+            }
+        }
+        Commands::History { limit, table, .. } => {
+            let mut entries = match journal::history(&conn, limit) {
+                Ok(entries) => entries,
+                Err(e) => fail(db_error(Operation::History, None, e)),
+            };
+            if args.format == Format::Table && entries.is_empty() {
+                println!("No history");
+            } else {
+                print_list(args.format, &mut entries, &table, &config.date_format);
+            }
+        }
+        Commands::Trash(trash_args) => match trash_args.command {
+            TrashCommand::List { table } => {
+                let mut items = match trash::list(&conn) {
+                    Ok(items) => items,
+                    Err(e) => fail(db_error(Operation::Trash, None, e)),
+                };
+                if args.format == Format::Table && items.is_empty() {
+                    println!("Trash is empty");
+                } else {
+                    print_list(args.format, &mut items, &table, &config.date_format);
+                }
+            }
+            TrashCommand::Restore { entity, id } => match trash::restore(&conn, entity, id) {
+                Ok(true) => println!("Restored {} {}", entity.as_ref(), id),
+                Ok(false) => {
+                    eprintln!("No {} with id {} in the trash", entity.as_ref(), id);
+                    exit(exitcode::NOINPUT);
+                }
+                Err(e) => fail(
+                    DbSnafu {
+                        operation: Operation::Restore,
+                        entity,
+                        id,
+                    }
+                    .into_error(e),
+                ),
+            },
+            TrashCommand::Purge { older_than, yes } => {
+                let question = match older_than {
+                    Some(days) => format!(
+                        "Permanently delete everything trashed more than {} days ago?",
+                        days
+                    ),
+                    None => String::from("Permanently delete everything in the trash?"),
+                };
+                if let Err(e) = confirm(&question, yes) {
+                    match e {
+                        SelectionError::Aborted => println!("Not purging"),
+                        _ => eprintln!("{}", e),
+                    }
+                    exit(selection_exit_code(&e));
+                }
+                match trash::purge(&conn, older_than) {
+                    Ok(purged) => println!("Purged {} entries", purged),
+                    Err(e) => fail(db_error(Operation::Purge, None, e)),
+                }
+            }
+        },
+        Commands::Search {
+            query,
+            limit,
+            table,
+        } => {
+            // Only highlight with ANSI codes when a human is reading a table
+            let highlight = if args.format == Format::Table && io::stdout().is_terminal() {
+                ("\x1b[1m", "\x1b[0m")
+            } else {
+                ("[", "]")
+            };
+            let query = query.join(" ");
+            let mut results = match search::search(&conn, &query, limit, highlight) {
+                Ok(results) => results,
+                Err(e) => fail(db_error(Operation::Search, None, e)),
+            };
+            if args.format == Format::Table && results.is_empty() {
+                println!("No results found");
+            } else {
+                print_list(args.format, &mut results, &table, &config.date_format);
+            }
+        }
+        Commands::Profile(_) => {
+            unreachable!("profile commands are handled before opening the database")
+        }
+    }
+
+    if let Err(e) = journal::prune(&conn, config.journal_retention) {
+        fail(db_error(Operation::Prune, None, e));
     }
+    cli::output::warn_skipped(&mut load);
 }
@@ -0,0 +1,457 @@
+pub mod smtp;
+
+use std::fmt;
+use std::io;
+use std::process::{Command, ExitStatus};
+
+use chrono::prelude::*;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use snafu::prelude::*;
+use strum_macros::AsRefStr;
+
+use crate::config::{Backend, BackendConfig};
+use crate::db::loader::LoadContext;
+use crate::db_interface::DbOperationsError;
+use crate::entities::event::{Event, EventError, EventType};
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum NotifyError {
+    #[snafu(display("Could not load events: {}", source))]
+    Events { source: EventError },
+    #[snafu(display("Could not record a sent notification: {}", source))]
+    Record { source: DbOperationsError },
+    #[snafu(display("Could not run {}: {}", command, source))]
+    Spawn { command: String, source: io::Error },
+    #[snafu(display("{} failed with {}", command, status))]
+    CommandStatus { command: String, status: ExitStatus },
+    #[snafu(display("Could not talk to SMTP server {}: {}", server, source))]
+    SmtpConnection { server: String, source: io::Error },
+    #[snafu(display("SMTP server {} replied: {}", server, reply))]
+    SmtpReply { server: String, reply: String },
+    #[snafu(display("Invalid mail address {:?}", address))]
+    InvalidAddress { address: String },
+    #[snafu(display("More than one notification backend is named {}", name))]
+    DuplicateBackend { name: String },
+    #[snafu(display("{} notifications could not be delivered", count))]
+    Undelivered { count: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Birthday,
+    Reminder,
+}
+
+// Events are notified about once while they are coming up and once more
+// when they are due
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Upcoming,
+    Due,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub entity_id: u64,
+    pub date: NaiveDate,
+    pub stage: Stage,
+    pub title: String,
+    pub body: String,
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}\n{}\n",
+            self.stage.as_ref(),
+            self.title,
+            self.body
+        )
+    }
+}
+
+// The birthday on or after `today`. People born on the 29th of February
+// celebrate on the 28th in other years.
+fn next_birthday(birthday: NaiveDate, today: NaiveDate) -> Option<NaiveDate> {
+    [today.year(), today.year() + 1]
+        .into_iter()
+        .find_map(|year| {
+            let date = birthday
+                .with_year(year)
+                .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))?;
+            (date >= today).then_some(date)
+        })
+}
+
+// The notifications for events that are due at `now` or come up within
+// `days`, whether they were sent or not
+pub fn pending(
+    conn: &Connection,
+    load: &mut LoadContext,
+    now: DateTime<Local>,
+    days: u64,
+) -> Result<Vec<Notification>, NotifyError> {
+    let today = now.date_naive();
+    let limit = today + chrono::Duration::days(days as i64);
+    let events = Event::get_all(conn, load, days.max(1)).context(EventsSnafu)?;
+    let mut notifications = vec![];
+    for event in events {
+        match event.details {
+            EventType::Person(person) => {
+                let date = match next_birthday(event.date, today) {
+                    Some(date) if date <= limit => date,
+                    _ => continue,
+                };
+                notifications.push(Notification {
+                    kind: NotificationKind::Birthday,
+                    entity_id: person.id,
+                    date,
+                    stage: if date == today {
+                        Stage::Due
+                    } else {
+                        Stage::Upcoming
+                    },
+                    title: format!("{}'s birthday", person.name),
+                    body: format!("{}'s birthday is on {}", person.name, date),
+                });
+            }
+            EventType::Reminder(reminder) => {
+                if event.date > limit {
+                    continue;
+                }
+                let moment = crate::entities::datetime::Moment {
+                    date: event.date,
+                    ..reminder.moment()
+                };
+                // Timed reminders are due at their time, the others all day
+                let due = match moment.at() {
+                    Some(at) => at <= now,
+                    None => event.date <= today,
+                };
+                let mut body = format!("Due {}", moment);
+                if let Some(description) = reminder.description.filter(|d| !d.is_empty()) {
+                    body.push('\n');
+                    body.push_str(&description);
+                }
+                if !reminder.people.is_empty() {
+                    let names: Vec<&str> =
+                        reminder.people.iter().map(|p| p.name.as_str()).collect();
+                    body.push_str(&format!("\nWith {}", names.join(", ")));
+                }
+                notifications.push(Notification {
+                    kind: NotificationKind::Reminder,
+                    entity_id: reminder.id,
+                    date: event.date,
+                    stage: if due { Stage::Due } else { Stage::Upcoming },
+                    title: reminder.name,
+                    body,
+                });
+            }
+        }
+    }
+    notifications.sort_by(|a, b| (a.date, &a.title).cmp(&(b.date, &b.title)));
+    Ok(notifications)
+}
+
+fn query_error(e: rusqlite::Error) -> NotifyError {
+    NotifyError::Record {
+        source: DbOperationsError::QueryError { sqlite_error: e },
+    }
+}
+
+fn fired(
+    conn: &Connection,
+    notification: &Notification,
+    backend: &str,
+) -> Result<bool, NotifyError> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM notifications
+            WHERE kind = ?1 AND entity_id = ?2 AND date = ?3 AND stage = ?4 AND backend = ?5",
+        params![
+            notification.kind.as_ref(),
+            notification.entity_id,
+            notification.date.to_string(),
+            notification.stage.as_ref(),
+            backend
+        ],
+        |row| row.get::<_, u64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(query_error)
+}
+
+fn record(
+    conn: &Connection,
+    notification: &Notification,
+    backend: &str,
+) -> Result<(), NotifyError> {
+    conn.execute(
+        "INSERT OR IGNORE INTO notifications (kind, entity_id, date, stage, backend, fired_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))",
+        params![
+            notification.kind.as_ref(),
+            notification.entity_id,
+            notification.date.to_string(),
+            notification.stage.as_ref(),
+            backend
+        ],
+    )
+    .map_err(query_error)?;
+    Ok(())
+}
+
+// A way of delivering notifications
+pub trait Notifier {
+    // Identifies the backend in the log of sent notifications
+    fn name(&self) -> String;
+    fn send(&self, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+pub struct Stdout {
+    pub name: String,
+}
+
+impl Notifier for Stdout {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        println!("{}", notification);
+        Ok(())
+    }
+}
+
+// Runs a shell command for every notification
+pub struct Hook {
+    pub name: String,
+    pub command: String,
+}
+
+impl Notifier for Hook {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("PRM_KIND", notification.kind.as_ref())
+            .env("PRM_ID", notification.entity_id.to_string())
+            .env("PRM_DATE", notification.date.to_string())
+            .env("PRM_STAGE", notification.stage.as_ref())
+            .env("PRM_TITLE", &notification.title)
+            .env("PRM_BODY", &notification.body)
+            .status()
+            .context(SpawnSnafu {
+                command: &self.command,
+            })?;
+        ensure!(
+            status.success(),
+            CommandStatusSnafu {
+                command: &self.command,
+                status
+            }
+        );
+        Ok(())
+    }
+}
+
+// The notifiers of the configured backends, whose names have to be unique
+// for each of them to keep its own log of sent notifications
+pub fn notifiers(backends: &[BackendConfig]) -> Result<Vec<Box<dyn Notifier>>, NotifyError> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = vec![];
+    for backend in backends {
+        let name = backend.name().to_string();
+        ensure!(
+            !notifiers.iter().any(|notifier| notifier.name() == name),
+            DuplicateBackendSnafu { name }
+        );
+        notifiers.push(match &backend.backend {
+            Backend::Stdout => Box::new(Stdout { name }),
+            Backend::Command { command } => Box::new(Hook {
+                name,
+                command: command.clone(),
+            }),
+            Backend::Smtp(config) => Box::new(smtp::Smtp {
+                name,
+                config: config.clone(),
+            }),
+        });
+    }
+    Ok(notifiers)
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub sent: usize,
+    pub failed: usize,
+}
+
+// Sends the pending notifications each backend has not sent yet. Failed
+// deliveries are reported and tried again next time.
+pub fn notify(
+    conn: &Connection,
+    load: &mut LoadContext,
+    notifiers: &[Box<dyn Notifier>],
+    now: DateTime<Local>,
+    days: u64,
+) -> Result<Summary, NotifyError> {
+    let mut summary = Summary::default();
+    for notification in pending(conn, load, now, days)? {
+        for notifier in notifiers {
+            let name = notifier.name();
+            if fired(conn, &notification, &name)? {
+                continue;
+            }
+            match notifier.send(&notification) {
+                Ok(()) => {
+                    record(conn, &notification, &name)?;
+                    summary.sent += 1;
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Warning: could not notify about {} {}: {}",
+                        notification.kind.as_ref(),
+                        notification.entity_id,
+                        e
+                    );
+                    summary.failed += 1;
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    use crate::db_interface::DbOperations;
+    use crate::entities::reminder::Reminder;
+
+    struct Recorder {
+        sent: RefCell<Vec<(String, Stage)>>,
+        fail: bool,
+    }
+
+    impl Notifier for Recorder {
+        fn name(&self) -> String {
+            String::from("recorder")
+        }
+
+        fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+            if self.fail {
+                return UndeliveredSnafu { count: 1_usize }.fail();
+            }
+            self.sent
+                .borrow_mut()
+                .push((notification.title.clone(), notification.stage));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_notify() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::migrate(&conn).unwrap();
+        let now = Local::now();
+        let today = now.date_naive();
+        for (name, days) in [("Call", 0), ("Write", 1), ("Later", 5)] {
+            Reminder::new(
+                0,
+                String::from(name),
+                today + chrono::Duration::days(days),
+                None,
+                None,
+                vec![],
+            )
+            .add(&conn)
+            .unwrap();
+        }
+
+        let failing: Vec<Box<dyn Notifier>> = vec![Box::new(Recorder {
+            sent: RefCell::new(vec![]),
+            fail: true,
+        })];
+        assert_eq!(
+            Summary { sent: 0, failed: 2 },
+            notify(&conn, &mut LoadContext::default(), &failing, now, 1).unwrap()
+        );
+
+        let recorder = Recorder {
+            sent: RefCell::new(vec![]),
+            fail: false,
+        };
+        let notifiers: Vec<Box<dyn Notifier>> = vec![Box::new(recorder)];
+        assert_eq!(
+            Summary { sent: 2, failed: 0 },
+            notify(&conn, &mut LoadContext::default(), &notifiers, now, 1).unwrap()
+        );
+        // Nothing is sent twice
+        assert_eq!(
+            Summary::default(),
+            notify(&conn, &mut LoadContext::default(), &notifiers, now, 1).unwrap()
+        );
+        // Tomorrow's reminder is due tomorrow, and notified about again
+        let tomorrow = now + chrono::Duration::days(1);
+        assert_eq!(
+            Summary { sent: 1, failed: 0 },
+            notify(&conn, &mut LoadContext::default(), &notifiers, tomorrow, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_birthday() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            Some(date(2026, 10, 20)),
+            next_birthday(date(1990, 10, 20), date(2026, 10, 18))
+        );
+        assert_eq!(
+            Some(date(2027, 1, 2)),
+            next_birthday(date(1990, 1, 2), date(2026, 10, 18))
+        );
+        assert_eq!(
+            Some(date(2027, 2, 28)),
+            next_birthday(date(2000, 2, 29), date(2026, 10, 18))
+        );
+    }
+
+    #[test]
+    fn test_notifiers() {
+        let command = |name: Option<&str>| BackendConfig {
+            name: name.map(String::from),
+            backend: Backend::Command {
+                command: String::from("true"),
+            },
+        };
+        let names = |backends: &[BackendConfig]| {
+            notifiers(backends).map(|n| n.iter().map(|n| n.name()).collect::<Vec<_>>())
+        };
+        assert_eq!(
+            vec!["stdout", "command", "other"],
+            names(&[
+                Backend::Stdout.into(),
+                command(None),
+                command(Some("other"))
+            ])
+            .unwrap()
+        );
+        match names(&[command(None), command(None)]) {
+            Err(NotifyError::DuplicateBackend { name }) => assert_eq!("command", name),
+            other => panic!("expected a duplicate backend, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use chrono::Local;
+use snafu::prelude::*;
+
+use crate::config::SmtpConfig;
+use crate::notify::{
+    InvalidAddressSnafu, Notification, Notifier, NotifyError, SmtpConnectionSnafu, SmtpReplySnafu,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// Mails notifications through a server that takes mail without
+// authentication or TLS, like a relay on the same machine
+pub struct Smtp {
+    pub name: String,
+    pub config: SmtpConfig,
+}
+
+struct Session {
+    server: String,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Session {
+    fn connect(server: String) -> Result<Session, NotifyError> {
+        let writer =
+            TcpStream::connect(&server).context(SmtpConnectionSnafu { server: &server })?;
+        let setup = writer
+            .set_read_timeout(Some(TIMEOUT))
+            .and_then(|_| writer.set_write_timeout(Some(TIMEOUT)))
+            .and_then(|_| writer.try_clone());
+        let reader = BufReader::new(setup.context(SmtpConnectionSnafu { server: &server })?);
+        let mut session = Session {
+            server,
+            reader,
+            writer,
+        };
+        session.expect(&[220])?;
+        Ok(session)
+    }
+
+    // Reads a reply, which can span several lines, and checks its code
+    fn expect(&mut self, codes: &[u16]) -> Result<(), NotifyError> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .context(SmtpConnectionSnafu {
+                    server: &self.server,
+                })?;
+            reply.push_str(line.trim_end());
+            // The last line has a space after the code
+            if read == 0 || line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+            reply.push(' ');
+        }
+        let code = reply.get(..3).and_then(|code| code.parse::<u16>().ok());
+        match code {
+            Some(code) if codes.contains(&code) => Ok(()),
+            _ => SmtpReplySnafu {
+                server: &self.server,
+                reply,
+            }
+            .fail(),
+        }
+    }
+
+    fn command(&mut self, command: &str, codes: &[u16]) -> Result<(), NotifyError> {
+        self.writer
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .context(SmtpConnectionSnafu {
+                server: &self.server,
+            })?;
+        self.expect(codes)
+    }
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::new();
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+// A header value on a single line, encoded when it is not plain ASCII.
+// Line breaks would start new headers, so they are folded into spaces.
+fn header(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", base64(value.as_bytes()))
+    }
+}
+
+// Addresses end up in SMTP commands and headers, where a line break would
+// smuggle in commands or headers of its own
+fn check_address(address: &str) -> Result<(), NotifyError> {
+    ensure!(
+        !address.is_empty() && !address.contains(['\r', '\n', '<', '>']),
+        InvalidAddressSnafu { address }
+    );
+    Ok(())
+}
+
+// The message, with CRLF line endings and lines starting with a dot
+// doubled so they don't end the data
+pub fn message(config: &SmtpConfig, notification: &Notification) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        config.from,
+        config.to.join(", "),
+        header(&notification.title),
+        Local::now().to_rfc2822()
+    );
+    for line in notification.body.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message
+}
+
+impl Notifier for Smtp {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        check_address(&self.config.from)?;
+        for to in self.config.to.iter() {
+            check_address(to)?;
+        }
+        let server = format!("{}:{}", self.config.host, self.config.port);
+        let mut session = Session::connect(server)?;
+        session.command("EHLO localhost", &[250])?;
+        session.command(&format!("MAIL FROM:<{}>", self.config.from), &[250])?;
+        for to in self.config.to.iter() {
+            session.command(&format!("RCPT TO:<{}>", to), &[250, 251])?;
+        }
+        session.command("DATA", &[354])?;
+        let message = message(&self.config, notification);
+        session.command(&format!("{}.", message), &[250])?;
+        // The message is delivered once the server accepted it, so a failed
+        // goodbye must not send it again
+        let _ = session.command("QUIT", &[221]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    use chrono::NaiveDate;
+
+    use crate::notify::{NotificationKind, Stage};
+
+    // Plays a mail server for a single message and returns what it was sent
+    fn server(listener: TcpListener, rcpt: &'static str) -> thread::JoinHandle<Vec<String>> {
+        server_with_quit(listener, rcpt, "221 bye")
+    }
+
+    fn server_with_quit(
+        listener: TcpListener,
+        rcpt: &'static str,
+        quit: &'static str,
+    ) -> thread::JoinHandle<Vec<String>> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut received = vec![];
+            writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+            let mut data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end_matches("\r\n").to_string();
+                received.push(line.clone());
+                let reply = if data {
+                    if line != "." {
+                        continue;
+                    }
+                    data = false;
+                    "250 queued"
+                } else if line.starts_with("EHLO") {
+                    "250-localhost\r\n250 8BITMIME"
+                } else if line.starts_with("RCPT") {
+                    rcpt
+                } else if line == "DATA" {
+                    data = true;
+                    "354 go ahead"
+                } else if line == "QUIT" {
+                    quit
+                } else {
+                    "250 ok"
+                };
+                writer
+                    .write_all(format!("{}\r\n", reply).as_bytes())
+                    .unwrap();
+                if line == "QUIT" || reply.starts_with('5') {
+                    break;
+                }
+            }
+            received
+        })
+    }
+
+    fn smtp(port: u16) -> Smtp {
+        Smtp {
+            name: String::from("smtp"),
+            config: SmtpConfig {
+                host: String::from("127.0.0.1"),
+                port,
+                from: String::from("prm@example.com"),
+                to: vec![String::from("me@example.com")],
+            },
+        }
+    }
+
+    #[test]
+    fn test_send() {
+        let notification = Notification {
+            kind: NotificationKind::Reminder,
+            entity_id: 1,
+            date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            stage: Stage::Due,
+            title: String::from("Ligar à Ana"),
+            body: String::from("Due 2024-05-01 18:30 Europe/Lisbon\n.and bring cake"),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = server(listener, "250 ok");
+        smtp(port).send(&notification).unwrap();
+        let received = received.join().unwrap();
+        assert_eq!("EHLO localhost", received[0]);
+        assert_eq!("MAIL FROM:<prm@example.com>", received[1]);
+        assert_eq!("RCPT TO:<me@example.com>", received[2]);
+        assert!(received.contains(&String::from("Subject: =?UTF-8?B?TGlnYXIgw6AgQW5h?=")));
+        assert!(received.contains(&String::from("..and bring cake")));
+        assert_eq!(vec![".", "QUIT"], received[received.len() - 2..]);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = server(listener, "550 no such user");
+        match smtp(port).send(&notification) {
+            Err(NotifyError::SmtpReply { reply, .. }) => assert_eq!("550 no such user", reply),
+            other => panic!("expected a rejection, got {:?}", other),
+        }
+        received.join().unwrap();
+
+        // A server that fails to say goodbye still got the message
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = server_with_quit(listener, "250 ok", "421 closing");
+        smtp(port).send(&notification).unwrap();
+        received.join().unwrap();
+    }
+
+    #[test]
+    fn test_header_injection() {
+        let notification = Notification {
+            kind: NotificationKind::Reminder,
+            entity_id: 1,
+            date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            stage: Stage::Due,
+            title: String::from("Call\r\nBcc: someone@example.com"),
+            body: String::new(),
+        };
+        let message = message(&smtp(25).config, &notification);
+        assert!(message.contains("Subject: Call  Bcc: someone@example.com\r\n"));
+        assert!(!message.contains("\r\nBcc:"));
+
+        let mut injected = smtp(25);
+        injected.config.to = vec![String::from(
+            "me@example.com>\r\nRCPT TO:<someone@example.com",
+        )];
+        match injected.send(&notification) {
+            Err(NotifyError::InvalidAddress { .. }) => (),
+            other => panic!("expected an invalid address, got {:?}", other),
+        }
+    }
+}
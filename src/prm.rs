@@ -0,0 +1,445 @@
+#[cfg(feature = "async")]
+pub mod asynchronous;
+
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::Connection;
+use snafu::ResultExt;
+
+use crate::db::entity_kind::EntityKind;
+use crate::db::journal;
+use crate::db::loader::{self, LoadContext, Relations, SkippedRow};
+use crate::db::pool::ConnectionManager;
+use crate::db_interface::{DbOperations, DbOperationsError};
+use crate::entities::activity::Activity;
+use crate::entities::note::Note;
+use crate::entities::person::Person;
+use crate::entities::reminder::Reminder;
+use crate::entities::shallow::{ShallowActivity, ShallowNote, ShallowPerson, ShallowReminder};
+use crate::entities::{Entities, Entity};
+use crate::error::{DbSnafu, Error, Operation};
+
+pub type PooledConnection = r2d2::PooledConnection<ConnectionManager>;
+
+fn pool_error(e: r2d2::Error) -> Error {
+    Error::Db {
+        operation: Operation::Open,
+        entity: None,
+        id: None,
+        source: DbOperationsError::PoolError { pool_error: e },
+    }
+}
+
+// A prm database for library consumers. It is cheap to clone and can be
+// shared between threads: every call takes a connection from a pool, so
+// concurrent reads don't block each other and writes wait for their turn.
+//
+// let prm = Prm::open("prm.db")?;
+// let people = prm.people().all()?;
+#[derive(Clone)]
+pub struct Prm {
+    pool: r2d2::Pool<ConnectionManager>,
+    strict: bool,
+    journal_retention: u64,
+    // Rows the repositories skipped because they could not be read
+    skipped: Arc<Mutex<Vec<SkippedRow>>>,
+}
+
+impl Prm {
+    // Opens the database at `path`, creating and migrating it as needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Prm, Error> {
+        Prm::build(
+            ConnectionManager::file(path.as_ref()),
+            r2d2::Pool::builder(),
+        )
+    }
+
+    // A private, empty database that lives as long as the `Prm` and its clones
+    pub fn open_in_memory() -> Result<Prm, Error> {
+        Prm::build(
+            ConnectionManager::memory(),
+            r2d2::Pool::builder()
+                .max_size(1)
+                .idle_timeout(None)
+                .max_lifetime(None),
+        )
+    }
+
+    fn build(
+        manager: ConnectionManager,
+        builder: r2d2::Builder<ConnectionManager>,
+    ) -> Result<Prm, Error> {
+        let pool = builder.build(manager).map_err(pool_error)?;
+        let conn = pool.get().map_err(pool_error)?;
+        crate::db::migrations::migrate(&conn).context(DbSnafu {
+            operation: Operation::Migrate,
+            entity: None,
+            id: None,
+        })?;
+        Ok(Prm {
+            pool,
+            strict: false,
+            journal_retention: journal::DEFAULT_RETENTION,
+            skipped: Arc::default(),
+        })
+    }
+
+    // Makes loads fail on rows that cannot be read instead of skipping them
+    pub fn strict(self, strict: bool) -> Prm {
+        Prm { strict, ..self }
+    }
+
+    // How many operations the repositories keep for undo, 0 for all of them
+    pub fn journal_retention(self, journal_retention: u64) -> Prm {
+        Prm {
+            journal_retention,
+            ..self
+        }
+    }
+
+    // The rows skipped by the repositories since the last call
+    pub fn take_skipped(&self) -> Vec<SkippedRow> {
+        let mut skipped = self.skipped.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut *skipped)
+    }
+
+    // A connection for the functions in `db` that aren't covered by the
+    // repositories, e.g. search or undo. It returns to the pool when dropped.
+    pub fn connection(&self) -> Result<PooledConnection, Error> {
+        self.pool.get().map_err(pool_error)
+    }
+
+    // A context for loads on a `connection`, as strict as the repositories
+    pub fn load_context(&self) -> LoadContext {
+        LoadContext::new(self.strict)
+    }
+
+    // The repository of any stored entity type
+    pub fn repository<T: Stored>(&self) -> Repository<'_, T> {
+        Repository::new(self)
+    }
+
+    pub fn people(&self) -> Repository<'_, Person> {
+        Repository::new(self)
+    }
+
+    pub fn activities(&self) -> Repository<'_, Activity> {
+        Repository::new(self)
+    }
+
+    pub fn reminders(&self) -> Repository<'_, Reminder> {
+        Repository::new(self)
+    }
+
+    pub fn notes(&self) -> Repository<'_, Note> {
+        Repository::new(self)
+    }
+}
+
+// An entity a `Repository` can load and write
+pub trait Stored: DbOperations + Entity + Sized {
+    const KIND: EntityKind;
+
+    fn from_entities(entity: Entities) -> Option<Self>;
+}
+
+impl Stored for Person {
+    const KIND: EntityKind = EntityKind::Person;
+
+    fn from_entities(entity: Entities) -> Option<Self> {
+        match entity {
+            Entities::Person(person) => Some(person),
+            _ => None,
+        }
+    }
+}
+
+impl Stored for Activity {
+    const KIND: EntityKind = EntityKind::Activity;
+
+    fn from_entities(entity: Entities) -> Option<Self> {
+        match entity {
+            Entities::Activity(activity) => Some(activity),
+            _ => None,
+        }
+    }
+}
+
+impl Stored for Reminder {
+    const KIND: EntityKind = EntityKind::Reminder;
+
+    fn from_entities(entity: Entities) -> Option<Self> {
+        match entity {
+            Entities::Reminder(reminder) => Some(reminder),
+            _ => None,
+        }
+    }
+}
+
+impl Stored for Note {
+    const KIND: EntityKind = EntityKind::Note;
+
+    fn from_entities(entity: Entities) -> Option<Self> {
+        match entity {
+            Entities::Note(note) => Some(note),
+            _ => None,
+        }
+    }
+}
+
+// Typed access to one kind of entity. Entities are loaded with all their
+// relations; use the `shallow` methods to load relations by id only.
+pub struct Repository<'a, T> {
+    prm: &'a Prm,
+    entity: PhantomData<T>,
+}
+
+impl<'a, T> Repository<'a, T> {
+    fn new(prm: &'a Prm) -> Repository<'a, T> {
+        Repository {
+            prm,
+            entity: PhantomData,
+        }
+    }
+
+    // Runs `f` with a pooled connection, adding what was being done to the
+    // error and keeping the rows its loads skipped
+    fn with_connection<R>(
+        &self,
+        operation: Operation,
+        entity: EntityKind,
+        id: Option<u64>,
+        f: impl FnOnce(&Connection, &mut LoadContext) -> Result<R, DbOperationsError>,
+    ) -> Result<R, Error> {
+        let conn = self.prm.connection()?;
+        let mut load = self.prm.load_context();
+        let result = f(&conn, &mut load).context(DbSnafu {
+            operation,
+            entity,
+            id,
+        });
+        self.prm
+            .skipped
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(load.skipped);
+        result
+    }
+}
+
+impl<T: Stored> Repository<'_, T> {
+    pub fn get(&self, id: u64) -> Result<Option<T>, Error> {
+        self.with_connection(Operation::Load, T::KIND, Some(id), |conn, load| {
+            Ok(T::get_by_id(conn, load, id)?.and_then(T::from_entities))
+        })
+    }
+
+    pub fn all(&self) -> Result<Vec<T>, Error> {
+        self.with_connection(Operation::List, T::KIND, None, T::get_all)
+    }
+
+    pub fn add(&self, entity: &T) -> Result<(), Error> {
+        self.with_connection(Operation::Add, T::KIND, None, |conn, _| {
+            entity.add(conn)?;
+            journal::prune(conn, self.prm.journal_retention)
+        })
+    }
+
+    pub fn save(&self, entity: &T) -> Result<(), Error> {
+        self.with_connection(
+            Operation::Save,
+            T::KIND,
+            Some(entity.get_id()),
+            |conn, _| {
+                entity.save(conn)?;
+                journal::prune(conn, self.prm.journal_retention)
+            },
+        )
+    }
+
+    // Moves `entity` to the trash
+    pub fn remove(&self, entity: &T) -> Result<(), Error> {
+        self.with_connection(
+            Operation::Remove,
+            T::KIND,
+            Some(entity.get_id()),
+            |conn, _| {
+                entity.remove(conn)?;
+                journal::prune(conn, self.prm.journal_retention)
+            },
+        )
+    }
+}
+
+impl Repository<'_, Person> {
+    pub fn find_by_name(&self, name: &str) -> Result<Vec<Person>, Error> {
+        self.with_connection(Operation::Search, EntityKind::Person, None, |conn, load| {
+            crate::db_helpers::people::get_by_name(conn, load, name.to_string())
+        })
+    }
+    // `ids` with only the requested relations loaded
+    pub fn load(&self, ids: &[u64], relations: Relations) -> Result<Vec<Person>, Error> {
+        self.with_connection(Operation::Load, EntityKind::Person, None, |conn, load| {
+            loader::people_by_id(conn, load, ids, relations)
+        })
+    }
+
+    pub fn shallow(&self, id: u64) -> Result<Option<ShallowPerson>, Error> {
+        self.with_connection(
+            Operation::Load,
+            EntityKind::Person,
+            Some(id),
+            |conn, load| ShallowPerson::get_by_id(conn, load, id),
+        )
+    }
+
+    pub fn all_shallow(&self) -> Result<Vec<ShallowPerson>, Error> {
+        self.with_connection(
+            Operation::List,
+            EntityKind::Person,
+            None,
+            ShallowPerson::get_all,
+        )
+    }
+}
+
+impl Repository<'_, Activity> {
+    pub fn load(&self, ids: &[u64], relations: Relations) -> Result<Vec<Activity>, Error> {
+        self.with_connection(Operation::Load, EntityKind::Activity, None, |conn, load| {
+            loader::activities_by_id(conn, load, ids, relations)
+        })
+    }
+
+    pub fn shallow(&self, id: u64) -> Result<Option<ShallowActivity>, Error> {
+        self.with_connection(
+            Operation::Load,
+            EntityKind::Activity,
+            Some(id),
+            |conn, load| ShallowActivity::get_by_id(conn, load, id),
+        )
+    }
+
+    pub fn all_shallow(&self) -> Result<Vec<ShallowActivity>, Error> {
+        self.with_connection(
+            Operation::List,
+            EntityKind::Activity,
+            None,
+            ShallowActivity::get_all,
+        )
+    }
+}
+
+impl Repository<'_, Reminder> {
+    pub fn load(&self, ids: &[u64], relations: Relations) -> Result<Vec<Reminder>, Error> {
+        self.with_connection(Operation::Load, EntityKind::Reminder, None, |conn, load| {
+            loader::reminders_by_id(conn, load, ids, relations)
+        })
+    }
+
+    pub fn shallow(&self, id: u64) -> Result<Option<ShallowReminder>, Error> {
+        self.with_connection(
+            Operation::Load,
+            EntityKind::Reminder,
+            Some(id),
+            |conn, load| ShallowReminder::get_by_id(conn, load, id),
+        )
+    }
+
+    pub fn all_shallow(&self) -> Result<Vec<ShallowReminder>, Error> {
+        self.with_connection(
+            Operation::List,
+            EntityKind::Reminder,
+            None,
+            ShallowReminder::get_all,
+        )
+    }
+}
+
+impl Repository<'_, Note> {
+    pub fn load(&self, ids: &[u64], relations: Relations) -> Result<Vec<Note>, Error> {
+        self.with_connection(Operation::Load, EntityKind::Note, None, |conn, load| {
+            loader::notes_by_id(conn, load, ids, relations)
+        })
+    }
+
+    pub fn shallow(&self, id: u64) -> Result<Option<ShallowNote>, Error> {
+        self.with_connection(Operation::Load, EntityKind::Note, Some(id), |conn, load| {
+            ShallowNote::get_by_id(conn, load, id)
+        })
+    }
+
+    pub fn all_shallow(&self) -> Result<Vec<ShallowNote>, Error> {
+        self.with_connection(
+            Operation::List,
+            EntityKind::Note,
+            None,
+            ShallowNote::get_all,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_concurrent_writes() {
+        assert_send_sync::<Prm>();
+        let path = std::env::temp_dir().join(format!("prm-{}.db", uuid::Uuid::new_v4()));
+        let prm = Prm::open(&path).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let prm = prm.clone();
+                scope.spawn(move || {
+                    for i in 0..10 {
+                        let note = Note::new(0, date, format!("{} {}", thread, i), vec![]);
+                        prm.notes().add(&note).unwrap();
+                    }
+                });
+            }
+        });
+
+        let notes = prm.notes().all().unwrap();
+        assert_eq!(40, notes.len());
+        assert_eq!(
+            Some(notes[0].content.clone()),
+            prm.notes().get(notes[0].id).unwrap().map(|n| n.content)
+        );
+        drop(prm);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_skipped_rows() {
+        let prm = Prm::open_in_memory().unwrap();
+        prm.connection()
+            .unwrap()
+            .execute_batch(
+                "INSERT INTO people (name, birthday, deleted) VALUES ('Ana', NULL, FALSE);
+                INSERT INTO people (name, birthday, deleted) VALUES ('Cy', 'soon', FALSE);",
+            )
+            .unwrap();
+
+        assert_eq!(1, prm.people().all().unwrap().len());
+        let skipped = prm.take_skipped();
+        assert_eq!(
+            vec![("people", 2)],
+            skipped
+                .iter()
+                .map(|s| (s.table.as_str(), s.id))
+                .collect::<Vec<_>>()
+        );
+        assert!(prm.take_skipped().is_empty());
+
+        let strict = prm.clone().strict(true);
+        assert!(strict.people().all().is_err());
+        assert_eq!(1, prm.people().all().unwrap().len());
+    }
+}
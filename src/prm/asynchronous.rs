@@ -0,0 +1,167 @@
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use rusqlite::Connection;
+
+use super::{Prm, Stored};
+use crate::entities::activity::Activity;
+use crate::entities::note::Note;
+use crate::entities::person::Person;
+use crate::entities::reminder::Reminder;
+use crate::error::Error;
+
+// An async front for `Prm`. SQLite calls block, so each call runs on tokio's
+// blocking thread pool with a connection from the `Prm` pool, and must be
+// awaited from within a tokio runtime.
+#[derive(Clone)]
+pub struct AsyncPrm {
+    prm: Prm,
+}
+
+// Runs `f` on the blocking pool. A panic in `f` is resumed in the caller.
+async fn blocking<R: Send + 'static>(
+    f: impl FnOnce() -> Result<R, Error> + Send + 'static,
+) -> Result<R, Error> {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(Error::Task { source: e }),
+    }
+}
+
+impl AsyncPrm {
+    pub async fn open(path: impl Into<PathBuf>) -> Result<AsyncPrm, Error> {
+        let path = path.into();
+        let prm = blocking(move || Prm::open(path)).await?;
+        Ok(AsyncPrm { prm })
+    }
+
+    pub async fn open_in_memory() -> Result<AsyncPrm, Error> {
+        let prm = blocking(Prm::open_in_memory).await?;
+        Ok(AsyncPrm { prm })
+    }
+
+    // The synchronous API over the same pool
+    pub fn blocking(&self) -> &Prm {
+        &self.prm
+    }
+
+    // Runs `f` with a pooled connection, for the functions in `db` that
+    // aren't covered by the repositories. `f` adds the context to their
+    // errors, e.g. with `DbSnafu`.
+    pub async fn run<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<R, Error> + Send + 'static,
+    ) -> Result<R, Error> {
+        let prm = self.prm.clone();
+        blocking(move || {
+            let conn = prm.connection()?;
+            f(&conn)
+        })
+        .await
+    }
+
+    pub fn people(&self) -> AsyncRepository<Person> {
+        AsyncRepository::new(self)
+    }
+
+    pub fn activities(&self) -> AsyncRepository<Activity> {
+        AsyncRepository::new(self)
+    }
+
+    pub fn reminders(&self) -> AsyncRepository<Reminder> {
+        AsyncRepository::new(self)
+    }
+
+    pub fn notes(&self) -> AsyncRepository<Note> {
+        AsyncRepository::new(self)
+    }
+}
+
+// The async counterpart of `Repository`. It owns a handle to the pool, so
+// its futures can be spawned.
+pub struct AsyncRepository<T> {
+    prm: Prm,
+    entity: PhantomData<fn() -> T>,
+}
+
+impl<T> AsyncRepository<T> {
+    fn new(prm: &AsyncPrm) -> AsyncRepository<T> {
+        AsyncRepository {
+            prm: prm.prm.clone(),
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<T: Stored + Send + 'static> AsyncRepository<T> {
+    pub async fn get(&self, id: u64) -> Result<Option<T>, Error> {
+        let prm = self.prm.clone();
+        blocking(move || prm.repository::<T>().get(id)).await
+    }
+
+    pub async fn all(&self) -> Result<Vec<T>, Error> {
+        let prm = self.prm.clone();
+        blocking(move || prm.repository::<T>().all()).await
+    }
+
+    pub async fn add(&self, entity: T) -> Result<(), Error> {
+        let prm = self.prm.clone();
+        blocking(move || prm.repository::<T>().add(&entity)).await
+    }
+
+    pub async fn save(&self, entity: T) -> Result<(), Error> {
+        let prm = self.prm.clone();
+        blocking(move || prm.repository::<T>().save(&entity)).await
+    }
+
+    // Moves `entity` to the trash
+    pub async fn remove(&self, entity: T) -> Result<(), Error> {
+        let prm = self.prm.clone();
+        blocking(move || prm.repository::<T>().remove(&entity)).await
+    }
+}
+
+impl AsyncRepository<Person> {
+    pub async fn find_by_name(&self, name: String) -> Result<Vec<Person>, Error> {
+        let prm = self.prm.clone();
+        blocking(move || prm.people().find_by_name(&name)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_interface::DbOperationsError;
+    use crate::error::{DbSnafu, Operation};
+    use chrono::NaiveDate;
+    use snafu::ResultExt;
+
+    #[tokio::test]
+    async fn test_async_repository() {
+        let prm = AsyncPrm::open_in_memory().await.unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        prm.notes()
+            .add(Note::new(0, date, String::from("Lisbon"), vec![]))
+            .await
+            .unwrap();
+
+        let notes = prm.notes().all().await.unwrap();
+        assert_eq!(1, notes.len());
+        let note = prm.notes().get(notes[0].id).await.unwrap().unwrap();
+        assert_eq!("Lisbon", note.content);
+        let count: u64 = prm
+            .run(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM journal", [], |row| row.get(0))
+                    .map_err(|e| DbOperationsError::InvalidStatement { sqlite_error: e })
+                    .context(DbSnafu {
+                        operation: Operation::History,
+                        entity: None,
+                        id: None,
+                    })
+            })
+            .await
+            .unwrap();
+        assert_eq!(1, count);
+    }
+}